@@ -20,5 +20,11 @@ pub use player::PlayerPlugins;
 pub mod plugin;
 pub use plugin::ServerPlugins;
 
+pub mod shutdown;
+pub use shutdown::ShutdownPlugin;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 pub mod world;
 pub use world::WorldPlugins;
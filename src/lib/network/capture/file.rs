@@ -0,0 +1,138 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    time::Duration,
+};
+
+use crate::network::common::StatsState;
+
+/// The bytes at the start of every capture file.
+pub const CAPTURE_HEADER: [u8; 6] = *b"FLCAP\x02";
+
+/// An error that occurred while reading or writing a capture.
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    /// The capture could not be read or written.
+    #[error("Failed to access capture: {0}")]
+    Io(#[from] std::io::Error),
+    /// The capture does not start with the [`CAPTURE_HEADER`].
+    #[error("Not a packet capture, or an unsupported version")]
+    Header,
+    /// A packet has an invalid direction.
+    #[error("Invalid packet direction: {0}")]
+    Direction(u8),
+    /// A packet has an invalid state.
+    #[error("Invalid packet state: {0}")]
+    State(u8),
+    /// The capture is no longer being written.
+    #[error("Capture writer has stopped")]
+    Closed,
+}
+
+/// The direction a captured packet was sent in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureDirection {
+    /// Sent by the server to the client.
+    Clientbound,
+    /// Sent by the client to the server.
+    Serverbound,
+}
+
+/// A packet read from or written to a capture.
+///
+/// Each packet is stored as:
+/// - The time since the capture started, in microseconds (`u64`)
+/// - The [`CaptureDirection`] (`u8`)
+/// - The [`StatsState`] (`u8`)
+/// - The id of the connection (`u64`)
+/// - The length of the packet (`u32`), followed by the packet
+///
+/// All numbers are little-endian.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CapturedPacket {
+    /// The time since the capture started.
+    pub timestamp: Duration,
+    /// The direction the packet was sent in.
+    pub direction: CaptureDirection,
+    /// The state of the connection.
+    pub state: StatsState,
+    /// The id of the connection, unique within the capture.
+    pub connection: u64,
+    /// The encoded packet, including its id.
+    pub data: Vec<u8>,
+}
+
+impl CapturedPacket {
+    /// Write the packet to a capture.
+    ///
+    /// # Errors
+    /// Returns an error if the packet could not be written.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), CaptureError> {
+        let micros = u64::try_from(self.timestamp.as_micros()).unwrap_or(u64::MAX);
+        let length = u32::try_from(self.data.len())
+            .map_err(|_| std::io::Error::from(ErrorKind::InvalidInput))?;
+
+        writer.write_all(&micros.to_le_bytes())?;
+        writer.write_all(&[direction_id(self.direction), state_id(self.state)])?;
+        writer.write_all(&self.connection.to_le_bytes())?;
+        writer.write_all(&length.to_le_bytes())?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    /// Read the next packet from a capture.
+    ///
+    /// Returns `None` at the end of the capture.
+    ///
+    /// # Errors
+    /// Returns an error if the packet could not be read.
+    pub fn read_from(reader: &mut impl Read) -> Result<Option<Self>, CaptureError> {
+        let mut micros = [0; 8];
+        match reader.read_exact(&mut micros) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut ids = [0; 2];
+        reader.read_exact(&mut ids)?;
+        let mut connection = [0; 8];
+        reader.read_exact(&mut connection)?;
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+
+        let mut data = vec![0; u32::from_le_bytes(length) as usize];
+        reader.read_exact(&mut data)?;
+
+        Ok(Some(Self {
+            timestamp: Duration::from_micros(u64::from_le_bytes(micros)),
+            direction: match ids[0] {
+                0 => CaptureDirection::Clientbound,
+                1 => CaptureDirection::Serverbound,
+                other => return Err(CaptureError::Direction(other)),
+            },
+            state: StatsState::ALL
+                .get(usize::from(ids[1]))
+                .copied()
+                .ok_or(CaptureError::State(ids[1]))?,
+            connection: u64::from_le_bytes(connection),
+            data,
+        }))
+    }
+}
+
+const fn direction_id(direction: CaptureDirection) -> u8 {
+    match direction {
+        CaptureDirection::Clientbound => 0,
+        CaptureDirection::Serverbound => 1,
+    }
+}
+
+const fn state_id(state: StatsState) -> u8 {
+    match state {
+        StatsState::Handshake => 0,
+        StatsState::Status => 1,
+        StatsState::Login => 2,
+        StatsState::Configuration => 3,
+        StatsState::Play => 4,
+    }
+}
@@ -0,0 +1,189 @@
+//! TODO
+
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use async_channel::{Receiver, Sender};
+use bevy::prelude::*;
+use froglight::protocol::FrogWrite;
+
+mod file;
+pub use file::{CaptureDirection, CaptureError, CapturedPacket, CAPTURE_HEADER};
+
+mod replay;
+pub use replay::{PacketReplay, ReplayPlugin, ReplayedConnection};
+
+pub mod version;
+pub use version::ReplayTrait;
+
+use super::common::StatsState;
+
+/// A [`Plugin`] that records every packet sent and received
+/// by players to a capture file.
+///
+/// Captures can be replayed using the [`ReplayPlugin`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CapturePlugin {
+    /// The path to write the capture to.
+    pub path: PathBuf,
+}
+
+impl Default for CapturePlugin {
+    fn default() -> Self { Self::new(Self::DEFAULT_PATH) }
+}
+
+impl CapturePlugin {
+    /// The default path of the capture file.
+    pub const DEFAULT_PATH: &'static str = "capture.flcap";
+
+    /// Create a new [`CapturePlugin`] that writes to the given path.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self { Self { path: path.into() } }
+}
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        // Only create a `PacketCapture` if another plugin hasn't already
+        if app.world().contains_resource::<PacketCapture>() {
+            return;
+        }
+
+        match PacketCapture::create(&self.path) {
+            Ok(capture) => {
+                info!("Capturing packets to \"{}\"", self.path.display());
+                app.insert_resource(capture);
+            }
+            Err(err) => {
+                error!("Failed to create \"{}\": {err}", self.path.display());
+            }
+        }
+    }
+
+    fn is_unique(&self) -> bool { false }
+}
+
+/// A capture that packets are recorded to.
+///
+/// Every connection is recorded from the moment it is accepted, including
+/// the handshake and status requests, see [`PacketCapture::connection`].
+///
+/// Packets are written by a dedicated thread,
+/// which flushes the capture whenever it has caught up.
+#[derive(Clone, Resource)]
+pub struct PacketCapture {
+    sender: Sender<CapturedPacket>,
+    next_id: Arc<AtomicU64>,
+    start: Instant,
+}
+
+impl PacketCapture {
+    /// Create a new capture file at the given path.
+    ///
+    /// # Errors
+    /// Returns an error if the file could not be created.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, CaptureError> {
+        Self::from_writer(BufWriter::new(File::create(path)?))
+    }
+
+    /// Create a new capture that writes to the given writer.
+    ///
+    /// # Errors
+    /// Returns an error if the [`CAPTURE_HEADER`] could not be written,
+    /// or the writer thread could not be spawned.
+    pub fn from_writer(mut writer: impl Write + Send + 'static) -> Result<Self, CaptureError> {
+        writer.write_all(&CAPTURE_HEADER)?;
+
+        let (sender, receiver) = async_channel::unbounded();
+        std::thread::Builder::new()
+            .name(String::from("packet-capture"))
+            .spawn(move || Self::write_packets(&receiver, writer))?;
+
+        Ok(Self { sender, next_id: Arc::default(), start: Instant::now() })
+    }
+
+    /// Create a [`ConnectionCapture`] for a new connection.
+    #[must_use]
+    pub fn connection(&self) -> ConnectionCapture {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        ConnectionCapture(Some((self.clone(), id)))
+    }
+
+    /// Record a packet to the capture.
+    ///
+    /// # Errors
+    /// Returns an error if the writer thread has stopped.
+    pub fn record(
+        &self,
+        connection: u64,
+        state: StatsState,
+        direction: CaptureDirection,
+        data: Vec<u8>,
+    ) -> Result<(), CaptureError> {
+        let packet =
+            CapturedPacket { timestamp: self.start.elapsed(), direction, state, connection, data };
+        self.sender.try_send(packet).map_err(|_| CaptureError::Closed)
+    }
+
+    /// Write packets until every [`PacketCapture`] has been dropped,
+    /// flushing the writer whenever no packets are waiting.
+    fn write_packets(receiver: &Receiver<CapturedPacket>, mut writer: impl Write) {
+        while let Ok(packet) = receiver.recv_blocking() {
+            let result = std::iter::once(packet)
+                .chain(std::iter::from_fn(|| receiver.try_recv().ok()))
+                .try_for_each(|packet| packet.write_to(&mut writer))
+                .and_then(|()| Ok(writer.flush()?));
+
+            if let Err(err) = result {
+                error!("Failed to write packet capture: {err}");
+                return;
+            }
+        }
+    }
+}
+
+impl Debug for PacketCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketCapture").field("start", &self.start).finish_non_exhaustive()
+    }
+}
+
+/// Records the packets of a single connection to a [`PacketCapture`].
+///
+/// The default [`ConnectionCapture`] does not record anything.
+#[derive(Debug, Default, Clone, Component)]
+pub struct ConnectionCapture(Option<(PacketCapture, u64)>);
+
+impl ConnectionCapture {
+    /// The id of the connection in the capture,
+    /// or `None` if the connection is not being recorded.
+    #[must_use]
+    pub fn id(&self) -> Option<u64> { self.0.as_ref().map(|(_, id)| *id) }
+
+    /// Record a packet sent to the client.
+    pub fn sent(&self, state: StatsState, packet: &impl FrogWrite) {
+        self.record(state, CaptureDirection::Clientbound, packet);
+    }
+
+    /// Record a packet received from the client.
+    pub fn received(&self, state: StatsState, packet: &impl FrogWrite) {
+        self.record(state, CaptureDirection::Serverbound, packet);
+    }
+
+    /// Record a packet, only encoding it if the connection is being recorded.
+    fn record(&self, state: StatsState, direction: CaptureDirection, packet: &impl FrogWrite) {
+        if let Some((capture, id)) = &self.0 {
+            if let Err(err) = capture.record(*id, state, direction, packet.fg_to_bytes()) {
+                debug!("Failed to capture packet for connection {id}: {err}");
+            }
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufReader, Error as IoError, ErrorKind, Read},
+    marker::PhantomData,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use froglight::{
+    network::connection::{AccountInformation, ConnectionInformation, NetworkDirection},
+    prelude::{State, *},
+};
+
+use super::{CaptureDirection, CaptureError, CapturedPacket, ReplayTrait, CAPTURE_HEADER};
+use crate::network::common::{
+    channel, ChannelSettings, ConnectionTask, NetworkStats, PacketEvent, StatsState,
+};
+
+/// A [`Plugin`] that replays the packets sent by clients in a capture.
+///
+/// Every captured connection is spawned as an [`Entity`] with a
+/// [`ReplayedConnection`], a [`GameProfile`], [`ConnectionInformation`]
+/// and a [`ConnectionTask`] for its current [`State`],
+/// and its packets are sent as [`PacketEvent`]s.
+/// No sockets are created, so packets sent by the server are dropped.
+///
+/// Packets are replayed using a fixed step every frame,
+/// so replays are the same no matter how fast the app runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayPlugin<V: Version> {
+    /// The packets to replay.
+    pub replay: PacketReplay,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> ReplayPlugin<V> {
+    /// Create a new [`ReplayPlugin`] for the given [`PacketReplay`].
+    #[must_use]
+    pub const fn new(replay: PacketReplay) -> Self { Self { replay, _phantom: PhantomData } }
+}
+
+impl<V> Plugin for ReplayPlugin<V>
+where
+    V: Version + ReplayTrait<Login> + ReplayTrait<Configuration> + ReplayTrait<Play>,
+    Clientbound:
+        NetworkDirection<V, Login> + NetworkDirection<V, Configuration> + NetworkDirection<V, Play>,
+    Login: State<V>,
+    Configuration: State<V>,
+    Play: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<PacketEvent<V, Login>>();
+        app.add_event::<PacketEvent<V, Configuration>>();
+        app.add_event::<PacketEvent<V, Play>>();
+        app.insert_resource(self.replay.clone());
+
+        // Add systems
+        app.add_systems(
+            PreUpdate,
+            PacketReplay::replay_packets::<V>
+                .run_if(PacketReplay::is_replaying)
+                .ambiguous_with_all(),
+        );
+    }
+}
+
+/// A [`Component`] for connections spawned by a [`ReplayPlugin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct ReplayedConnection {
+    /// The id of the connection when it was captured.
+    pub captured: u64,
+}
+
+impl ReplayedConnection {
+    /// The socket every replayed connection appears to connect from.
+    pub const SOCKET: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+    /// The username of a replayed connection.
+    #[must_use]
+    pub fn username(&self) -> String { format!("replay{}", self.captured) }
+}
+
+/// The packets sent by clients in a capture, in the order they were sent.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct PacketReplay {
+    packets: VecDeque<CapturedPacket>,
+    entities: HashMap<u64, (Entity, StatsState)>,
+    clock: Duration,
+    step: Duration,
+}
+
+impl PacketReplay {
+    /// The default time replayed every frame.
+    pub const DEFAULT_STEP: Duration = Duration::from_millis(50);
+
+    /// Read a capture from the given path.
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a valid capture.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CaptureError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a capture from the given reader.
+    ///
+    /// Only packets sent by clients are kept.
+    ///
+    /// # Errors
+    /// Returns an error if the data is not a valid capture.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, CaptureError> {
+        let mut header = [0; CAPTURE_HEADER.len()];
+        if reader.read_exact(&mut header).is_err() || header != CAPTURE_HEADER {
+            return Err(CaptureError::Header);
+        }
+
+        let mut packets = VecDeque::new();
+        while let Some(packet) = CapturedPacket::read_from(&mut reader)? {
+            if packet.direction == CaptureDirection::Serverbound {
+                packets.push_back(packet);
+            }
+        }
+
+        Ok(Self {
+            packets,
+            entities: HashMap::new(),
+            clock: Duration::ZERO,
+            step: Self::DEFAULT_STEP,
+        })
+    }
+
+    /// Set the time replayed every frame.
+    #[must_use]
+    pub const fn with_step(mut self, step: Duration) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// The number of packets left to replay.
+    #[must_use]
+    pub fn len(&self) -> usize { self.packets.len() }
+
+    /// Returns `true` if every packet has been replayed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.packets.is_empty() }
+
+    /// Get the [`Entity`] a captured connection was replayed as.
+    #[must_use]
+    pub fn entity(&self, captured: u64) -> Option<Entity> {
+        self.entities.get(&captured).map(|(entity, _)| *entity)
+    }
+
+    /// Advance the replay by one step and
+    /// return the packets sent during it.
+    pub fn advance(&mut self) -> Vec<CapturedPacket> {
+        self.clock += self.step;
+
+        let mut packets = Vec::new();
+        while self.packets.front().is_some_and(|packet| packet.timestamp <= self.clock) {
+            packets.extend(self.packets.pop_front());
+        }
+        packets
+    }
+
+    /// A condition that returns `true` while there are packets to replay.
+    #[must_use]
+    pub fn is_replaying(replay: Option<Res<Self>>) -> bool {
+        replay.is_some_and(|replay| !replay.is_empty())
+    }
+
+    /// A system that sends the packets replayed every frame.
+    ///
+    /// Handshake and status packets are skipped.
+    pub fn replay_packets<V>(
+        mut replay: ResMut<Self>,
+        channels: Option<Res<ChannelSettings>>,
+        mut login: EventWriter<PacketEvent<V, Login>>,
+        mut config: EventWriter<PacketEvent<V, Configuration>>,
+        mut play: EventWriter<PacketEvent<V, Play>>,
+        mut commands: Commands,
+    ) where
+        V: Version + ReplayTrait<Login> + ReplayTrait<Configuration> + ReplayTrait<Play>,
+        Clientbound: NetworkDirection<V, Login>
+            + NetworkDirection<V, Configuration>
+            + NetworkDirection<V, Play>,
+        Login: State<V>,
+        Configuration: State<V>,
+        Play: State<V>,
+    {
+        let capacity = channels.map_or(ChannelSettings::DEFAULT_CAPACITY, |c| c.capacity);
+
+        for CapturedPacket { state, connection: captured, data, .. } in replay.advance() {
+            if matches!(state, StatsState::Handshake | StatsState::Status) {
+                continue;
+            }
+
+            let (entity, previous) = match replay.entities.get(&captured) {
+                Some(&(entity, previous)) => (entity, Some(previous)),
+                None => {
+                    let replayed = ReplayedConnection { captured };
+                    let username = replayed.username();
+                    let entity = commands
+                        .spawn((
+                            replayed,
+                            GameProfile {
+                                uuid: AccountInformation::offline_uuid(&username),
+                                username,
+                                properties: HashMap::new(),
+                            },
+                            ConnectionInformation {
+                                address: None,
+                                socket: ReplayedConnection::SOCKET,
+                            },
+                            NetworkStats::default(),
+                        ))
+                        .id();
+                    debug!("Replaying connection {captured} as Entity {entity}");
+                    (entity, None)
+                }
+            };
+
+            // Give the connection a task for its current state
+            if previous != Some(state) {
+                commands.entity(entity).queue(replace_task::<V>(previous, state, capacity));
+                replay.entities.insert(captured, (entity, state));
+            }
+
+            let sent = match state {
+                StatsState::Login => <V as ReplayTrait<Login>>::decode(&data)
+                    .map(|packet| login.send(PacketEvent::new(entity, Arc::new(packet)))),
+                StatsState::Configuration => <V as ReplayTrait<Configuration>>::decode(&data)
+                    .map(|packet| config.send(PacketEvent::new(entity, Arc::new(packet)))),
+                StatsState::Play => <V as ReplayTrait<Play>>::decode(&data)
+                    .map(|packet| play.send(PacketEvent::new(entity, Arc::new(packet)))),
+                StatsState::Handshake | StatsState::Status => continue,
+            };
+
+            if sent.is_none() {
+                warn!("Failed to decode replayed {state:?} packet for Entity {entity}");
+            }
+        }
+    }
+}
+
+/// Replace the [`ConnectionTask`] of a replayed connection
+/// with one for the given [`StatsState`].
+fn replace_task<V: Version>(
+    previous: Option<StatsState>,
+    state: StatsState,
+    capacity: usize,
+) -> impl FnOnce(Entity, &mut World) + Send + 'static
+where
+    Clientbound:
+        NetworkDirection<V, Login> + NetworkDirection<V, Configuration> + NetworkDirection<V, Play>,
+    Login: State<V>,
+    Configuration: State<V>,
+    Play: State<V>,
+{
+    move |entity: Entity, world: &mut World| {
+        let mut entity = world.entity_mut(entity);
+        let stats = entity.get::<NetworkStats>().cloned().unwrap_or_default();
+
+        match previous {
+            Some(StatsState::Login) => {
+                entity.remove::<ConnectionTask<V, Login>>();
+            }
+            Some(StatsState::Configuration) => {
+                entity.remove::<ConnectionTask<V, Configuration>>();
+            }
+            Some(StatsState::Play) => {
+                entity.remove::<ConnectionTask<V, Play>>();
+            }
+            _ => {}
+        }
+
+        match state {
+            StatsState::Login => {
+                entity.insert(replay_task::<V, Login>(capacity, &stats, state));
+            }
+            StatsState::Configuration => {
+                entity.insert(replay_task::<V, Configuration>(capacity, &stats, state));
+            }
+            StatsState::Play => {
+                entity.insert(replay_task::<V, Play>(capacity, &stats, state));
+            }
+            StatsState::Handshake | StatsState::Status => {}
+        }
+    }
+}
+
+/// Create a [`ConnectionTask`] for a replayed connection.
+///
+/// Packets sent by the server are dropped,
+/// and the task ends once the [`ConnectionTask`] is dropped.
+fn replay_task<V: Version, S: State<V>>(
+    capacity: usize,
+    stats: &NetworkStats,
+    state: StatsState,
+) -> ConnectionTask<V, S>
+where
+    Clientbound: NetworkDirection<V, S>,
+{
+    let (send, recv) = channel(capacity, stats.counter(state));
+    ConnectionTask::spawn(send, async move {
+        while recv.recv().await.is_ok() {}
+        Err(IoError::from(ErrorKind::ConnectionAborted).into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{core::TaskPoolPlugin, prelude::*};
+    use froglight::{
+        network::{connection::ConnectionInformation, versions::v1_21_0::V1_21_0},
+        prelude::*,
+    };
+
+    use super::{PacketReplay, ReplayPlugin, ReplayedConnection};
+    use crate::{
+        network::{
+            capture::{CaptureDirection, CaptureError, CapturedPacket, CAPTURE_HEADER},
+            common::{ConnectionTask, StatsState},
+        },
+        testing::{TestClient, TestServer},
+        ServerPlugins,
+    };
+
+    fn packet(millis: u64, direction: CaptureDirection, data: &[u8]) -> CapturedPacket {
+        CapturedPacket {
+            timestamp: Duration::from_millis(millis),
+            direction,
+            state: StatsState::Play,
+            connection: 7,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn replay() {
+        let mut capture = CAPTURE_HEADER.to_vec();
+        for packet in [
+            packet(10, CaptureDirection::Serverbound, &[0, 1]),
+            packet(20, CaptureDirection::Clientbound, &[2]),
+            packet(40, CaptureDirection::Serverbound, &[3, 4, 5]),
+            packet(120, CaptureDirection::Serverbound, &[]),
+        ] {
+            packet.write_to(&mut capture).unwrap();
+        }
+
+        // Only packets sent by the client are kept
+        let mut replay = PacketReplay::from_reader(capture.as_slice()).unwrap();
+        assert_eq!(replay.len(), 3);
+
+        // Packets are replayed in fixed steps
+        let first = replay.advance();
+        assert_eq!(
+            first,
+            [
+                packet(10, CaptureDirection::Serverbound, &[0, 1]),
+                packet(40, CaptureDirection::Serverbound, &[3, 4, 5])
+            ]
+        );
+        assert!(replay.advance().is_empty());
+        assert_eq!(replay.advance().len(), 1);
+        assert!(replay.is_empty());
+
+        assert!(matches!(PacketReplay::from_reader(&b"FLCAP"[..]), Err(CaptureError::Header)));
+    }
+
+    /// Capture a client from the handshake onwards,
+    /// then replay it as a connection with a profile and a task.
+    #[test]
+    fn capture_and_replay() {
+        let path = std::env::temp_dir().join(format!("replay-{}.flcap", std::process::id()));
+        let plugins = ServerPlugins::localhost().with_capture(&path);
+        let mut server = TestServer::from_plugins(plugins);
+        server
+            .run(|address| async move { TestClient::new(address, "Player").join().await })
+            .unwrap();
+
+        // Wait for the writer to catch up
+        let captured = |replay: &PacketReplay, state: StatsState| {
+            replay.packets.iter().any(|packet| packet.connection == 0 && packet.state == state)
+        };
+        assert!(server.update_until(|_| {
+            PacketReplay::open(&path)
+                .is_ok_and(|replay| captured(&replay, StatsState::Configuration))
+        }));
+
+        let replay = PacketReplay::open(&path).unwrap().with_step(Duration::from_secs(60));
+        std::fs::remove_file(&path).unwrap();
+        assert!(captured(&replay, StatsState::Handshake), "The handshake was not captured");
+
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), ReplayPlugin::<V1_21_0>::new(replay)));
+        app.update();
+        app.update();
+
+        let entity = app.world().resource::<PacketReplay>().entity(0).unwrap();
+        let entity = app.world().entity(entity);
+        assert_eq!(entity.get::<ReplayedConnection>(), Some(&ReplayedConnection { captured: 0 }));
+        assert_eq!(entity.get::<GameProfile>().unwrap().username, "replay0");
+        assert_eq!(
+            entity.get::<ConnectionInformation>().unwrap().socket,
+            ReplayedConnection::SOCKET
+        );
+        assert!(!entity.contains::<ConnectionTask<V1_21_0, Login>>());
+        assert!(entity.contains::<ConnectionTask<V1_21_0, Configuration>>());
+    }
+}
@@ -0,0 +1,14 @@
+use froglight::prelude::*;
+
+mod v1_21_0;
+
+/// A trait that defines how captured packets are decoded in a [`State`].
+pub trait ReplayTrait<S>: Version
+where
+    S: State<Self>,
+{
+    /// Decode a captured serverbound packet.
+    ///
+    /// Returns `None` if the packet is invalid.
+    fn decode(data: &[u8]) -> Option<<S as State<Self>>::ServerboundPacket>;
+}
@@ -0,0 +1,30 @@
+use std::io::Cursor;
+
+use froglight::{
+    network::versions::v1_21_0::{
+        configuration::ConfigurationServerboundPackets, login::LoginServerboundPackets,
+        play::PlayServerboundPackets, V1_21_0,
+    },
+    prelude::*,
+    protocol::FrogRead,
+};
+
+use super::ReplayTrait;
+
+impl ReplayTrait<Login> for V1_21_0 {
+    fn decode(data: &[u8]) -> Option<LoginServerboundPackets> {
+        LoginServerboundPackets::fg_read(&mut Cursor::new(data)).ok()
+    }
+}
+
+impl ReplayTrait<Configuration> for V1_21_0 {
+    fn decode(data: &[u8]) -> Option<ConfigurationServerboundPackets> {
+        ConfigurationServerboundPackets::fg_read(&mut Cursor::new(data)).ok()
+    }
+}
+
+impl ReplayTrait<Play> for V1_21_0 {
+    fn decode(data: &[u8]) -> Option<PlayServerboundPackets> {
+        PlayServerboundPackets::fg_read(&mut Cursor::new(data)).ok()
+    }
+}
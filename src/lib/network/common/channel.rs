@@ -1,37 +1,69 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use async_channel::{
-    Receiver, Recv as RecvFut, Send as SendFut, Sender, TryRecvError, TrySendError,
+use async_channel::{Receiver, RecvError, Send as SendFut, Sender, TryRecvError, TrySendError};
+use bevy::{
+    log::debug,
+    prelude::{Component, Resource},
 };
 use froglight::prelude::{State, *};
 
+use super::StatsCounter;
+
 type SentPacket<V, S> = <S as State<V>>::ClientboundPacket;
 type RecvPacket<V, S> = <S as State<V>>::ServerboundPacket;
 
+/// A packet to the client, along with the
+/// [`PacketChannel::clear_outbound`] generation it was sent in.
+type Outbound<V, S> = (u64, Arc<SentPacket<V, S>>);
+
 /// Create a new [`PacketChannel`] and [`AsyncPacketChannel`] pair.
+///
+/// Packets from the client are limited to `capacity` packets.
+/// Packets to the client are never dropped, instead `capacity` is a soft
+/// limit that marks the channel as full, see [`PacketChannel::is_full`].
+///
+/// Packets are counted using the [`StatsCounter`].
 #[must_use]
-pub fn channel<V: Version, S: State<V>>() -> (PacketChannel<V, S>, AsyncPacketChannel<V, S>) {
+pub fn channel<V: Version, S: State<V>>(
+    capacity: usize,
+    stats: StatsCounter,
+) -> (PacketChannel<V, S>, AsyncPacketChannel<V, S>) {
     let (p_send, p_recv) = async_channel::unbounded();
-    let (a_send, a_recv) = async_channel::unbounded();
+    let (a_send, a_recv) = async_channel::bounded(capacity);
+    let cleared = Arc::new(AtomicU64::new(0));
     (
-        PacketChannel { send: p_send, recv: a_recv },
-        AsyncPacketChannel { send: a_send, recv: p_recv },
+        PacketChannel { send: p_send, recv: a_recv, cleared: cleared.clone(), capacity },
+        AsyncPacketChannel { send: a_send, recv: p_recv, cleared, stats },
     )
 }
 
 /// A channel for sending and receiving packets.
 pub struct PacketChannel<V: Version, S: State<V>> {
-    send: Sender<Arc<SentPacket<V, S>>>,
+    send: Sender<Outbound<V, S>>,
     recv: Receiver<Arc<RecvPacket<V, S>>>,
+    cleared: Arc<AtomicU64>,
+    capacity: usize,
 }
 
 impl<V: Version, S: State<V>> PacketChannel<V, S> {
     /// Send a packet to the other side of the channel.
+    ///
+    /// Packets are only dropped if the channel has been closed.
     pub fn send(&self, packet: impl Into<SentPacket<V, S>>) {
-        let _ = self.try_send(Arc::new(packet.into()));
+        self.send_arc(Arc::new(packet.into()));
     }
     /// Send a packet to the other side of the channel.
-    pub fn send_arc(&self, packet: Arc<SentPacket<V, S>>) { let _ = self.try_send(packet); }
+    pub fn send_arc(&self, packet: Arc<SentPacket<V, S>>) {
+        if let Err(err) = self.try_send(packet) {
+            debug!("Failed to send packet: {err}");
+        }
+    }
     /// Try to send a packet to the other side of the channel.
     ///
     /// # Errors
@@ -40,7 +72,24 @@ impl<V: Version, S: State<V>> PacketChannel<V, S> {
         &self,
         packet: Arc<SentPacket<V, S>>,
     ) -> Result<(), TrySendError<Arc<SentPacket<V, S>>>> {
-        self.send.try_send(packet)
+        let generation = self.cleared.load(Ordering::Acquire);
+        self.send.try_send((generation, packet)).map_err(|err| match err {
+            TrySendError::Full((_, packet)) => TrySendError::Full(packet),
+            TrySendError::Closed((_, packet)) => TrySendError::Closed(packet),
+        })
+    }
+
+    /// Drop all packets waiting to be sent to the client.
+    ///
+    /// The packets are skipped by the task instead of being sent,
+    /// so they are still counted by [`PacketChannel::outbound_len`]
+    /// until the task reaches them.
+    ///
+    /// Returns the number of packets dropped.
+    pub fn clear_outbound(&self) -> usize {
+        let queued = self.send.len();
+        self.cleared.fetch_add(1, Ordering::AcqRel);
+        queued
     }
 
     /// Receive a packet from the other side of the channel.
@@ -51,6 +100,20 @@ impl<V: Version, S: State<V>> PacketChannel<V, S> {
     /// # Errors
     /// Returns an error if the channel has been closed.
     pub fn try_recv(&self) -> Result<Arc<RecvPacket<V, S>>, TryRecvError> { self.recv.try_recv() }
+
+    /// The number of packets waiting to be sent to the client.
+    #[must_use]
+    pub fn outbound_len(&self) -> usize { self.send.len() }
+
+    /// The number of packets received from the client
+    /// waiting to be handled.
+    #[must_use]
+    pub fn inbound_len(&self) -> usize { self.recv.len() }
+
+    /// Returns `true` if the packets waiting to be sent
+    /// to the client are over the channel's capacity.
+    #[must_use]
+    pub fn is_full(&self) -> bool { self.send.len() >= self.capacity }
 }
 
 /// An asynchronous version of [`PacketChannel`].
@@ -58,7 +121,9 @@ impl<V: Version, S: State<V>> PacketChannel<V, S> {
 /// Used inside of an async task to send and receive packets.
 pub struct AsyncPacketChannel<V: Version, S: State<V>> {
     send: Sender<Arc<RecvPacket<V, S>>>,
-    recv: Receiver<Arc<SentPacket<V, S>>>,
+    recv: Receiver<Outbound<V, S>>,
+    cleared: Arc<AtomicU64>,
+    stats: StatsCounter,
 }
 
 impl<V: Version, S: State<V>> AsyncPacketChannel<V, S> {
@@ -68,5 +133,170 @@ impl<V: Version, S: State<V>> AsyncPacketChannel<V, S> {
     }
 
     /// Receive a packet from the other side of the channel.
-    pub fn recv(&self) -> RecvFut<'_, Arc<SentPacket<V, S>>> { self.recv.recv() }
+    ///
+    /// Packets sent before the last [`PacketChannel::clear_outbound`]
+    /// are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the [`PacketChannel`] has been dropped
+    /// and every packet has been received.
+    pub async fn recv(&self) -> Result<Arc<SentPacket<V, S>>, RecvError> {
+        loop {
+            let (generation, packet) = self.recv.recv().await?;
+            if generation >= self.cleared.load(Ordering::Acquire) {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// The [`StatsCounter`] for packets sent and received by the task.
+    #[must_use]
+    pub fn stats(&self) -> &StatsCounter { &self.stats }
+}
+
+/// Settings for the [`PacketChannel`]s of new connections.
+///
+/// Set using [`NetworkPlugins::with_channels`](crate::network::NetworkPlugins::with_channels),
+/// or insert before adding the network plugins to change the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct ChannelSettings {
+    /// The number of packets that can be queued in each direction.
+    ///
+    /// Packets to the client are never dropped,
+    /// clients over this limit are marked as full instead.
+    pub capacity: usize,
+    /// How long a client can stay over its outbound
+    /// limit before it is disconnected.
+    pub slow_timeout: Duration,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self { capacity: Self::DEFAULT_CAPACITY, slow_timeout: Self::DEFAULT_SLOW_TIMEOUT }
+    }
+}
+
+impl ChannelSettings {
+    /// The default maximum number of packets queued in each direction.
+    pub const DEFAULT_CAPACITY: usize = 4096;
+    /// The default time a client can stay over its outbound limit.
+    pub const DEFAULT_SLOW_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// The reason given to clients disconnected for being too slow.
+    pub const SLOW_REASON: &'static str = "Connection too slow";
+
+    /// Set the number of packets that can be queued in each direction.
+    #[must_use]
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set how long a client can stay over its outbound limit.
+    #[must_use]
+    pub const fn with_slow_timeout(mut self, timeout: Duration) -> Self {
+        self.slow_timeout = timeout;
+        self
+    }
+}
+
+/// The number of packets queued for a connection.
+///
+/// Updated every frame while the connection has a task.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct QueueDepth {
+    /// The number of packets waiting to be sent to the client.
+    pub outbound: usize,
+    /// The number of packets received from the client
+    /// waiting to be handled.
+    pub inbound: usize,
+    full_since: Option<Instant>,
+}
+
+impl QueueDepth {
+    /// The instant the outbound queue became full, if it is full.
+    #[must_use]
+    pub const fn full_since(&self) -> Option<Instant> { self.full_since }
+
+    /// Update the queue depth, keeping track of how long the
+    /// outbound queue has been full.
+    #[must_use]
+    pub fn update(previous: Option<&Self>, outbound: usize, inbound: usize, full: bool) -> Self {
+        let full_since = if full {
+            previous.and_then(|depth| depth.full_since).or_else(|| Some(Instant::now()))
+        } else {
+            None
+        };
+        Self { outbound, inbound, full_since }
+    }
+
+    /// Returns `true` if the outbound queue has been full for at least the
+    /// given duration.
+    #[must_use]
+    pub fn is_slow(&self, timeout: Duration) -> bool {
+        self.full_since.is_some_and(|since| since.elapsed() >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use async_channel::TrySendError;
+    use froglight::{
+        network::versions::v1_21_0::{
+            play::{KeepAliveS2CPacket, PlayClientboundPackets},
+            V1_21_0,
+        },
+        prelude::Play,
+    };
+
+    use super::{channel, QueueDepth};
+    use crate::network::common::{NetworkStats, StatsState};
+
+    #[test]
+    fn soft_limit() {
+        let stats = NetworkStats::default();
+        let (channel, task) = channel::<V1_21_0, Play>(2, stats.counter(StatsState::Play));
+
+        // Packets over the capacity are queued instead of dropped
+        for id in 0..4 {
+            channel.send(KeepAliveS2CPacket { id });
+        }
+        assert_eq!(channel.outbound_len(), 4);
+        assert!(channel.is_full());
+
+        // Clearing the queue drops every packet,
+        // and packets sent afterwards are still received
+        assert_eq!(channel.clear_outbound(), 4);
+        channel.send(KeepAliveS2CPacket { id: 7 });
+        let packet = bevy::tasks::block_on(task.recv()).unwrap();
+        assert!(matches!(*packet, PlayClientboundPackets::KeepAlive(KeepAliveS2CPacket { id: 7 })));
+        assert_eq!(channel.outbound_len(), 0);
+        assert!(!channel.is_full());
+
+        // Once the task is gone the channel is closed
+        drop(task);
+        let packet = Arc::new(KeepAliveS2CPacket { id: 8 }.into());
+        assert!(matches!(channel.try_send(packet), Err(TrySendError::Closed(..))));
+    }
+
+    #[test]
+    fn slow_clients() {
+        let depth = QueueDepth::update(None, 10, 0, false);
+        assert_eq!(depth.full_since(), None);
+        assert!(!depth.is_slow(Duration::ZERO));
+
+        // The instant is kept while the queue stays full
+        let full = QueueDepth::update(Some(&depth), 16, 0, true);
+        let since = full.full_since().unwrap();
+        let still = QueueDepth::update(Some(&full), 16, 2, true);
+        assert_eq!(still.full_since(), Some(since));
+        assert!(still.is_slow(Duration::ZERO));
+        assert!(!still.is_slow(Duration::from_secs(60)));
+
+        // And reset once it has room again
+        let drained = QueueDepth::update(Some(&still), 3, 0, false);
+        assert_eq!(drained.full_since(), None);
+    }
 }
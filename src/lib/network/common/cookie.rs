@@ -0,0 +1,280 @@
+use bevy::{prelude::*, utils::HashMap};
+use froglight::{
+    network::connection::NetworkDirection,
+    prelude::{State, *},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{ConnectionTask, PacketEvent};
+
+/// A trait that defines how cookies are sent and received in a [`State`].
+pub trait CookieTrait<S>: Version
+where
+    Clientbound: NetworkDirection<Self, S>,
+    S: State<Self>,
+{
+    /// Create a packet that asks the client for a cookie.
+    fn request_packet(key: ResourceKey) -> <S as State<Self>>::ClientboundPacket;
+
+    /// Create a packet that stores a cookie on the client.
+    ///
+    /// Returns `None` if cookies cannot be stored in this [`State`].
+    fn store_packet(
+        key: ResourceKey,
+        payload: Vec<u8>,
+    ) -> Option<<S as State<Self>>::ClientboundPacket>;
+
+    /// Get the key and payload of a cookie response.
+    ///
+    /// Returns `None` if the packet is not a response.
+    fn cookie_response(
+        packet: &<S as State<Self>>::ServerboundPacket,
+    ) -> Option<(ResourceKey, Option<Vec<u8>>)>;
+}
+
+/// A typed cookie stored on the client.
+///
+/// Cookies are stored as JSON, and are kept by
+/// the client when it is transferred to another server.
+///
+/// Cookies are not signed, so clients can send any payload.
+/// Never trust a cookie to prove who a player is or where they came from.
+pub trait Cookie: Serialize + DeserializeOwned {
+    /// The key the cookie is stored under.
+    const KEY: &'static str;
+
+    /// The [`ResourceKey`] the cookie is stored under.
+    #[must_use]
+    fn key() -> ResourceKey { ResourceKey::const_new(Self::KEY) }
+}
+
+/// An error that occurred while encoding or decoding a [`Cookie`].
+#[derive(Debug, thiserror::Error)]
+pub enum CookieError {
+    /// The cookie is larger than [`Cookies::MAX_LENGTH`].
+    #[error("Cookie is too large: {0} bytes")]
+    TooLarge(usize),
+    /// The cookie could not be encoded or decoded.
+    #[error("Invalid cookie: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The cookies received from a client.
+///
+/// The payloads are sent by the client and cannot be trusted.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct Cookies {
+    received: HashMap<ResourceKey, Option<Vec<u8>>>,
+}
+
+impl Cookies {
+    /// The maximum size of a cookie's payload.
+    pub const MAX_LENGTH: usize = 5120;
+
+    /// Get and decode a received [`Cookie`].
+    ///
+    /// Returns `None` if the cookie has not been
+    /// received or the client did not have it.
+    ///
+    /// The client may have changed the cookie, so treat it as untrusted input.
+    #[must_use]
+    pub fn get<C: Cookie>(&self) -> Option<Result<C, CookieError>> {
+        self.get_raw(&C::key()).map(|payload| Ok(serde_json::from_slice(payload)?))
+    }
+
+    /// Get the payload of a received cookie.
+    #[must_use]
+    pub fn get_raw(&self, key: &ResourceKey) -> Option<&[u8]> {
+        self.received.get(key).and_then(Option::as_deref)
+    }
+
+    /// Returns `true` if the client has responded to a request for the
+    /// [`Cookie`], even if it did not have it.
+    #[must_use]
+    pub fn is_received<C: Cookie>(&self) -> bool { self.received.contains_key(&C::key()) }
+
+    /// Insert a received cookie.
+    pub fn insert(&mut self, key: ResourceKey, payload: Option<Vec<u8>>) {
+        self.received.insert(key, payload);
+    }
+}
+
+/// An [`Event`] that asks a client for a cookie.
+///
+/// The response is added to the client's [`Cookies`],
+/// and a [`CookieReceived`] event is sent.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct RequestCookie {
+    /// The player to ask.
+    pub entity: Entity,
+    /// The key of the cookie.
+    pub key: ResourceKey,
+}
+
+impl RequestCookie {
+    /// Create a new [`RequestCookie`] event for a [`Cookie`].
+    #[must_use]
+    pub fn new<C: Cookie>(entity: Entity) -> Self { Self { entity, key: C::key() } }
+}
+
+/// An [`Event`] that stores a cookie on a client.
+///
+/// Cookies cannot be stored while logging in,
+/// so they are sent once the client is configuring.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct StoreCookie {
+    /// The player to store the cookie on.
+    pub entity: Entity,
+    /// The key of the cookie.
+    pub key: ResourceKey,
+    /// The payload of the cookie.
+    pub payload: Vec<u8>,
+}
+
+impl StoreCookie {
+    /// Create a new [`StoreCookie`] event for a [`Cookie`].
+    ///
+    /// # Errors
+    /// Returns an error if the cookie could not be encoded
+    /// or is larger than [`Cookies::MAX_LENGTH`].
+    pub fn new<C: Cookie>(entity: Entity, cookie: &C) -> Result<Self, CookieError> {
+        let payload = serde_json::to_vec(cookie)?;
+        if payload.len() > Cookies::MAX_LENGTH {
+            return Err(CookieError::TooLarge(payload.len()));
+        }
+        Ok(Self { entity, key: C::key(), payload })
+    }
+}
+
+/// An [`Event`] that is sent when a client responds to a [`RequestCookie`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct CookieReceived {
+    /// The player that responded.
+    pub entity: Entity,
+    /// The key of the cookie.
+    pub key: ResourceKey,
+}
+
+/// Cookies waiting for a [`State`] they can be stored in.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component, Deref, DerefMut)]
+#[component(storage = "SparseSet")]
+pub struct PendingCookies(Vec<(ResourceKey, Vec<u8>)>);
+
+impl<V: Version + CookieTrait<S>, S: State<V>> ConnectionTask<V, S>
+where
+    Clientbound: NetworkDirection<V, S>,
+{
+    /// A system that sends a request for
+    /// every [`RequestCookie`] event received.
+    pub fn request_cookies(query: Query<&Self>, mut events: EventReader<RequestCookie>) {
+        for RequestCookie { entity, key } in events.read() {
+            if let Ok(task) = query.get(*entity) {
+                trace!("Requesting cookie \"{key}\" from Entity {entity}");
+                task.send(V::request_packet(key.clone()));
+            }
+        }
+    }
+
+    /// A system that stores a cookie for every [`StoreCookie`] event received.
+    ///
+    /// Cookies that cannot be stored yet are kept as [`PendingCookies`].
+    pub fn store_cookies(
+        query: Query<&Self>,
+        mut pending: Query<&mut PendingCookies>,
+        mut events: EventReader<StoreCookie>,
+        mut commands: Commands,
+    ) {
+        for StoreCookie { entity, key, payload } in events.read() {
+            let Ok(task) = query.get(*entity) else { continue };
+
+            if let Some(packet) = V::store_packet(key.clone(), payload.clone()) {
+                trace!("Storing cookie \"{key}\" on Entity {entity}");
+                task.send(packet);
+            } else if let Ok(mut pending) = pending.get_mut(*entity) {
+                pending.push((key.clone(), payload.clone()));
+            } else {
+                let cookie = (key.clone(), payload.clone());
+                commands.entity(*entity).insert(PendingCookies(vec![cookie]));
+            }
+        }
+    }
+
+    /// A system that stores [`PendingCookies`] once possible.
+    pub fn store_pending(
+        mut query: Query<(Entity, &Self, &mut PendingCookies)>,
+        mut commands: Commands,
+    ) {
+        for (entity, task, mut pending) in &mut query {
+            pending.retain(|(key, payload)| match V::store_packet(key.clone(), payload.clone()) {
+                Some(packet) => {
+                    trace!("Storing cookie \"{key}\" on Entity {entity}");
+                    task.send(packet);
+                    false
+                }
+                None => true,
+            });
+
+            if pending.is_empty() {
+                commands.entity(entity).remove::<PendingCookies>();
+            }
+        }
+    }
+
+    /// A system that adds cookie responses to the client's [`Cookies`].
+    pub fn receive_cookies(
+        mut query: Query<&mut Cookies>,
+        mut packets: EventReader<PacketEvent<V, S>>,
+        mut events: EventWriter<CookieReceived>,
+    ) {
+        for PacketEvent { entity, packet } in packets.read() {
+            let Some((key, payload)) = V::cookie_response(packet) else { continue };
+            if let Ok(mut cookies) = query.get_mut(*entity) {
+                trace!("Received cookie \"{key}\" from Entity {entity}");
+                cookies.insert(key.clone(), payload);
+                events.send(CookieReceived { entity: *entity, key });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Cookie, CookieError, Cookies, StoreCookie};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Visit {
+        server: String,
+    }
+
+    impl Cookie for Visit {
+        const KEY: &'static str = "froglight:visit";
+    }
+
+    #[test]
+    fn typed_cookies() {
+        let visit = Visit { server: String::from("lobby") };
+        let store = StoreCookie::new(Entity::PLACEHOLDER, &visit).unwrap();
+
+        let mut cookies = Cookies::default();
+        assert!(!cookies.is_received::<Visit>());
+        assert!(cookies.get::<Visit>().is_none());
+
+        cookies.insert(store.key, Some(store.payload));
+        assert!(cookies.is_received::<Visit>());
+        assert_eq!(cookies.get::<Visit>().unwrap().unwrap(), visit);
+
+        // Clients without the cookie respond with no payload
+        cookies.insert(Visit::key(), None);
+        assert!(cookies.is_received::<Visit>());
+        assert!(cookies.get::<Visit>().is_none());
+
+        let large = Visit { server: "a".repeat(Cookies::MAX_LENGTH) };
+        assert!(matches!(
+            StoreCookie::new(Entity::PLACEHOLDER, &large),
+            Err(CookieError::TooLarge(..))
+        ));
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{prelude::*, tasks::IoTaskPool};
+use compact_str::CompactString;
+use froglight::{
+    network::connection::NetworkDirection,
+    prelude::{State, *},
+};
+
+use super::{ChannelSettings, ConnectionFilter, ConnectionTask, FilterResult, QueueDepth};
+use crate::{
+    dimension::subapp::DimensionMarker,
+    shutdown::{ShutdownSettings, ShutdownTasks},
+};
+
+/// A trait that defines how clients are disconnected in a [`State`].
+pub trait DisconnectTrait<S>: Version
+where
+    Clientbound: NetworkDirection<Self, S>,
+    S: State<Self>,
+{
+    /// Create a disconnect packet with the given reason.
+    fn disconnect_packet(reason: &str) -> <S as State<Self>>::ClientboundPacket;
+}
+
+/// The reason sent to clients when a filter denies them without one.
+pub const DEFAULT_REASON: &str = "Denied by server";
+
+/// An [`Event`] that disconnects a player with the given reason.
+///
+/// Works in any [`State`] that has a [`ConnectionTask`],
+/// sending the matching disconnect packet before despawning
+/// the entity and any linked entity in a [`SubApp`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct DisconnectPlayer {
+    /// The player to disconnect.
+    pub entity: Entity,
+    /// The reason shown to the player.
+    pub reason: CompactString,
+}
+
+impl DisconnectPlayer {
+    /// Create a new [`DisconnectPlayer`] event.
+    #[must_use]
+    pub fn new(entity: Entity, reason: impl Into<CompactString>) -> Self {
+        Self { entity, reason: reason.into() }
+    }
+}
+
+/// Send a disconnect packet with the given reason and close the
+/// [`Connection`].
+///
+/// The packet is sent in the background using the [`IoTaskPool`].
+pub fn disconnect<V: DisconnectTrait<S>, S: State<V>>(
+    mut conn: Connection<V, S, Clientbound>,
+    reason: &str,
+) where
+    Clientbound: NetworkDirection<V, S>,
+{
+    let packet = V::disconnect_packet(reason);
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(err) = conn.send_packet(&packet).await {
+                debug!("Failed to send disconnect packet: {err}");
+            }
+        })
+        .detach();
+}
+
+impl<V: Version + DisconnectTrait<S>, S: State<V>> ConnectionFilter<V, S>
+where
+    Clientbound: NetworkDirection<V, S>,
+{
+    /// A system that checks all connections against the [`ConnectionFilter`]
+    /// and disconnects any that are denied.
+    pub fn filter_tasks(
+        query: Query<(Entity, &GameProfile), With<ConnectionTask<V, S>>>,
+        filter: Res<Self>,
+        world: &World,
+        mut commands: Commands,
+    ) {
+        for (entity, profile) in &query {
+            if let FilterResult::Deny(reason) = filter.check(entity, world) {
+                let reason = reason.unwrap_or_else(|| DEFAULT_REASON.into());
+                warn!("Disconnecting {}: {reason}", profile.username);
+
+                commands.entity(entity).queue(disconnect_entity::<V, S>(reason));
+            }
+        }
+    }
+}
+
+impl<V: Version + DisconnectTrait<S>, S: State<V>> ConnectionTask<V, S>
+where
+    Clientbound: NetworkDirection<V, S>,
+{
+    /// A system that disconnects all players when the server shuts down.
+    pub fn shutdown_tasks(
+        query: Query<Entity, With<Self>>,
+        settings: Res<ShutdownSettings>,
+        mut commands: Commands,
+    ) {
+        for entity in &query {
+            commands.entity(entity).queue(disconnect_entity::<V, S>(settings.message.clone()));
+        }
+    }
+
+    /// A system that updates the [`QueueDepth`] of every connection.
+    ///
+    /// Clients that stay over their outbound limit for longer than the
+    /// [`ChannelSettings::slow_timeout`] have their queued packets dropped
+    /// and are disconnected with the [`ChannelSettings::SLOW_REASON`].
+    pub fn check_queues(
+        query: Query<(Entity, &GameProfile, &Self, Option<&QueueDepth>)>,
+        settings: Res<ChannelSettings>,
+        mut commands: Commands,
+    ) {
+        for (entity, profile, task, previous) in &query {
+            let depth = QueueDepth::update(
+                previous,
+                task.outbound_len(),
+                task.inbound_len(),
+                task.is_full(),
+            );
+
+            if depth.is_slow(settings.slow_timeout) {
+                warn!("Disconnecting {}: {}", profile.username, ChannelSettings::SLOW_REASON);
+                debug!("Dropped {} packets queued for Entity {entity}", task.clear_outbound());
+                commands
+                    .entity(entity)
+                    .queue(disconnect_entity::<V, S>(ChannelSettings::SLOW_REASON.into()));
+            } else if previous != Some(&depth) {
+                commands.entity(entity).insert(depth);
+            }
+        }
+    }
+
+    /// A system that disconnects players for
+    /// every [`DisconnectPlayer`] event received.
+    pub fn disconnect_players(
+        query: Query<&GameProfile, With<Self>>,
+        mut events: EventReader<DisconnectPlayer>,
+        mut commands: Commands,
+    ) {
+        for DisconnectPlayer { entity, reason } in events.read() {
+            if let Ok(profile) = query.get(*entity) {
+                info!("Disconnecting {}: {reason}", profile.username);
+                commands.entity(*entity).queue(disconnect_entity::<V, S>(reason.clone()));
+            }
+        }
+    }
+}
+
+/// Disconnect the [`ConnectionTask`] of an entity and despawn it.
+///
+/// If there are [`ShutdownTasks`], the closing connection is added to them.
+///
+/// The [`DimensionMarker`] is removed first,
+/// which despawns the linked entity in the [`SubApp`].
+fn disconnect_entity<V: Version + DisconnectTrait<S>, S: State<V>>(
+    reason: CompactString,
+) -> impl FnOnce(Entity, &mut World) + Send + 'static
+where
+    Clientbound: NetworkDirection<V, S>,
+{
+    move |entity: Entity, world: &mut World| {
+        let mut entity_mut = world.entity_mut(entity);
+        let task = entity_mut.take::<ConnectionTask<V, S>>().map(|task| task.close(&reason));
+
+        // Remove the `DimensionMarker` and apply any queued commands
+        entity_mut.remove::<DimensionMarker>();
+        world.flush();
+
+        // Keep track of the task so the server waits for it when shutting down
+        if let Some(task) = task {
+            match world.get_resource_mut::<ShutdownTasks>() {
+                Some(mut tasks) => tasks.push(task),
+                None => task.detach(),
+            }
+        }
+
+        debug!("Despawning Entity {entity}");
+        world.entity_mut(entity).despawn_recursive();
+    }
+}
@@ -4,16 +4,33 @@
 //! and can be used in any network-related code.
 
 mod channel;
-pub use channel::{channel, AsyncPacketChannel, PacketChannel};
+pub use channel::{channel, AsyncPacketChannel, ChannelSettings, PacketChannel, QueueDepth};
 
 mod component;
 pub use component::ComponentFilter;
 
+mod cookie;
+pub use cookie::{
+    Cookie, CookieError, CookieReceived, CookieTrait, Cookies, PendingCookies, RequestCookie,
+    StoreCookie,
+};
+
+mod disconnect;
+pub use disconnect::{disconnect, DisconnectPlayer, DisconnectTrait, DEFAULT_REASON};
+
 mod event;
 pub use event::{ConnectionStateEvent, PacketEvent};
 
 mod filter;
 pub use filter::{ConnectionFilter, FilterResult};
 
+mod stats;
+pub use stats::{
+    GlobalNetworkStats, NetworkStats, PacketStats, StatsCounter, StatsState, StatsStream,
+};
+
 mod task;
 pub use task::ConnectionTask;
+
+mod transfer;
+pub use transfer::{TransferPlayer, TransferTrait, Transferred};
@@ -0,0 +1,303 @@
+use std::{
+    io::Error as IoError,
+    ops::Add,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+
+use bevy::prelude::{Component, Deref, Resource};
+use futures_lite::{AsyncRead, AsyncWrite};
+
+/// The connection states that [`NetworkStats`] are kept for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatsState {
+    /// The client is sending its handshake.
+    Handshake,
+    /// The client is requesting the server status.
+    Status,
+    /// The client is logging in.
+    Login,
+    /// The client is configuring.
+    Configuration,
+    /// The client is playing.
+    Play,
+}
+
+impl StatsState {
+    /// All [`StatsState`]s, in order.
+    pub const ALL: [Self; 5] =
+        [Self::Handshake, Self::Status, Self::Login, Self::Configuration, Self::Play];
+
+    /// The position of the state in [`StatsState::ALL`].
+    const fn index(self) -> usize {
+        match self {
+            Self::Handshake => 0,
+            Self::Status => 1,
+            Self::Login => 2,
+            Self::Configuration => 3,
+            Self::Play => 4,
+        }
+    }
+}
+
+/// The number of packets and bytes sent and received.
+///
+/// Bytes are counted as they are written to and read from
+/// the connection's stream, after compression and encryption.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PacketStats {
+    /// The number of packets sent to the client.
+    pub packets_sent: u64,
+    /// The number of packets received from the client.
+    pub packets_received: u64,
+    /// The number of bytes sent to the client.
+    pub bytes_sent: u64,
+    /// The number of bytes received from the client.
+    pub bytes_received: u64,
+}
+
+impl Add for PacketStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            packets_sent: self.packets_sent + rhs.packets_sent,
+            packets_received: self.packets_received + rhs.packets_received,
+            bytes_sent: self.bytes_sent + rhs.bytes_sent,
+            bytes_received: self.bytes_received + rhs.bytes_received,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    packets_sent: AtomicU64,
+    packets_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl Counters {
+    fn sent(&self, packets: u64, bytes: u64) {
+        self.packets_sent.fetch_add(packets, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn received(&self, packets: u64, bytes: u64) {
+        self.packets_received.fetch_add(packets, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> PacketStats {
+        PacketStats {
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct StatsInner {
+    states: [Counters; 5],
+    current: AtomicU8,
+}
+
+/// Network statistics for a connection, broken down by [`StatsState`].
+///
+/// Shared with the connection's task, which counts every packet,
+/// and the connection's [`StatsStream`], which counts every byte.
+#[derive(Debug, Default, Clone, Component)]
+pub struct NetworkStats {
+    inner: Arc<StatsInner>,
+    parent: Option<Arc<StatsInner>>,
+}
+
+impl NetworkStats {
+    /// Create new [`NetworkStats`] for a connection
+    /// that also adds to these statistics.
+    #[must_use]
+    pub fn connection(&self) -> Self {
+        Self { inner: Arc::default(), parent: Some(self.inner.clone()) }
+    }
+
+    /// Get the statistics for a [`StatsState`].
+    #[must_use]
+    pub fn get(&self, state: StatsState) -> PacketStats { self.inner.states[state.index()].load() }
+
+    /// Get the statistics for all [`StatsState`]s combined.
+    #[must_use]
+    pub fn total(&self) -> PacketStats {
+        self.inner.states.iter().map(Counters::load).fold(PacketStats::default(), Add::add)
+    }
+
+    /// The latest [`StatsState`] a [`StatsCounter`] was created for.
+    #[must_use]
+    pub fn state(&self) -> StatsState {
+        StatsState::ALL[usize::from(self.inner.current.load(Ordering::Relaxed))]
+    }
+
+    /// Create a [`StatsCounter`] for the given [`StatsState`].
+    #[must_use]
+    pub fn counter(&self, state: StatsState) -> StatsCounter {
+        if let Ok(index) = u8::try_from(state.index()) {
+            self.inner.current.store(index, Ordering::Relaxed);
+        }
+        StatsCounter { stats: self.clone(), state }
+    }
+
+    /// Apply a function to the [`Counters`] of a [`StatsState`],
+    /// and to the parent's if there is one.
+    fn count(&self, state: StatsState, f: impl Fn(&Counters)) {
+        f(&self.inner.states[state.index()]);
+        if let Some(parent) = &self.parent {
+            f(&parent.states[state.index()]);
+        }
+    }
+}
+
+/// The [`NetworkStats`] of every connection combined.
+#[derive(Debug, Default, Clone, Deref, Resource)]
+pub struct GlobalNetworkStats(NetworkStats);
+
+/// Counts packets for one [`StatsState`] of a connection.
+#[derive(Debug, Clone)]
+pub struct StatsCounter {
+    stats: NetworkStats,
+    state: StatsState,
+}
+
+impl StatsCounter {
+    /// The [`StatsState`] packets are counted in.
+    #[must_use]
+    pub const fn state(&self) -> StatsState { self.state }
+
+    /// Count a packet sent to the client.
+    pub fn sent(&self) { self.stats.count(self.state, |counters| counters.sent(1, 0)); }
+
+    /// Count a packet received from the client.
+    pub fn received(&self) { self.stats.count(self.state, |counters| counters.received(1, 0)); }
+
+    /// Count a packet of the given size sent to the client.
+    pub fn sent_bytes(&self, bytes: usize) {
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        self.stats.count(self.state, |counters| counters.sent(1, bytes));
+    }
+
+    /// Count a packet of the given size received from the client.
+    pub fn received_bytes(&self, bytes: usize) {
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        self.stats.count(self.state, |counters| counters.received(1, bytes));
+    }
+}
+
+/// A stream that counts the bytes sent and received by a connection.
+///
+/// Bytes are added to the [`StatsState`] the connection is in,
+/// see [`NetworkStats::state`].
+#[derive(Debug)]
+pub struct StatsStream<T> {
+    stream: T,
+    stats: NetworkStats,
+}
+
+impl<T> StatsStream<T> {
+    /// Create a new [`StatsStream`] that counts bytes in the [`NetworkStats`].
+    #[must_use]
+    pub const fn new(stream: T, stats: NetworkStats) -> Self { Self { stream, stats } }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for StatsStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        let read = ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+
+        let bytes = u64::try_from(read).unwrap_or(u64::MAX);
+        this.stats.count(this.stats.state(), |counters| counters.received(0, bytes));
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for StatsStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        let written = ready!(Pin::new(&mut this.stream).poll_write(cx, buf))?;
+
+        let bytes = u64::try_from(written).unwrap_or(u64::MAX);
+        this.stats.count(this.stats.state(), |counters| counters.sent(0, bytes));
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, AsyncReadExt, AsyncWriteExt};
+
+    use super::{NetworkStats, PacketStats, StatsState, StatsStream};
+
+    #[test]
+    fn connection_stats() {
+        let global = NetworkStats::default();
+        let connection = global.connection();
+
+        let login = connection.counter(StatsState::Login);
+        login.sent_bytes(10);
+        login.received_bytes(4);
+        assert_eq!(connection.state(), StatsState::Login);
+
+        let play = connection.counter(StatsState::Play);
+        play.sent_bytes(100);
+        play.sent_bytes(20);
+        assert_eq!(connection.state(), StatsState::Play);
+
+        let expected =
+            PacketStats { packets_sent: 1, packets_received: 1, bytes_sent: 10, bytes_received: 4 };
+        assert_eq!(connection.get(StatsState::Login), expected);
+        assert_eq!(connection.get(StatsState::Play).bytes_sent, 120);
+        assert_eq!(connection.total().packets_sent, 3);
+
+        // The global statistics outlive the connection
+        drop((connection, login, play));
+        assert_eq!(global.total().bytes_sent, 130);
+        assert_eq!(global.get(StatsState::Status), PacketStats::default());
+    }
+
+    #[test]
+    fn stream_bytes() {
+        let global = NetworkStats::default();
+        let connection = global.connection();
+        let mut stream =
+            StatsStream::new(futures_lite::io::Cursor::new(vec![0; 8]), connection.clone());
+
+        // Bytes are counted in the current state
+        block_on(stream.read_exact(&mut [0; 3])).unwrap();
+        let _login = connection.counter(StatsState::Login);
+        block_on(stream.write_all(&[1; 16])).unwrap();
+
+        assert_eq!(connection.get(StatsState::Handshake).bytes_received, 3);
+        let expected = PacketStats { bytes_sent: 16, ..PacketStats::default() };
+        assert_eq!(connection.get(StatsState::Login), expected);
+        assert_eq!(global.total().bytes_sent, 16);
+    }
+}
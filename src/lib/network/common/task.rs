@@ -9,7 +9,7 @@ use froglight::{
     prelude::{State, *},
 };
 
-use super::PacketChannel;
+use super::{DisconnectTrait, PacketChannel};
 
 type TaskResult<V, S> = Result<Connection<V, S, Clientbound>, ConnectionError>;
 
@@ -42,4 +42,36 @@ where
     /// # Note
     /// This will panic if the task returns `Some` and is then polled again.
     pub fn poll(&mut self) -> Option<TaskResult<V, S>> { block_on(poll_once(&mut self.task)) }
+
+    /// Send a disconnect packet with the given reason and close the
+    /// [`ConnectionTask`].
+    ///
+    /// The task is detached so any queued packets are still sent.
+    pub fn disconnect(self, reason: &str)
+    where
+        V: DisconnectTrait<S>,
+    {
+        self.close(reason).detach();
+    }
+
+    /// Send a disconnect packet with the given reason and close the
+    /// [`PacketChannel`].
+    ///
+    /// Returns a [`Task`] that finishes once all queued packets are sent.
+    #[must_use]
+    pub fn close(self, reason: &str) -> Task<()>
+    where
+        V: DisconnectTrait<S>,
+    {
+        self.channel.send(V::disconnect_packet(reason));
+
+        let Self { channel, task } = self;
+        drop(channel);
+
+        IoTaskPool::get().spawn(async move {
+            if let Err(err) = task.await {
+                debug!("Connection closed with an error: {err}");
+            }
+        })
+    }
 }
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use compact_str::CompactString;
+use froglight::{
+    network::connection::NetworkDirection,
+    prelude::{State, *},
+};
+
+use super::ConnectionTask;
+
+/// A trait that defines how clients are transferred in a [`State`].
+pub trait TransferTrait<S>: Version
+where
+    Clientbound: NetworkDirection<Self, S>,
+    S: State<Self>,
+{
+    /// Create a packet that transfers the client to another server.
+    fn transfer_packet(host: &str, port: u16) -> <S as State<Self>>::ClientboundPacket;
+}
+
+/// An [`Event`] that transfers a player to another server.
+///
+/// Works while the player is configuring or playing.
+/// The client keeps its cookies and disconnects on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct TransferPlayer {
+    /// The player to transfer.
+    pub entity: Entity,
+    /// The host of the other server.
+    pub host: CompactString,
+    /// The port of the other server.
+    pub port: u16,
+}
+
+impl TransferPlayer {
+    /// The default port of a server.
+    pub const DEFAULT_PORT: u16 = 25565;
+
+    /// Create a new [`TransferPlayer`] event.
+    #[must_use]
+    pub fn new(entity: Entity, host: impl Into<CompactString>, port: u16) -> Self {
+        Self { entity, host: host.into(), port }
+    }
+}
+
+/// A marker [`Component`] for players that were transferred
+/// to this server by another server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Component)]
+#[component(storage = "SparseSet")]
+pub struct Transferred;
+
+impl<V: Version + TransferTrait<S>, S: State<V>> ConnectionTask<V, S>
+where
+    Clientbound: NetworkDirection<V, S>,
+{
+    /// A system that transfers players for
+    /// every [`TransferPlayer`] event received.
+    pub fn transfer_players(
+        query: Query<(&GameProfile, &Self)>,
+        mut events: EventReader<TransferPlayer>,
+    ) {
+        for TransferPlayer { entity, host, port } in events.read() {
+            if let Ok((profile, task)) = query.get(*entity) {
+                info!("Transferring {} to {host}:{port}", profile.username);
+                task.send(V::transfer_packet(host, *port));
+            }
+        }
+    }
+}
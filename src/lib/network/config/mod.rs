@@ -19,7 +19,14 @@ pub use types::*;
 mod version;
 pub use version::ConfigTrait;
 
-use super::login::LoginStateEvent;
+use super::{
+    common::{
+        ChannelSettings, CookieReceived, DisconnectPlayer, PendingCookies, RequestCookie,
+        StoreCookie, TransferPlayer,
+    },
+    login::LoginStateEvent,
+};
+use crate::shutdown::{ShutdownState, ShutdownSystemSet};
 
 /// A [`Plugin`] that receives logged in and reconfiguring clients.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -35,12 +42,19 @@ where
         // Add events and initialize resources
         app.add_event::<ConfigStateEvent<V>>();
         app.add_event::<ConfigPacketEvent<V>>();
+        app.add_event::<DisconnectPlayer>();
+        app.add_event::<TransferPlayer>();
+        app.add_event::<RequestCookie>();
+        app.add_event::<StoreCookie>();
+        app.add_event::<CookieReceived>();
         app.init_resource::<ConfigFilter<V>>();
+        app.init_resource::<ChannelSettings>();
 
         // Add `HasRegistries` as a required config component
-        let mut required = ConfigRequiredComponents::<V>::default();
-        required.add_required::<HasRegistries>();
-        app.insert_resource(required);
+        app.init_resource::<ConfigRequiredComponents<V>>();
+        app.world_mut()
+            .resource_mut::<ConfigRequiredComponents<V>>()
+            .add_required::<HasRegistries>();
 
         // Add systems
         app.add_systems(
@@ -51,7 +65,27 @@ where
         );
         app.add_systems(
             Update,
-            (ConfigTask::<V>::complete_configurations, ConfigTask::<V>::send_registries)
+            (
+                (
+                    ConfigFilter::<V>::filter_tasks,
+                    ConfigTask::<V>::disconnect_players.run_if(on_event::<DisconnectPlayer>),
+                    ConfigTask::<V>::check_queues,
+                ),
+                (ConfigTask::<V>::complete_configurations, ConfigTask::<V>::send_registries),
+            )
+                .chain()
+                .run_if(any_with_component::<ConfigTask<V>>)
+                .ambiguous_with_all(),
+        );
+        app.add_systems(
+            Update,
+            (
+                ConfigTask::<V>::transfer_players.run_if(on_event::<TransferPlayer>),
+                ConfigTask::<V>::request_cookies.run_if(on_event::<RequestCookie>),
+                ConfigTask::<V>::store_cookies.run_if(on_event::<StoreCookie>),
+                ConfigTask::<V>::store_pending.run_if(any_with_component::<PendingCookies>),
+                ConfigTask::<V>::receive_cookies.run_if(on_event::<ConfigPacketEvent<V>>),
+            )
                 .run_if(any_with_component::<ConfigTask<V>>)
                 .ambiguous_with_all(),
         );
@@ -63,5 +97,12 @@ where
             )
                 .ambiguous_with_all(),
         );
+        app.add_systems(
+            Last,
+            ConfigTask::<V>::shutdown_tasks
+                .run_if(ShutdownState::is_disconnecting)
+                .in_set(ShutdownSystemSet::Disconnect)
+                .ambiguous_with_all(),
+        );
     }
 }
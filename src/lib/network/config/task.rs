@@ -9,7 +9,12 @@ use super::{
     CompletedConfig, ConfigPacketEvent, ConfigRegistryTrait, ConfigRequiredComponents, ConfigTask,
     ConfigTrait, HasRegistries,
 };
-use crate::network::{common::channel, config::ConfigStateEvent, login::LoginStateEvent};
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{channel, ChannelSettings, NetworkStats, StatsState},
+    config::ConfigStateEvent,
+    login::LoginStateEvent,
+};
 
 impl<V: Version + ConfigTrait + ConfigRegistryTrait> ConfigTask<V>
 where
@@ -20,14 +25,29 @@ where
     /// A system that configures incoming logins.
     #[expect(clippy::missing_panics_doc)]
     pub fn receive_logins(
-        query: Query<&GameProfile>,
+        query: Query<(&GameProfile, Option<&NetworkStats>, Option<&ConnectionCapture>)>,
         mut events: EventReader<LoginStateEvent<V>>,
+        channels: Res<ChannelSettings>,
         mut commands: Commands,
     ) {
         for LoginStateEvent { entity, connection } in events.read() {
             if let Some(conn) = connection.lock().take() {
-                debug!("Configuring {} ...", query.get(*entity).unwrap().username);
-                commands.entity(*entity).insert(ConfigTask::new(conn.configuration()));
+                let (profile, stats, capture) = query.get(*entity).unwrap();
+                debug!("Configuring {} ...", profile.username);
+
+                let mut commands = commands.entity(*entity);
+                let stats = stats.cloned().unwrap_or_else(|| {
+                    let stats = NetworkStats::default();
+                    commands.insert(stats.clone());
+                    stats
+                });
+                let capture = capture.cloned().unwrap_or_default();
+                commands.insert(ConfigTask::new(
+                    conn.configuration(),
+                    channels.capacity,
+                    &stats,
+                    capture,
+                ));
             }
         }
     }
@@ -39,10 +59,19 @@ where
     Configuration: State<V>,
 {
     /// Create a new [`ConfigTask`] with the given [`Connection`]
+    ///
+    /// Each direction of the connection holds up to `capacity` packets,
+    /// packets are counted in the [`NetworkStats`]
+    /// and recorded to the [`ConnectionCapture`].
     #[must_use]
-    pub fn new(conn: Connection<V, Configuration, Clientbound>) -> Self {
-        let (send, recv) = channel();
-        Self::spawn(send, V::config(conn, recv))
+    pub fn new(
+        conn: Connection<V, Configuration, Clientbound>,
+        capacity: usize,
+        stats: &NetworkStats,
+        capture: ConnectionCapture,
+    ) -> Self {
+        let (send, recv) = channel(capacity, stats.counter(StatsState::Configuration));
+        Self::spawn(send, V::config(conn, recv, capture))
     }
 
     /// A system that sends registries to clients that
@@ -6,20 +6,27 @@ use froglight::{
 };
 
 use super::ConfigTask;
-use crate::network::common::AsyncPacketChannel;
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{AsyncPacketChannel, CookieTrait, DisconnectTrait, TransferTrait},
+};
 
 mod v1_21_0;
 
 ///  A trait that defines the behavior of the configuration process.
-pub trait ConfigTrait: Version
+pub trait ConfigTrait:
+    Version + DisconnectTrait<Configuration> + CookieTrait<Configuration> + TransferTrait<Configuration>
 where
     Clientbound: NetworkDirection<Self, Configuration>,
     Configuration: State<Self>,
 {
     /// An async function that performs the configuration process.
+    ///
+    /// Every packet is recorded to the [`ConnectionCapture`].
     fn config(
         conn: Connection<Self, Configuration, Clientbound>,
         channel: AsyncPacketChannel<Self, Configuration>,
+        capture: ConnectionCapture,
     ) -> impl Future<Output = Result<Connection<Self, Configuration, Clientbound>, ConnectionError>>
            + Send
            + Sync;
@@ -5,18 +5,24 @@ use froglight::{
         configuration::{
             ConfigurationClientboundPackets, ConfigurationServerboundPackets, ReadyS2CPacket,
         },
+        play::{CookieRequestPacket, DisconnectPacket, StoreCookiePacket, TransferPacket},
         V1_21_0,
     },
     prelude::*,
 };
 
 use super::ConfigTrait;
-use crate::network::{common::AsyncPacketChannel, config::ConfigTask};
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{AsyncPacketChannel, CookieTrait, DisconnectTrait, StatsState, TransferTrait},
+    config::ConfigTask,
+};
 
 impl ConfigTrait for V1_21_0 {
     async fn config(
         conn: Connection<Self, Configuration, Clientbound>,
         channel: AsyncPacketChannel<Self, Configuration>,
+        capture: ConnectionCapture,
     ) -> Result<Connection<Self, Configuration, Clientbound>, ConnectionError> {
         let (mut read, mut write) = conn.into_split();
 
@@ -45,6 +51,8 @@ impl ConfigTrait for V1_21_0 {
                             _ => {}
                         }
 
+                        channel.stats().sent();
+                        capture.sent(StatsState::Configuration, packet.as_ref());
                         write.send_packet(&packet).await?;
                     } else {
                         break;
@@ -55,6 +63,8 @@ impl ConfigTrait for V1_21_0 {
             async {
                 while !finished.load(Ordering::Relaxed) || pending.load(Ordering::Relaxed) > 0 {
                     let packet = read.recv().await?;
+                    channel.stats().received();
+                    capture.received(StatsState::Configuration, &packet);
                     #[cfg(debug_assertions)]
                     bevy::log::trace!(
                         "Received config packet: {packet:?}, pending: {}",
@@ -101,3 +111,36 @@ impl ConfigTrait for V1_21_0 {
 
     fn send_finish(task: &ConfigTask<Self>) { task.send(ReadyS2CPacket); }
 }
+
+impl DisconnectTrait<Configuration> for V1_21_0 {
+    fn disconnect_packet(reason: &str) -> ConfigurationClientboundPackets {
+        DisconnectPacket { reason: reason.into() }.into()
+    }
+}
+
+impl CookieTrait<Configuration> for V1_21_0 {
+    fn request_packet(key: ResourceKey) -> ConfigurationClientboundPackets {
+        CookieRequestPacket { key }.into()
+    }
+
+    fn store_packet(key: ResourceKey, payload: Vec<u8>) -> Option<ConfigurationClientboundPackets> {
+        Some(StoreCookiePacket { key, payload }.into())
+    }
+
+    fn cookie_response(
+        packet: &ConfigurationServerboundPackets,
+    ) -> Option<(ResourceKey, Option<Vec<u8>>)> {
+        match packet {
+            ConfigurationServerboundPackets::CookieResponse(response) => {
+                Some((response.key.clone(), response.payload.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TransferTrait<Configuration> for V1_21_0 {
+    fn transfer_packet(host: &str, port: u16) -> ConfigurationClientboundPackets {
+        TransferPacket { host: host.into(), port: port.into() }.into()
+    }
+}
@@ -0,0 +1,110 @@
+//! [`Diagnostics`] for connections and network traffic.
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use super::common::{GlobalNetworkStats, NetworkStats, PacketStats, StatsState};
+
+/// A [`Plugin`] that adds [`Diagnostics`] for connections and network
+/// traffic, using the [`NetworkStats`] of every connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetworkDiagnosticsPlugin;
+
+impl NetworkDiagnosticsPlugin {
+    /// The number of clients logging in.
+    pub const LOGIN_CONNECTIONS: DiagnosticPath =
+        DiagnosticPath::const_new("network/connections/login");
+    /// The number of clients configuring.
+    pub const CONFIG_CONNECTIONS: DiagnosticPath =
+        DiagnosticPath::const_new("network/connections/configuration");
+    /// The number of clients playing.
+    pub const PLAY_CONNECTIONS: DiagnosticPath =
+        DiagnosticPath::const_new("network/connections/play");
+
+    /// The number of packets sent per second.
+    pub const PACKETS_SENT: DiagnosticPath = DiagnosticPath::const_new("network/packets_sent");
+    /// The number of packets received per second.
+    pub const PACKETS_RECEIVED: DiagnosticPath =
+        DiagnosticPath::const_new("network/packets_received");
+    /// The number of bytes sent per second.
+    pub const BYTES_SENT: DiagnosticPath = DiagnosticPath::const_new("network/bytes_sent");
+    /// The number of bytes received per second.
+    pub const BYTES_RECEIVED: DiagnosticPath = DiagnosticPath::const_new("network/bytes_received");
+}
+
+impl Plugin for NetworkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GlobalNetworkStats>();
+
+        // Register diagnostics
+        for path in [Self::LOGIN_CONNECTIONS, Self::CONFIG_CONNECTIONS, Self::PLAY_CONNECTIONS] {
+            app.register_diagnostic(Diagnostic::new(path));
+        }
+        for path in [Self::PACKETS_SENT, Self::PACKETS_RECEIVED] {
+            app.register_diagnostic(Diagnostic::new(path).with_suffix(" packets/s"));
+        }
+        for path in [Self::BYTES_SENT, Self::BYTES_RECEIVED] {
+            app.register_diagnostic(Diagnostic::new(path).with_suffix(" B/s"));
+        }
+
+        // Add systems
+        app.add_systems(
+            PostUpdate,
+            (Self::measure_connections, Self::measure_traffic).ambiguous_with_all(),
+        );
+    }
+}
+
+impl NetworkDiagnosticsPlugin {
+    /// A system that counts the connections in each [`StatsState`].
+    #[expect(clippy::cast_precision_loss)]
+    fn measure_connections(query: Query<&NetworkStats>, mut diagnostics: Diagnostics) {
+        let (mut login, mut config, mut play) = (0usize, 0usize, 0usize);
+        for stats in &query {
+            match stats.state() {
+                StatsState::Login => login += 1,
+                StatsState::Configuration => config += 1,
+                StatsState::Play => play += 1,
+                StatsState::Handshake | StatsState::Status => {}
+            }
+        }
+
+        diagnostics.add_measurement(&Self::LOGIN_CONNECTIONS, || login as f64);
+        diagnostics.add_measurement(&Self::CONFIG_CONNECTIONS, || config as f64);
+        diagnostics.add_measurement(&Self::PLAY_CONNECTIONS, || play as f64);
+    }
+
+    /// A system that measures the packets and bytes
+    /// sent and received per second.
+    #[expect(clippy::cast_precision_loss)]
+    fn measure_traffic(
+        stats: Res<GlobalNetworkStats>,
+        time: Res<Time<Real>>,
+        mut previous: Local<PacketStats>,
+        mut diagnostics: Diagnostics,
+    ) {
+        let delta = time.delta_secs_f64();
+        if delta <= 0.0 {
+            return;
+        }
+
+        let total = stats.total();
+        let rate = |now: u64, before: u64| now.saturating_sub(before) as f64 / delta;
+
+        diagnostics.add_measurement(&Self::PACKETS_SENT, || {
+            rate(total.packets_sent, previous.packets_sent)
+        });
+        diagnostics.add_measurement(&Self::PACKETS_RECEIVED, || {
+            rate(total.packets_received, previous.packets_received)
+        });
+        diagnostics
+            .add_measurement(&Self::BYTES_SENT, || rate(total.bytes_sent, previous.bytes_sent));
+        diagnostics.add_measurement(&Self::BYTES_RECEIVED, || {
+            rate(total.bytes_received, previous.bytes_received)
+        });
+
+        *previous = total;
+    }
+}
@@ -0,0 +1,227 @@
+use std::net::IpAddr;
+
+use bevy::utils::HashMap;
+use froglight::prelude::{GameProfile, ProfileProperty, Uuid};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// Player information forwarded by a proxy.
+#[derive(Debug, Clone)]
+pub struct ForwardedInfo {
+    /// The address of the client.
+    pub address: IpAddr,
+    /// The hostname the client connected to, if forwarded.
+    pub hostname: Option<String>,
+    /// The profile of the client.
+    pub profile: GameProfile,
+}
+
+/// An error that occurred while reading forwarded player information.
+#[derive(Debug, thiserror::Error)]
+pub enum ForwardingError {
+    /// The signature did not match the forwarding secret.
+    #[error("Invalid signature")]
+    Signature,
+    /// The forwarding version is not supported.
+    #[error("Unsupported forwarding version: {0}")]
+    Version(i32),
+    /// The forwarded data was not valid.
+    #[error("Invalid data: {0}")]
+    Invalid(&'static str),
+    /// The forwarded properties were not valid.
+    #[error("Invalid properties: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl ForwardedInfo {
+    /// The Velocity forwarding version requested from the proxy.
+    pub const VELOCITY_VERSION: u8 = 1;
+
+    /// The length of the HMAC-SHA256 signature
+    /// at the start of the Velocity response.
+    const SIGNATURE_LENGTH: usize = 32;
+
+    /// Verify and read the response to a `velocity:player_info` query.
+    ///
+    /// # Errors
+    /// Returns an error if the signature does not match
+    /// the secret or the data is invalid.
+    pub fn from_velocity(secret: &[u8], response: &[u8]) -> Result<Self, ForwardingError> {
+        if response.len() < Self::SIGNATURE_LENGTH {
+            return Err(ForwardingError::Invalid("Missing signature"));
+        }
+        let (signature, data) = response.split_at(Self::SIGNATURE_LENGTH);
+
+        // Check the signature before reading anything else
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+            .map_err(|_| ForwardingError::Invalid("Invalid secret"))?;
+        mac.update(data);
+        mac.verify_slice(signature).map_err(|_| ForwardingError::Signature)?;
+
+        let mut reader = Reader(data);
+        let version = reader.var_int()?;
+        if version < i32::from(Self::VELOCITY_VERSION) {
+            return Err(ForwardingError::Version(version));
+        }
+
+        let address = reader.string()?;
+        let address = address.parse().map_err(|_| ForwardingError::Invalid("Invalid address"))?;
+        let uuid = Uuid::from_u128(reader.u128()?);
+        let username = reader.string()?.to_string();
+
+        let mut properties = HashMap::new();
+        for _ in 0..reader.var_int()? {
+            let name = reader.string()?.to_string();
+            let value = reader.string()?.to_string();
+            let signature = if reader.bool()? { Some(reader.string()?.to_string()) } else { None };
+            properties.insert(name, ProfileProperty { value, signature });
+        }
+
+        Ok(Self { address, hostname: None, profile: GameProfile { uuid, username, properties } })
+    }
+
+    /// Read the player information BungeeCord adds to the handshake address.
+    ///
+    /// The address is made of the hostname, client address, UUID
+    /// and optionally the profile properties, separated by `\0`.
+    ///
+    /// # Errors
+    /// Returns an error if the address does not contain forwarded information.
+    pub fn from_bungeecord(address: &str, username: &str) -> Result<Self, ForwardingError> {
+        let mut parts = address.split('\0');
+        let (Some(hostname), Some(client), Some(uuid)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(ForwardingError::Invalid("Missing forwarded information"));
+        };
+
+        let address = client.parse().map_err(|_| ForwardingError::Invalid("Invalid address"))?;
+        let uuid = Uuid::try_parse(uuid).map_err(|_| ForwardingError::Invalid("Invalid UUID"))?;
+
+        let properties = match parts.next() {
+            Some(json) => serde_json::from_str::<Vec<BungeeProperty>>(json)?
+                .into_iter()
+                .map(|p| (p.name, ProfileProperty { value: p.value, signature: p.signature }))
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            address,
+            hostname: Some(hostname.to_string()),
+            profile: GameProfile { uuid, username: username.to_string(), properties },
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct BungeeProperty {
+    name: String,
+    value: String,
+    signature: Option<String>,
+}
+
+/// Reads values from a Velocity response.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], ForwardingError> {
+        if self.0.len() < length {
+            return Err(ForwardingError::Invalid("Unexpected end of data"));
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn var_int(&mut self) -> Result<i32, ForwardingError> {
+        let mut value = 0u32;
+        for index in 0..5 {
+            let byte = self.take(1)?[0];
+            value |= u32::from(byte & 0x7F) << (index * 7);
+            if byte & 0x80 == 0 {
+                return Ok(i32::from_ne_bytes(value.to_ne_bytes()));
+            }
+        }
+        Err(ForwardingError::Invalid("VarInt is too long"))
+    }
+
+    fn string(&mut self) -> Result<&'a str, ForwardingError> {
+        let length = usize::try_from(self.var_int()?)
+            .map_err(|_| ForwardingError::Invalid("Negative string length"))?;
+        std::str::from_utf8(self.take(length)?)
+            .map_err(|_| ForwardingError::Invalid("Invalid string"))
+    }
+
+    fn bool(&mut self) -> Result<bool, ForwardingError> { Ok(self.take(1)?[0] != 0) }
+
+    fn u128(&mut self) -> Result<u128, ForwardingError> {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(self.take(16)?);
+        Ok(u128::from_be_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::{ForwardedInfo, ForwardingError};
+
+    #[test]
+    fn velocity() {
+        let uuid = 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef_u128;
+
+        let mut data = vec![0x01, 9];
+        data.extend_from_slice(b"127.0.0.1");
+        data.extend_from_slice(&uuid.to_be_bytes());
+        data.push(4);
+        data.extend_from_slice(b"Frog");
+        data.extend_from_slice(&[1, 8]);
+        data.extend_from_slice(b"textures");
+        data.push(3);
+        data.extend_from_slice(b"abc");
+        data.push(0);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(&data);
+        let mut response = mac.finalize().into_bytes().to_vec();
+        response.extend_from_slice(&data);
+
+        let info = ForwardedInfo::from_velocity(b"secret", &response).unwrap();
+        assert_eq!(info.address.to_string(), "127.0.0.1");
+        assert_eq!(info.profile.uuid.as_u128(), uuid);
+        assert_eq!(info.profile.username, "Frog");
+        assert_eq!(info.profile.properties["textures"].value, "abc");
+        assert_eq!(info.profile.properties["textures"].signature, None);
+
+        // Reject responses signed with a different secret
+        assert!(matches!(
+            ForwardedInfo::from_velocity(b"other", &response),
+            Err(ForwardingError::Signature)
+        ));
+        assert!(ForwardedInfo::from_velocity(b"secret", &response[..16]).is_err());
+    }
+
+    #[test]
+    fn bungeecord() {
+        let address = "play.example.com\x00192.168.0.2\x000123456789abcdef0123456789abcdef\x00[{\"name\":\"textures\",\"value\":\"abc\",\"signature\":\"def\"}]";
+        let info = ForwardedInfo::from_bungeecord(address, "Frog").unwrap();
+        assert_eq!(info.address.to_string(), "192.168.0.2");
+        assert_eq!(info.hostname.as_deref(), Some("play.example.com"));
+        assert_eq!(info.profile.uuid.as_u128(), 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef_u128);
+        assert_eq!(info.profile.properties["textures"].signature.as_deref(), Some("def"));
+
+        // Properties are optional
+        let address = "localhost\x00::1\x000123456789abcdef0123456789abcdef";
+        assert!(ForwardedInfo::from_bungeecord(address, "Frog")
+            .unwrap()
+            .profile
+            .properties
+            .is_empty());
+
+        // Connections that did not come through BungeeCord
+        assert!(ForwardedInfo::from_bungeecord("localhost", "Frog").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! TODO
+
+use std::{marker::PhantomData, net::SocketAddr, sync::Arc};
+
+use bevy::prelude::*;
+use froglight::{
+    network::connection::{ConnectionInformation, NetworkDirection},
+    prelude::{State, *},
+};
+
+mod info;
+pub use info::{ForwardedInfo, ForwardingError};
+
+use super::{
+    common::DisconnectPlayer,
+    login::{
+        AuthenticatedProfile, AuthenticationServer, LoginFilter, LoginQueries, LoginQuery,
+        LoginQueryAnswer, LoginQueryPlugin, LoginRequiredComponents, LoginTask, LoginTrait,
+    },
+};
+
+/// A [`Plugin`] that trusts player information forwarded by a proxy.
+///
+/// The proxy authenticates players, so the
+/// [`LoginPlugin`](super::LoginPlugin) should be in offline mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForwardingPlugin<V: Version> {
+    /// How player information is forwarded.
+    pub forwarding: PlayerForwarding,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> ForwardingPlugin<V> {
+    /// Create a new [`ForwardingPlugin`] using the given [`PlayerForwarding`].
+    #[must_use]
+    pub const fn new(forwarding: PlayerForwarding) -> Self {
+        Self { forwarding, _phantom: PhantomData }
+    }
+
+    /// Create a new [`ForwardingPlugin`] using Velocity modern forwarding.
+    #[must_use]
+    pub fn velocity(secret: impl AsRef<[u8]>) -> Self {
+        Self::new(PlayerForwarding::velocity(secret))
+    }
+
+    /// Create a new [`ForwardingPlugin`] using BungeeCord legacy forwarding.
+    #[must_use]
+    pub const fn bungeecord() -> Self { Self::new(PlayerForwarding::BungeeCord) }
+}
+
+impl<V: Version + LoginTrait> Plugin for ForwardingPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Login>,
+    Login: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        // Only insert the `PlayerForwarding` if another version hasn't already
+        if !app.world().contains_resource::<PlayerForwarding>() {
+            app.insert_resource(self.forwarding.clone());
+        }
+
+        // Require a `ForwardedPlayer` to finish logging in
+        app.world_mut()
+            .resource_mut::<LoginRequiredComponents<V>>()
+            .add_required::<ForwardedPlayer>();
+
+        // Add systems
+        app.add_systems(
+            Update,
+            ForwardedPlayer::begin_forwarding::<V>
+                .before(LoginFilter::<V>::filter_tasks)
+                .run_if(any_with_component::<LoginTask<V>>)
+                .ambiguous_with_all(),
+        );
+
+        // Send a `VelocityPlayerInfo` query to every client
+        if let PlayerForwarding::Velocity { .. } = self.forwarding {
+            app.add_plugins(
+                LoginQueryPlugin::<V, VelocityPlayerInfo>::new().with_on_login(VelocityPlayerInfo),
+            );
+            app.add_systems(
+                Update,
+                ForwardedPlayer::receive_responses::<V>
+                    .run_if(on_event::<LoginQueryAnswer<VelocityPlayerInfo>>)
+                    .after(LoginQueries::receive_answers::<V, VelocityPlayerInfo>)
+                    .before(LoginFilter::<V>::filter_tasks)
+                    .ambiguous_with_all(),
+            );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if app
+            .world()
+            .get_resource::<AuthenticationServer<V>>()
+            .is_some_and(|auth| auth.read().is_some())
+        {
+            warn!("Player forwarding is enabled, but the server is in online mode!");
+        }
+    }
+}
+
+/// How player information is forwarded by a proxy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
+pub enum PlayerForwarding {
+    /// Velocity modern forwarding, signed using a shared secret.
+    Velocity {
+        /// The secret shared with the proxy.
+        secret: Arc<[u8]>,
+    },
+    /// BungeeCord legacy forwarding, sent in the handshake address.
+    ///
+    /// This is not signed, so the server must
+    /// only be reachable through the proxy.
+    BungeeCord,
+}
+
+impl PlayerForwarding {
+    /// The reason given to players that did not connect through the proxy.
+    pub const MISSING_REASON: &'static str = "This server requires you to connect through a proxy.";
+
+    /// The reason given to players whose information could not be verified.
+    pub const INVALID_REASON: &'static str = "Unable to verify player details.";
+
+    /// Create a new [`PlayerForwarding::Velocity`] with the given secret.
+    #[must_use]
+    pub fn velocity(secret: impl AsRef<[u8]>) -> Self {
+        Self::Velocity { secret: Arc::from(secret.as_ref()) }
+    }
+}
+
+/// A marker [`Component`] for players whose information
+/// was forwarded by the proxy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Component)]
+#[component(storage = "SparseSet")]
+pub struct ForwardedPlayer;
+
+/// A [`LoginQuery`] that asks Velocity for the player's information.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VelocityPlayerInfo;
+
+impl LoginQuery for VelocityPlayerInfo {
+    const CHANNEL: &'static str = "velocity:player_info";
+    type Response = Option<Vec<u8>>;
+
+    fn encode(&self) -> Vec<u8> { vec![ForwardedInfo::VELOCITY_VERSION] }
+
+    fn decode(data: Option<&[u8]>) -> Self::Response { data.map(<[u8]>::to_vec) }
+}
+
+impl ForwardedPlayer {
+    /// A system that starts forwarding for new logins.
+    ///
+    /// Velocity players wait for a [`VelocityPlayerInfo`] response,
+    /// while BungeeCord players are read from the handshake address.
+    pub fn begin_forwarding<V: Version>(
+        mut query: Query<
+            (Entity, &mut GameProfile, &mut ConnectionInformation),
+            Added<LoginTask<V>>,
+        >,
+        forwarding: Res<PlayerForwarding>,
+        mut events: EventWriter<DisconnectPlayer>,
+        mut commands: Commands,
+    ) where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        for (entity, mut profile, mut information) in &mut query {
+            match &*forwarding {
+                PlayerForwarding::Velocity { .. } => {
+                    // The profile cannot be trusted until the proxy responds
+                    commands.entity(entity).remove::<AuthenticatedProfile>();
+                }
+                PlayerForwarding::BungeeCord => {
+                    let address = information.address.as_deref().unwrap_or_default();
+                    match ForwardedInfo::from_bungeecord(address, &profile.username) {
+                        Ok(info) => {
+                            Self::apply(info, &mut profile, &mut information);
+                            commands.entity(entity).insert((ForwardedPlayer, AuthenticatedProfile));
+                        }
+                        Err(err) => {
+                            debug!("Failed to read forwarded information: {err}");
+                            events.send(DisconnectPlayer::new(
+                                entity,
+                                PlayerForwarding::MISSING_REASON,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// A system that verifies responses to [`VelocityPlayerInfo`] queries.
+    pub fn receive_responses<V: Version>(
+        mut query: Query<(&mut GameProfile, &mut ConnectionInformation), With<LoginTask<V>>>,
+        forwarding: Res<PlayerForwarding>,
+        mut answers: EventReader<LoginQueryAnswer<VelocityPlayerInfo>>,
+        mut events: EventWriter<DisconnectPlayer>,
+        mut commands: Commands,
+    ) where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        let PlayerForwarding::Velocity { secret } = &*forwarding else { return };
+
+        for LoginQueryAnswer { entity, response, .. } in answers.read() {
+            let Ok((mut profile, mut information)) = query.get_mut(*entity) else { continue };
+
+            let Some(response) = response else {
+                debug!("{} did not connect through Velocity", profile.username);
+                events.send(DisconnectPlayer::new(*entity, PlayerForwarding::MISSING_REASON));
+                continue;
+            };
+
+            match ForwardedInfo::from_velocity(secret, response) {
+                Ok(info) => {
+                    Self::apply(info, &mut profile, &mut information);
+                    commands.entity(*entity).insert((ForwardedPlayer, AuthenticatedProfile));
+                }
+                Err(err) => {
+                    warn!("Failed to verify forwarded information for {}: {err}", profile.username);
+                    events.send(DisconnectPlayer::new(*entity, PlayerForwarding::INVALID_REASON));
+                }
+            }
+        }
+    }
+
+    /// Replace the [`GameProfile`] and [`ConnectionInformation`]
+    /// with the forwarded information.
+    ///
+    /// This runs before the [`LoginFilter`],
+    /// so the forwarded address is filtered before the login completes.
+    fn apply(
+        info: ForwardedInfo,
+        profile: &mut GameProfile,
+        information: &mut ConnectionInformation,
+    ) {
+        debug!(
+            "Forwarded {} as {} ({}) from {}",
+            profile.username, info.profile.username, info.profile.uuid, info.address
+        );
+
+        *profile = info.profile;
+        information.socket = SocketAddr::new(info.address, information.socket.port());
+        if let Some(hostname) = info.hostname {
+            information.address = Some(hostname.into());
+        }
+    }
+}
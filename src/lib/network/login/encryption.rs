@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use bevy::prelude::{Deref, Resource};
+use rand::RngCore;
+use rsa::{pkcs8::EncodePublicKey, Pkcs1v15Encrypt, RsaPrivateKey};
+
+/// The RSA key pair used to encrypt connections.
+///
+/// Generated once at startup and shared between all login tasks.
+#[derive(Debug, Clone, Deref, Resource)]
+pub struct ServerKeyPair {
+    #[deref]
+    private: Arc<RsaPrivateKey>,
+    public: Arc<[u8]>,
+}
+
+impl ServerKeyPair {
+    /// The size of the generated RSA key, in bits.
+    pub const KEY_BITS: usize = 1024;
+
+    /// The length of the verify token sent to clients.
+    pub const TOKEN_LENGTH: usize = 4;
+
+    /// Generate a new random [`ServerKeyPair`].
+    ///
+    /// # Panics
+    /// Panics if the RSA key fails to generate.
+    #[must_use]
+    pub fn new() -> Self {
+        let private = RsaPrivateKey::new(&mut rand::thread_rng(), Self::KEY_BITS)
+            .expect("Failed to generate RSA key");
+        let public = private
+            .to_public_key()
+            .to_public_key_der()
+            .expect("Failed to encode RSA public key")
+            .into_vec();
+
+        Self { private: Arc::new(private), public: public.into() }
+    }
+
+    /// The public key, encoded as an ASN.1 DER `SubjectPublicKeyInfo`.
+    #[must_use]
+    pub fn public_key(&self) -> &[u8] { &self.public }
+
+    /// Decrypt data that was encrypted with the public key.
+    ///
+    /// Returns `None` if the data could not be decrypted.
+    #[must_use]
+    pub fn decrypt(&self, data: &[u8]) -> Option<Vec<u8>> {
+        self.private.decrypt(Pkcs1v15Encrypt, data).ok()
+    }
+
+    /// Generate a random verify token.
+    #[must_use]
+    pub fn verify_token() -> [u8; Self::TOKEN_LENGTH] {
+        let mut token = [0u8; Self::TOKEN_LENGTH];
+        rand::thread_rng().fill_bytes(&mut token);
+        token
+    }
+}
+
+impl Default for ServerKeyPair {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+    use froglight::{
+        network::{
+            connection::AccountInformation,
+            versions::v1_21_0::login::{LoginClientboundPackets, LoginHelloC2SPacket},
+        },
+        prelude::*,
+    };
+    use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+
+    use super::ServerKeyPair;
+    use crate::{
+        network::login::SessionBackend,
+        testing::{TestClient, TestServer, TestSession},
+        ServerPlugins,
+    };
+
+    /// Encrypt a secret the way a client would and decrypt it again.
+    #[test]
+    fn keypair_roundtrip() {
+        let keys = ServerKeyPair::new();
+        let public = RsaPublicKey::from_public_key_der(keys.public_key()).unwrap();
+
+        let secret = [42u8; 16];
+        let encrypted = public.encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, &secret).unwrap();
+        assert_eq!(keys.decrypt(&encrypted).as_deref(), Some(secret.as_slice()));
+
+        // Garbage should not decrypt.
+        assert_eq!(keys.decrypt(&[0u8; 16]), None);
+    }
+
+    /// Create a [`TestServer`] in online mode that verifies every client.
+    fn online_server(session: TestSession) -> TestServer {
+        let mut server = TestServer::from_plugins(ServerPlugins::localhost().online());
+        server.app().insert_resource(SessionBackend::new(session));
+        server
+    }
+
+    /// Log in using the full encryption exchange.
+    #[test]
+    fn encrypted_login() {
+        let profile = GameProfile {
+            uuid: Uuid::from_u128(1),
+            username: String::from("Verified"),
+            properties: HashMap::new(),
+        };
+        let mut server = online_server(TestSession::accept(profile));
+
+        let client = server.run(|address| async move {
+            let mut client = TestClient::new(address, "Player");
+            client.login().await.map(|_| client)
+        });
+        let client = client.unwrap();
+
+        // The server requested encryption
+        assert!(matches!(
+            client.received.login.first(),
+            Some(LoginClientboundPackets::LoginHello(..))
+        ));
+
+        // The client was given the verified profile
+        let Some(LoginClientboundPackets::LoginSuccess(success)) = client.received.login.last()
+        else {
+            panic!("Expected a login success, got {:?}", client.received.login);
+        };
+        assert_eq!(success.profile.username, "Verified");
+        assert_eq!(success.profile.uuid, Uuid::from_u128(1));
+    }
+
+    /// A client that sends back the wrong verify token is disconnected.
+    #[test]
+    fn wrong_verify_token() {
+        let session = TestSession::reject();
+        let mut server = online_server(session.clone());
+
+        let result = server.run(|address| async move {
+            let client = TestClient::new(address, "Player");
+            let mut conn = client.connect(ConnectionIntent::Login).await?.login();
+            conn.send(LoginHelloC2SPacket {
+                username: client.username.clone(),
+                uuid: AccountInformation::offline_uuid(&client.username),
+            })
+            .await?;
+
+            let LoginClientboundPackets::LoginHello(request) = conn.recv().await? else {
+                panic!("Expected an encryption request");
+            };
+            let token: Vec<u8> = request.nonce.iter().map(|byte| !byte).collect();
+            TestClient::encrypt(&mut conn, &request, &token).await?;
+
+            // The server closes the connection
+            conn.recv().await.map(drop)
+        });
+
+        assert!(result.is_err());
+        assert!(session.requests().is_empty(), "The session server should not be asked");
+    }
+}
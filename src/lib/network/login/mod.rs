@@ -6,8 +6,31 @@ use bevy::prelude::*;
 use compact_str::CompactString;
 use froglight::{network::connection::NetworkDirection, prelude::*};
 
-use super::socket::ConnectionRequestEvent;
-use crate::dimension::{All, DimensionApp};
+use super::{
+    common::{
+        ChannelSettings, CookieReceived, DisconnectPlayer, FilterResult, RequestCookie,
+        StoreCookie, Transferred,
+    },
+    socket::ConnectionRequestEvent,
+};
+use crate::{
+    dimension::{All, DimensionApp},
+    shutdown::{ShutdownState, ShutdownSystemSet},
+};
+
+mod encryption;
+pub use encryption::ServerKeyPair;
+
+mod query;
+pub use query::{
+    LoginQueries, LoginQuery, LoginQueryAnswer, LoginQueryPlugin, QueryResponse, SendLoginQuery,
+};
+
+mod session;
+pub use session::{
+    server_hash, ProfileReceiver, SessionBackend, SessionClient, SessionError, SessionRequest,
+    UreqClient,
+};
 
 mod version;
 pub use version::LoginTrait;
@@ -22,6 +45,18 @@ pub use types::*;
 pub struct LoginPlugin<V: Version> {
     /// The address of the authentication server.
     pub auth_server: Option<CompactString>,
+    /// The minimum size of a packet before it is compressed.
+    ///
+    /// If [`None`], packets will not be compressed.
+    pub compression_threshold: Option<i32>,
+    /// Whether clients transferred from another server are accepted.
+    ///
+    /// Disabled by default, like vanilla servers.
+    pub accept_transfers: bool,
+    /// The [`ChannelSettings`] for new connections.
+    ///
+    /// If [`None`], the defaults are used unless already inserted.
+    pub channels: Option<ChannelSettings>,
     _phantom: PhantomData<V>,
 }
 
@@ -30,26 +65,62 @@ impl<V: Version> LoginPlugin<V> {
     pub const MOJANG_SERVER: CompactString =
         CompactString::const_new("https://sessionserver.mojang.com");
 
+    /// The default compression threshold used by vanilla servers.
+    pub const DEFAULT_COMPRESSION: i32 = 256;
+
+    /// The reason given to transferred clients when transfers are disabled.
+    pub const TRANSFERS_DISABLED: &'static str = "Transfers are disabled on this server";
+
     /// Create a new [`LoginPlugin`] without authentication.
     #[must_use]
-    pub const fn offline() -> Self { Self { auth_server: None, _phantom: PhantomData } }
+    pub const fn offline() -> Self { Self::from_option(None) }
 
     /// Create a new [`LoginPlugin`] using online authentication.
     #[must_use]
-    pub const fn online() -> Self {
-        Self { auth_server: Some(Self::MOJANG_SERVER), _phantom: PhantomData }
-    }
+    pub const fn online() -> Self { Self::from_address(Self::MOJANG_SERVER) }
 
     /// Create a new [`LoginPlugin`] using custom authentication.
     #[must_use]
-    pub const fn from_address(server: CompactString) -> Self {
-        Self { auth_server: Some(server), _phantom: PhantomData }
-    }
+    pub const fn from_address(server: CompactString) -> Self { Self::from_option(Some(server)) }
 
     /// Create a new [`LoginPlugin`] optionally using custom authentication.
     #[must_use]
     pub const fn from_option(server: Option<CompactString>) -> Self {
-        Self { auth_server: server, _phantom: PhantomData }
+        Self {
+            auth_server: server,
+            compression_threshold: None,
+            accept_transfers: false,
+            channels: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Compress packets larger than the given threshold.
+    #[must_use]
+    pub const fn with_compression(mut self, threshold: i32) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Disable packet compression.
+    #[must_use]
+    pub const fn without_compression(mut self) -> Self {
+        self.compression_threshold = None;
+        self
+    }
+
+    /// Set whether clients transferred from another server are accepted.
+    #[must_use]
+    pub const fn with_transfers(mut self, accept: bool) -> Self {
+        self.accept_transfers = accept;
+        self
+    }
+
+    /// Use the given [`ChannelSettings`] for new connections.
+    #[must_use]
+    pub const fn with_channels(mut self, channels: ChannelSettings) -> Self {
+        self.channels = Some(channels);
+        self
     }
 }
 
@@ -64,26 +135,74 @@ where
         app.insert_dimension_resource(All, auth_server.clone());
         app.insert_resource(auth_server);
 
+        // Insert the `CompressionThreshold`
+        app.insert_resource(CompressionThreshold::<V>::from(self.compression_threshold));
+
+        // Generate the `ServerKeyPair` and create the `SessionBackend`,
+        // unless they were already inserted
+        app.init_resource::<ServerKeyPair>();
+        app.init_resource::<SessionBackend>();
+
         // Add events and initialize resources
         app.add_event::<LoginStateEvent<V>>();
         app.add_event::<LoginPacketEvent<V>>();
+        app.add_event::<DisconnectPlayer>();
+        app.add_event::<RequestCookie>();
+        app.add_event::<StoreCookie>();
+        app.add_event::<CookieReceived>();
         app.init_resource::<LoginFilter<V>>();
+        match self.channels {
+            Some(channels) => app.insert_resource(channels),
+            None => app.init_resource::<ChannelSettings>(),
+        };
+
+        // Reject transferred clients if transfers are disabled
+        if !self.accept_transfers {
+            app.world_mut().resource_mut::<LoginFilter<V>>().add_filter(|entity, world| {
+                if world.get::<Transferred>(entity).is_some() {
+                    FilterResult::Deny(Some(Self::TRANSFERS_DISABLED.into()))
+                } else {
+                    FilterResult::Allow
+                }
+            });
+        }
 
         // Initialize and add required components
         let mut required = LoginRequiredComponents::<V>::new_empty();
         required.add_required::<GameProfile>();
+        required.add_required::<AuthenticatedProfile>();
         app.insert_resource(required);
 
         // Add systems
         app.add_systems(
             PreUpdate,
-            LoginTask::<V>::receive_packets
+            (
+                LoginTask::<V>::receive_packets.run_if(any_with_component::<LoginTask<V>>),
+                LoginTask::<V>::receive_profiles.run_if(any_with_component::<ProfileReceiver>),
+            )
+                .ambiguous_with_all(),
+        );
+        app.add_systems(
+            Update,
+            (
+                (
+                    LoginFilter::<V>::filter_tasks,
+                    LoginTask::<V>::disconnect_players.run_if(on_event::<DisconnectPlayer>),
+                    LoginTask::<V>::check_queues,
+                ),
+                LoginTask::<V>::complete_logins,
+            )
+                .chain()
                 .run_if(any_with_component::<LoginTask<V>>)
                 .ambiguous_with_all(),
         );
         app.add_systems(
             Update,
-            LoginTask::<V>::complete_logins
+            (
+                LoginTask::<V>::request_cookies.run_if(on_event::<RequestCookie>),
+                LoginTask::<V>::store_cookies.run_if(on_event::<StoreCookie>),
+                LoginTask::<V>::receive_cookies.run_if(on_event::<LoginPacketEvent<V>>),
+            )
                 .run_if(any_with_component::<LoginTask<V>>)
                 .ambiguous_with_all(),
         );
@@ -95,5 +214,12 @@ where
             )
                 .ambiguous_with_all(),
         );
+        app.add_systems(
+            Last,
+            LoginTask::<V>::shutdown_tasks
+                .run_if(ShutdownState::is_disconnecting)
+                .in_set(ShutdownSystemSet::Disconnect)
+                .ambiguous_with_all(),
+        );
     }
 }
@@ -0,0 +1,435 @@
+use std::{
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use froglight::{
+    network::connection::NetworkDirection,
+    prelude::{State, *},
+};
+
+use super::{LoginPacketEvent, LoginRequiredComponents, LoginTask, LoginTrait};
+use crate::network::common::DisconnectPlayer;
+
+/// A typed query sent to clients on a plugin channel while logging in.
+pub trait LoginQuery: Send + Sync + 'static {
+    /// The plugin channel the query is sent on.
+    const CHANNEL: &'static str;
+
+    /// The decoded response from the client.
+    type Response: Clone + Send + Sync + 'static;
+
+    /// Encode the query into the payload sent to the client.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Decode the client's response.
+    ///
+    /// The data is `None` if the client did not understand the channel.
+    fn decode(data: Option<&[u8]>) -> Self::Response;
+}
+
+/// A [`Plugin`] that sends and receives a [`LoginQuery`].
+///
+/// Queries are sent using [`SendLoginQuery`] events, and responses
+/// are received as [`LoginQueryAnswer`] events and [`QueryResponse`]s.
+///
+/// Clients that do not answer within the timeout are disconnected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoginQueryPlugin<V: Version, Q: LoginQuery> {
+    /// A query sent to every client when it starts logging in.
+    pub on_login: Option<Q>,
+    /// Whether clients must answer the query to finish logging in.
+    pub required: bool,
+    /// How long clients have to answer a query.
+    pub timeout: Duration,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version, Q: LoginQuery> Default for LoginQueryPlugin<V, Q> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<V: Version, Q: LoginQuery> LoginQueryPlugin<V, Q> {
+    /// Create a new [`LoginQueryPlugin`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            on_login: None,
+            required: false,
+            timeout: LoginQueries::DEFAULT_TIMEOUT,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Send the given query to every client when it starts logging in.
+    #[must_use]
+    pub fn with_on_login(mut self, query: Q) -> Self {
+        self.on_login = Some(query);
+        self
+    }
+
+    /// Require clients to answer the query before they finish logging in.
+    ///
+    /// Clients are held until they answer, so the query
+    /// must be sent to every client that logs in.
+    #[must_use]
+    pub const fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Set how long clients have to answer a query.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<V: Version + LoginTrait, Q: LoginQuery + Clone> Plugin for LoginQueryPlugin<V, Q>
+where
+    Clientbound: NetworkDirection<V, Login>,
+    Login: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<SendLoginQuery<Q>>();
+        app.add_event::<LoginQueryAnswer<Q>>();
+
+        // Hold logins until the client answers
+        if self.required {
+            app.world_mut()
+                .resource_mut::<LoginRequiredComponents<V>>()
+                .add_required::<QueryResponse<Q>>();
+        }
+
+        // Add systems
+        if let Some(query) = self.on_login.clone() {
+            app.add_systems(
+                Update,
+                LoginQueries::send_on_login::<V, Q>(query)
+                    .before(LoginQueries::send_queries::<V, Q>)
+                    .run_if(any_with_component::<LoginTask<V>>)
+                    .ambiguous_with_all(),
+            );
+        }
+        app.add_systems(
+            Update,
+            (
+                LoginQueries::send_queries::<V, Q>.run_if(on_event::<SendLoginQuery<Q>>),
+                LoginQueries::receive_answers::<V, Q>.run_if(on_event::<LoginPacketEvent<V>>),
+                LoginQueries::timeout_queries::<V, Q>(self.timeout),
+            )
+                .chain()
+                .run_if(any_with_component::<LoginTask<V>>)
+                .ambiguous_with_all(),
+        );
+    }
+}
+
+/// An [`Event`] that sends a [`LoginQuery`] to a client that is logging in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Event)]
+pub struct SendLoginQuery<Q: LoginQuery> {
+    /// The client to send the query to.
+    pub entity: Entity,
+    /// The query.
+    pub query: Q,
+}
+
+impl<Q: LoginQuery> SendLoginQuery<Q> {
+    /// Create a new [`SendLoginQuery`] event.
+    #[must_use]
+    pub const fn new(entity: Entity, query: Q) -> Self { Self { entity, query } }
+}
+
+/// An [`Event`] that is sent when a client answers a [`LoginQuery`].
+#[derive(Event)]
+pub struct LoginQueryAnswer<Q: LoginQuery> {
+    /// The client that answered.
+    pub entity: Entity,
+    /// The message id of the query.
+    pub id: u32,
+    /// The decoded response.
+    pub response: Q::Response,
+}
+
+/// The latest response to a [`LoginQuery`].
+///
+/// Can be added to the [`LoginRequiredComponents`]
+/// to hold logins until the client answers.
+#[derive(Component, Deref)]
+#[component(storage = "SparseSet")]
+pub struct QueryResponse<Q: LoginQuery>(pub Q::Response);
+
+/// The [`LoginQuery`]s waiting for a response from a client.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct LoginQueries {
+    next_id: u32,
+    pending: HashMap<u32, (&'static str, Instant)>,
+}
+
+impl LoginQueries {
+    /// The default time clients have to answer a query.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// The reason given to clients that did not answer a query in time.
+    pub const TIMEOUT_REASON: &'static str = "Timed out while logging in";
+
+    /// Send a [`LoginQuery`] to the client and return its message id.
+    pub fn send<V: Version + LoginTrait, Q: LoginQuery>(
+        &mut self,
+        task: &LoginTask<V>,
+        query: &Q,
+    ) -> u32
+    where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending.insert(id, (Q::CHANNEL, Instant::now()));
+
+        V::send_query(task, id, ResourceKey::const_new(Q::CHANNEL), query.encode());
+        id
+    }
+
+    /// Returns `true` if any queries are waiting for a response.
+    #[must_use]
+    pub fn is_pending(&self) -> bool { !self.pending.is_empty() }
+
+    /// Remove a pending query if it was sent on the given channel.
+    fn answer(&mut self, id: u32, channel: &'static str) -> bool {
+        if self.pending.get(&id).is_some_and(|(pending, _)| *pending == channel) {
+            self.pending.remove(&id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Remove pending queries on the given channel that were sent
+    /// longer ago than the timeout, returning `true` if any were removed.
+    fn expire(&mut self, channel: &'static str, timeout: Duration) -> bool {
+        let count = self.pending.len();
+        self.pending.retain(|_, (pending, sent)| *pending != channel || sent.elapsed() < timeout);
+        self.pending.len() != count
+    }
+
+    /// A system that sends a query to every client when it starts logging in.
+    fn send_on_login<V: Version + LoginTrait, Q: LoginQuery>(
+        query: Q,
+    ) -> impl FnMut(Query<(&LoginTask<V>, &mut LoginQueries), Added<LoginTask<V>>>) + Send + Sync + 'static
+    where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        move |mut tasks| {
+            for (task, mut queries) in &mut tasks {
+                queries.send(task, &query);
+            }
+        }
+    }
+
+    /// A system that sends a query for every [`SendLoginQuery`] event
+    /// received.
+    pub fn send_queries<V: Version + LoginTrait, Q: LoginQuery>(
+        mut query: Query<(&LoginTask<V>, &mut LoginQueries)>,
+        mut events: EventReader<SendLoginQuery<Q>>,
+    ) where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        for SendLoginQuery { entity, query: login_query } in events.read() {
+            if let Ok((task, mut queries)) = query.get_mut(*entity) {
+                let id = queries.send(task, login_query);
+                trace!("Sent login query {id} on \"{}\" to Entity {entity}", Q::CHANNEL);
+            }
+        }
+    }
+
+    /// A system that receives responses to queries sent on the
+    /// [`LoginQuery`]'s channel.
+    pub fn receive_answers<V: Version + LoginTrait, Q: LoginQuery>(
+        mut query: Query<&mut LoginQueries>,
+        mut packets: EventReader<LoginPacketEvent<V>>,
+        mut events: EventWriter<LoginQueryAnswer<Q>>,
+        mut commands: Commands,
+    ) where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        for LoginPacketEvent { entity, packet } in packets.read() {
+            let Some((id, data)) = V::query_response(packet) else { continue };
+            let Ok(mut queries) = query.get_mut(*entity) else { continue };
+            if !queries.answer(id, Q::CHANNEL) {
+                continue;
+            }
+
+            trace!("Received login query {id} on \"{}\" from Entity {entity}", Q::CHANNEL);
+            let response = Q::decode(data);
+            commands.entity(*entity).insert(QueryResponse::<Q>(response.clone()));
+            events.send(LoginQueryAnswer { entity: *entity, id, response });
+        }
+    }
+
+    /// A system that disconnects clients that did not answer
+    /// a query on the [`LoginQuery`]'s channel in time.
+    fn timeout_queries<V: Version, Q: LoginQuery>(
+        timeout: Duration,
+    ) -> impl FnMut(
+        Query<(Entity, &mut LoginQueries), With<LoginTask<V>>>,
+        EventWriter<DisconnectPlayer>,
+    ) + Send
+           + Sync
+           + 'static
+    where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        move |mut query, mut events| {
+            for (entity, mut queries) in &mut query {
+                if queries.is_pending() && queries.expire(Q::CHANNEL, timeout) {
+                    debug!("Entity {entity} did not answer a query on \"{}\"", Q::CHANNEL);
+                    events.send(DisconnectPlayer::new(entity, Self::TIMEOUT_REASON));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use froglight::{
+        network::{
+            connection::AccountInformation,
+            versions::v1_21_0::{
+                login::{
+                    LoginClientboundPackets, LoginHelloC2SPacket, LoginQueryRequestPacket,
+                    LoginQueryResponsePacket,
+                },
+                V1_21_0,
+            },
+        },
+        prelude::*,
+    };
+
+    use super::{LoginQueries, LoginQuery, LoginQueryPlugin, QueryResponse};
+    use crate::{
+        network::login::CompletedLogin,
+        testing::{ServerAddress, TestClient, TestServer},
+    };
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Ping;
+
+    impl LoginQuery for Ping {
+        const CHANNEL: &'static str = "froglight:ping";
+        type Response = Option<Vec<u8>>;
+
+        fn encode(&self) -> Vec<u8> { vec![1] }
+
+        fn decode(data: Option<&[u8]>) -> Self::Response { data.map(<[u8]>::to_vec) }
+    }
+
+    /// Start logging in and wait for the [`Ping`] query.
+    async fn start_login(
+        address: ServerAddress,
+    ) -> Result<(Connection<V1_21_0, Login, Serverbound>, LoginQueryRequestPacket), ConnectionError>
+    {
+        let client = TestClient::new(address, "Player");
+        let mut conn = client.connect(ConnectionIntent::Login).await?.login();
+        conn.send(LoginHelloC2SPacket {
+            username: client.username.clone(),
+            uuid: AccountInformation::offline_uuid(&client.username),
+        })
+        .await?;
+
+        loop {
+            if let LoginClientboundPackets::LoginQueryRequest(request) = conn.recv().await? {
+                return Ok((conn, request));
+            }
+        }
+    }
+
+    /// Answer a query with the given id.
+    fn answer(id: u32) -> LoginQueryResponsePacket {
+        LoginQueryResponsePacket { id, payload: Some(vec![2].into()) }
+    }
+
+    /// Required queries hold the login until the answer with the right id
+    /// arrives.
+    #[test]
+    fn required_query() {
+        let mut server = TestServer::new();
+        server.app().add_plugins(
+            LoginQueryPlugin::<V1_21_0, Ping>::new().with_on_login(Ping).with_required(true),
+        );
+
+        let (ready_send, ready_recv) = async_channel::bounded::<()>(1);
+        let (next_send, next_recv) = async_channel::bounded::<()>(1);
+        let client = server.spawn_client(|address| async move {
+            let (mut conn, request) = start_login(address).await?;
+            assert_eq!(request.identifier, ResourceKey::const_new(Ping::CHANNEL));
+
+            // Answer with the wrong id, then wait for the server
+            conn.send(answer(request.id.wrapping_add(1))).await?;
+            ready_send.send(()).await.unwrap();
+            next_recv.recv().await.unwrap();
+
+            // Answer with the right id
+            conn.send(answer(request.id)).await?;
+            loop {
+                if let LoginClientboundPackets::LoginSuccess(..) = conn.recv().await? {
+                    return Ok::<_, ConnectionError>(());
+                }
+            }
+        });
+
+        // The wrong answer is ignored and the login is held
+        assert!(server.update_until(|_| ready_recv.try_recv().is_ok()));
+        for _ in 0..10 {
+            server.update();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let world = server.app().world_mut();
+        assert_eq!(
+            world.query::<&LoginQueries>().iter(world).filter(|q| q.is_pending()).count(),
+            1
+        );
+        assert_eq!(world.query::<&QueryResponse<Ping>>().iter(world).count(), 0);
+        assert_eq!(world.query::<&CompletedLogin>().iter(world).count(), 0);
+
+        // The right answer completes the login
+        next_send.try_send(()).unwrap();
+        server.run_client(client).unwrap();
+        let world = server.app().world_mut();
+        let responses: Vec<_> =
+            world.query::<&QueryResponse<Ping>>().iter(world).map(|r| r.0.clone()).collect();
+        assert_eq!(responses, [Some(vec![2])]);
+    }
+
+    /// Clients that do not answer in time are disconnected.
+    #[test]
+    fn query_timeout() {
+        let mut server = TestServer::new();
+        server.app().add_plugins(
+            LoginQueryPlugin::<V1_21_0, Ping>::new()
+                .with_on_login(Ping)
+                .with_timeout(Duration::from_millis(100)),
+        );
+
+        let packet = server
+            .run(|address| async move {
+                let (mut conn, _) = start_login(address).await?;
+                conn.recv().await
+            })
+            .unwrap();
+
+        let LoginClientboundPackets::LoginDisconnect(packet) = packet else {
+            panic!("Expected the client to be disconnected, got {packet:?}");
+        };
+        assert_eq!(packet.reason, LoginQueries::TIMEOUT_REASON.into());
+    }
+}
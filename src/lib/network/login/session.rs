@@ -0,0 +1,297 @@
+use std::{fmt::Write, sync::Arc};
+
+use async_channel::{Receiver, Sender};
+use bevy::{
+    prelude::{Component, Deref, Resource},
+    utils::{BoxedFuture, HashMap},
+};
+use compact_str::CompactString;
+use froglight::prelude::{GameProfile, ProfileProperty, Uuid};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+/// A client used to send requests to a session server.
+///
+/// Can be replaced by inserting a custom [`SessionBackend`]
+/// before the [`LoginPlugin`](super::LoginPlugin) is added.
+pub trait SessionClient: Send + Sync + 'static {
+    /// Send a `GET` request to the given url.
+    ///
+    /// Returns the response body,
+    /// or `None` if the server responded without any content.
+    fn get(&self, url: String) -> BoxedFuture<'static, Result<Option<String>, SessionError>>;
+}
+
+/// The [`SessionClient`] used to verify clients.
+#[derive(Clone, Deref, Resource)]
+pub struct SessionBackend(Arc<dyn SessionClient>);
+
+impl SessionBackend {
+    /// Create a new [`SessionBackend`] using the given [`SessionClient`].
+    #[must_use]
+    pub fn new(client: impl SessionClient) -> Self { Self(Arc::new(client)) }
+}
+
+impl Default for SessionBackend {
+    fn default() -> Self { Self::new(UreqClient) }
+}
+
+/// The default [`SessionClient`], using [`ureq`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UreqClient;
+
+impl SessionClient for UreqClient {
+    fn get(&self, url: String) -> BoxedFuture<'static, Result<Option<String>, SessionError>> {
+        Box::pin(blocking::unblock(move || {
+            let response =
+                ureq::get(&url).call().map_err(|err| SessionError::Request(err.to_string()))?;
+            if response.status() == 204 {
+                return Ok(None);
+            }
+
+            let body =
+                response.into_string().map_err(|err| SessionError::Request(err.to_string()))?;
+            if body.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(body))
+            }
+        }))
+    }
+}
+
+/// An error that occurred while verifying a client.
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    /// The request to the session server failed.
+    #[error("Request failed: {0}")]
+    Request(String),
+    /// The session server did not recognize the client.
+    #[error("Failed to verify username")]
+    NotJoined,
+    /// The session server sent an invalid response.
+    #[error("Invalid response: {0}")]
+    Response(#[from] serde_json::Error),
+    /// The session server sent an invalid UUID.
+    #[error("Invalid UUID: {0}")]
+    Uuid(String),
+}
+
+/// A request to verify a client with the session server.
+///
+/// Created for each client logging in while using an
+/// [`AuthenticationServer`](super::AuthenticationServer).
+pub struct SessionRequest {
+    client: SessionBackend,
+    server: CompactString,
+    username: CompactString,
+    sender: Sender<GameProfile>,
+}
+
+/// A [`Component`] that receives the verified [`GameProfile`]
+/// from a [`LoginTask`](super::LoginTask).
+#[derive(Debug, Component, Deref)]
+pub struct ProfileReceiver(Receiver<GameProfile>);
+
+impl SessionRequest {
+    /// The reason given to clients that could not be verified.
+    pub const FAILED_REASON: &'static str = "Failed to verify username!";
+
+    /// Create a new [`SessionRequest`] and the matching [`ProfileReceiver`].
+    #[must_use]
+    pub fn new(
+        client: SessionBackend,
+        server: CompactString,
+        username: CompactString,
+    ) -> (Self, ProfileReceiver) {
+        let (sender, receiver) = async_channel::bounded(1);
+        (Self { client, server, username, sender }, ProfileReceiver(receiver))
+    }
+
+    /// Ask the session server if the client has joined,
+    /// and return the verified [`GameProfile`].
+    ///
+    /// # Errors
+    /// Returns an error if the request failed or the client was not verified.
+    pub async fn verify(&self, hash: &str) -> Result<GameProfile, SessionError> {
+        let url = format!(
+            "{}/session/minecraft/hasJoined?username={}&serverId={}",
+            self.server.trim_end_matches('/'),
+            encode_query(&self.username),
+            encode_query(hash),
+        );
+
+        let Some(body) = self.client.get(url).await? else {
+            return Err(SessionError::NotJoined);
+        };
+        let response: HasJoinedResponse = serde_json::from_str(&body)?;
+        let Ok(uuid) = Uuid::try_parse(&response.id) else {
+            return Err(SessionError::Uuid(response.id));
+        };
+
+        Ok(GameProfile {
+            uuid,
+            username: response.name,
+            properties: response
+                .properties
+                .into_iter()
+                .map(|p| (p.name, ProfileProperty { value: p.value, signature: p.signature }))
+                .collect::<HashMap<_, _>>(),
+        })
+    }
+
+    /// Send the verified [`GameProfile`] to the [`ProfileReceiver`].
+    pub async fn complete(self, profile: GameProfile) { let _ = self.sender.send(profile).await; }
+}
+
+/// The response from the `hasJoined` endpoint.
+#[derive(Deserialize)]
+struct HasJoinedResponse {
+    id: String,
+    name: String,
+    #[serde(default)]
+    properties: Vec<HasJoinedProperty>,
+}
+
+#[derive(Deserialize)]
+struct HasJoinedProperty {
+    name: String,
+    value: String,
+    signature: Option<String>,
+}
+
+/// Percent-encode a value used in a url query.
+///
+/// Only unreserved characters are left as they are.
+fn encode_query(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Compute the server hash sent to the session server.
+///
+/// This is the SHA-1 digest of the server id, shared secret and public key,
+/// formatted as a signed hexadecimal number.
+#[must_use]
+pub fn server_hash(server_id: &str, secret: &[u8], public_key: &[u8]) -> String {
+    let digest = Sha1::new().chain_update(server_id).chain_update(secret).chain_update(public_key);
+    let mut digest: [u8; 20] = digest.finalize().into();
+
+    // Take the two's complement of negative digests
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                (*byte, carry) = byte.overflowing_add(1);
+            }
+        }
+    }
+
+    let mut hex = String::with_capacity(40);
+    for byte in digest.into_iter().skip_while(|b| *b == 0) {
+        let _ = write!(hex, "{byte:02x}");
+    }
+
+    // Remove any leading zero and add the sign
+    let hex = hex.strip_prefix('0').unwrap_or(&hex);
+    if negative {
+        format!("-{hex}")
+    } else {
+        hex.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{tasks::block_on, utils::HashMap};
+    use froglight::{
+        network::versions::v1_21_0::login::LoginClientboundPackets,
+        prelude::{GameProfile, Uuid},
+    };
+
+    use super::{server_hash, SessionBackend, SessionError, SessionRequest};
+    use crate::{
+        testing::{TestClient, TestServer, TestSession},
+        ServerPlugins,
+    };
+
+    /// Create a [`SessionRequest`] for the given username.
+    fn request(session: &TestSession, username: &str) -> SessionRequest {
+        let backend = SessionBackend::new(session.clone());
+        SessionRequest::new(backend, "https://session.example.com/".into(), username.into()).0
+    }
+
+    #[test]
+    fn verify_accepts() {
+        let profile = GameProfile {
+            uuid: Uuid::from_u128(1),
+            username: String::from("Player"),
+            properties: HashMap::new(),
+        };
+        let session = TestSession::accept(profile);
+
+        let verified = block_on(request(&session, "Player").verify("-1a2b")).unwrap();
+        assert_eq!(verified.uuid, Uuid::from_u128(1));
+        assert_eq!(verified.username, "Player");
+        assert_eq!(
+            session.requests(),
+            ["https://session.example.com/session/minecraft/hasJoined?username=Player&serverId=-1a2b"]
+        );
+    }
+
+    #[test]
+    fn verify_rejects() {
+        let session = TestSession::reject();
+        let result = block_on(request(&session, "Player").verify("-1a2b"));
+        assert!(matches!(result, Err(SessionError::NotJoined)));
+    }
+
+    /// Clients that fail to verify are told why.
+    #[test]
+    fn unverified_disconnect() {
+        let mut server = TestServer::from_plugins(ServerPlugins::localhost().online());
+        server.app().insert_resource(SessionBackend::new(TestSession::reject()));
+
+        let client = server.run(|address| async move {
+            let mut client = TestClient::new(address, "Player");
+            let result = client.login().await;
+            (client, result.map(drop))
+        });
+
+        assert!(client.1.is_err());
+        let Some(LoginClientboundPackets::LoginDisconnect(disconnect)) =
+            client.0.received.login.last()
+        else {
+            panic!("Expected a disconnect, got {:?}", client.0.received.login);
+        };
+        assert!(format!("{:?}", disconnect.reason).contains(SessionRequest::FAILED_REASON));
+    }
+
+    /// Usernames can't add their own query parameters.
+    #[test]
+    fn verify_encodes_username() {
+        let session = TestSession::reject();
+        let _ = block_on(request(&session, "a&serverId=b#c d").verify("-1a2b"));
+        assert_eq!(
+            session.requests(),
+            ["https://session.example.com/session/minecraft/hasJoined?username=a%26serverId%3Db%23c%20d&serverId=-1a2b"]
+        );
+    }
+
+    /// Test against known `sha1` digests.
+    #[test]
+    fn server_hashes() {
+        assert_eq!(server_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(server_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(server_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
+    }
+}
@@ -7,10 +7,13 @@ use froglight::{
 };
 
 use super::{
-    AuthenticationServer, LoginPacketEvent, LoginRequiredComponents, LoginTask, LoginTrait,
+    AuthenticatedProfile, AuthenticationServer, CompressionThreshold, LoginPacketEvent,
+    LoginQueries, LoginRequiredComponents, LoginTask, LoginTrait, ProfileReceiver, ServerKeyPair,
+    SessionBackend, SessionRequest,
 };
 use crate::network::{
-    common::channel,
+    capture::ConnectionCapture,
+    common::{channel, ChannelSettings, Cookies, NetworkStats, StatsState, Transferred},
     login::{CompletedLogin, ConnectionInstant, LoginStateEvent},
     socket::ConnectionRequestEvent,
 };
@@ -20,23 +23,42 @@ where
     Clientbound: NetworkDirection<V, Login>,
     Login: State<V>,
 {
-    /// Create a new [`LoginTask`] with the
-    /// given [`Connection`] and [`AuthenticationServer`].
+    /// Create a new [`LoginTask`] with the given [`Connection`].
+    ///
+    /// If a [`SessionRequest`] is given, the connection will be
+    /// encrypted using the [`ServerKeyPair`] and the client verified.
+    ///
+    /// If a compression threshold is given,
+    /// compression is enabled before the login succeeds.
+    ///
+    /// Each direction of the connection holds up to `capacity` packets,
+    /// packets are counted in the [`NetworkStats`]
+    /// and recorded to the [`ConnectionCapture`].
     #[must_use]
+    #[expect(clippy::too_many_arguments)]
     pub fn new(
         conn: Connection<V, Login, Clientbound>,
-        auth_server: AuthenticationServer<V>,
+        keys: ServerKeyPair,
+        session: Option<SessionRequest>,
+        compression: Option<i32>,
         resolver: Resolver,
+        capacity: usize,
+        stats: &NetworkStats,
+        capture: ConnectionCapture,
     ) -> Self {
-        let (send, recv) = channel();
-        Self::spawn(send, V::login(conn, recv, auth_server, resolver))
+        let (send, recv) = channel(capacity, stats.counter(StatsState::Login));
+        Self::spawn(send, V::login(conn, recv, keys, session, compression, resolver, capture))
     }
 
     /// A system that authenticates incoming connection requests.
     pub fn receive_requests(
         mut events: EventReader<ConnectionRequestEvent<V>>,
         auth: Res<AuthenticationServer<V>>,
+        keys: Res<ServerKeyPair>,
+        backend: Res<SessionBackend>,
+        compression: Res<CompressionThreshold<V>>,
         resolver: Res<Resolver>,
+        channels: Res<ChannelSettings>,
         mut commands: Commands,
     ) {
         for ConnectionRequestEvent { listener, request } in events.read() {
@@ -46,17 +68,55 @@ where
                     request.information.clone(),
                     ConnectionInstant::from(Instant::now()),
                     GameProfile {
-                        uuid: if auth.read().is_none() {
-                            AccountInformation::offline_uuid(&request.username)
-                        } else {
-                            request.uuid
-                        },
+                        uuid: AccountInformation::offline_uuid(&request.username),
                         username: request.username.to_string(),
                         properties: HashMap::new(),
                     },
-                    LoginTask::<V>::new(conn, auth.clone(), resolver.clone()),
+                    Cookies::default(),
+                    LoginQueries::default(),
+                    request.stats.clone(),
+                    request.capture.clone(),
                 ));
 
+                if request.transfer {
+                    debug!("{} was transferred from another server", request.username);
+                    entity.insert(Transferred);
+                }
+
+                if let Some(server) = auth.read().clone() {
+                    // Verify the client with the authentication server
+                    let (session, receiver) =
+                        SessionRequest::new(backend.clone(), server, request.username.clone());
+                    entity.insert((
+                        receiver,
+                        LoginTask::<V>::new(
+                            conn,
+                            keys.clone(),
+                            Some(session),
+                            **compression,
+                            resolver.clone(),
+                            channels.capacity,
+                            &request.stats,
+                            request.capture.clone(),
+                        ),
+                    ));
+                } else {
+                    // Trust the client's profile
+                    entity.insert((
+                        AuthenticatedProfile,
+                        LoginTask::<V>::new(
+                            conn,
+                            keys.clone(),
+                            None,
+                            **compression,
+                            resolver.clone(),
+                            channels.capacity,
+                            &request.stats,
+                            request.capture.clone(),
+                        ),
+                    ));
+                }
+
                 entity.set_parent(*listener);
                 debug!("Spawning Entity {} for {}", entity.id(), request.username);
             }
@@ -85,6 +145,24 @@ where
     Clientbound: NetworkDirection<V, Login>,
     Login: State<V>,
 {
+    /// A system that receives verified [`GameProfile`]s
+    /// and replaces the profiles sent by clients.
+    pub fn receive_profiles(
+        mut query: Query<(Entity, &ProfileReceiver, &mut GameProfile)>,
+        mut commands: Commands,
+    ) {
+        for (entity, receiver, mut profile) in &mut query {
+            if let Ok(verified) = receiver.try_recv() {
+                debug!(
+                    "Verified {} as {} ({})",
+                    profile.username, verified.username, verified.uuid
+                );
+                *profile = verified;
+                commands.entity(entity).remove::<ProfileReceiver>().insert(AuthenticatedProfile);
+            }
+        }
+    }
+
     /// A system that receives packets from all login tasks.
     pub fn receive_packets(
         query: Query<(Entity, &LoginTask<V>)>,
@@ -29,15 +29,41 @@ pub struct AuthenticationServer<V: Version> {
     _phantom: std::marker::PhantomData<V>,
 }
 
+/// The minimum size of a packet before it is compressed.
+///
+/// If [`None`], packets will not be compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deref, Resource)]
+pub struct CompressionThreshold<V: Version> {
+    #[deref]
+    threshold: Option<i32>,
+    _phantom: std::marker::PhantomData<V>,
+}
+
 /// The instant a connection was established.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Deref, From)]
 pub struct ConnectionInstant(Instant);
 
+/// A marker component that indicates that the [`GameProfile`] can be trusted.
+///
+/// Inserted immediately in offline mode, otherwise
+/// after the session server has verified the client.
+///
+/// [`GameProfile`]: froglight::prelude::GameProfile
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Component)]
+#[component(storage = "SparseSet")]
+pub struct AuthenticatedProfile;
+
 /// A marker component that indicates that the login process has been completed.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Component)]
 #[component(storage = "SparseSet")]
 pub struct CompletedLogin;
 
+impl<V: Version> From<Option<i32>> for CompressionThreshold<V> {
+    fn from(threshold: Option<i32>) -> Self {
+        Self { threshold, _phantom: std::marker::PhantomData }
+    }
+}
+
 impl<V: Version> From<Option<CompactString>> for AuthenticationServer<V> {
     fn from(address: Option<CompactString>) -> Self {
         Self { address: Arc::new(RwLock::new(address)), _phantom: std::marker::PhantomData }
@@ -3,29 +3,55 @@ use std::future::Future;
 use froglight::{
     network::connection::NetworkDirection,
     prelude::{
-        Clientbound, Connection, ConnectionError, GameProfile, Login, Resolver, State, Version,
+        Clientbound, Connection, ConnectionError, GameProfile, Login, Resolver, ResourceKey, State,
+        Version,
     },
 };
 
-use super::{AuthenticationServer, LoginTask};
-use crate::network::common::AsyncPacketChannel;
+use super::{LoginTask, ServerKeyPair, SessionRequest};
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{AsyncPacketChannel, CookieTrait, DisconnectTrait},
+};
 
 mod v1_21_0;
 
 ///  A trait that defines the behavior of a login process.
-pub trait LoginTrait: Version
+pub trait LoginTrait: Version + DisconnectTrait<Login> + CookieTrait<Login>
 where
     Clientbound: NetworkDirection<Self, Login>,
     Login: State<Self>,
 {
     /// An async function that performs the login process.
+    ///
+    /// If a [`SessionRequest`] is given, the connection is encrypted
+    /// using the [`ServerKeyPair`] and the client is verified
+    /// by the [`AuthenticationServer`](super::AuthenticationServer).
+    ///
+    /// If a compression threshold is given, compression is
+    /// negotiated immediately before the login succeeds.
+    ///
+    /// Every packet is recorded to the [`ConnectionCapture`].
     fn login(
         conn: Connection<Self, Login, Clientbound>,
         channel: AsyncPacketChannel<Self, Login>,
-        auth_server: AuthenticationServer<Self>,
+        keys: ServerKeyPair,
+        session: Option<SessionRequest>,
+        compression: Option<i32>,
         resolver: Resolver,
+        capture: ConnectionCapture,
     ) -> impl Future<Output = Result<Connection<Self, Login, Clientbound>, ConnectionError>> + Send + Sync;
 
     /// Send a [`GameProfile`] to the client.
     fn send_profile(profile: &GameProfile, task: &LoginTask<Self>);
+
+    /// Send a login query with the given message id on a plugin channel.
+    fn send_query(task: &LoginTask<Self>, id: u32, channel: ResourceKey, data: Vec<u8>);
+
+    /// Get the message id and data of a login query response.
+    ///
+    /// Returns `None` if the packet is not a response.
+    fn query_response(
+        packet: &<Login as State<Self>>::ServerboundPacket,
+    ) -> Option<(u32, Option<&[u8]>)>;
 }
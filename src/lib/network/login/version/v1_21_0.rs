@@ -1,8 +1,16 @@
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::{
+    io::{Error as IoError, ErrorKind},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
 
 use froglight::{
     network::versions::v1_21_0::{
-        login::{LoginClientboundPackets, LoginServerboundPackets, LoginSuccessPacket},
+        login::{
+            LoginClientboundPackets, LoginCompressionPacket, LoginDisconnectPacket,
+            LoginHelloS2CPacket, LoginQueryRequestPacket, LoginServerboundPackets,
+            LoginSuccessPacket,
+        },
+        play::CookieRequestPacket,
         V1_21_0,
     },
     prelude::*,
@@ -10,17 +18,40 @@ use froglight::{
 
 use super::LoginTrait;
 use crate::network::{
-    common::AsyncPacketChannel,
-    login::{AuthenticationServer, LoginTask},
+    capture::ConnectionCapture,
+    common::{AsyncPacketChannel, CookieTrait, DisconnectTrait, StatsCounter, StatsState},
+    login::{server_hash, LoginTask, ServerKeyPair, SessionRequest},
 };
 
 impl LoginTrait for V1_21_0 {
     async fn login(
-        conn: Connection<Self, Login, Clientbound>,
+        mut conn: Connection<Self, Login, Clientbound>,
         channel: AsyncPacketChannel<Self, Login>,
-        _auth_server: AuthenticationServer<Self>,
+        keys: ServerKeyPair,
+        session: Option<SessionRequest>,
+        compression: Option<i32>,
         _resolver: Resolver,
+        capture: ConnectionCapture,
     ) -> Result<Connection<Self, Login, Clientbound>, ConnectionError> {
+        // Encrypt the connection and verify the client
+        if let Some(session) = session {
+            let secret = encrypt(&mut conn, &keys, channel.stats(), &capture).await?;
+
+            let hash = server_hash("", &secret, keys.public_key());
+            match session.verify(&hash).await {
+                Ok(profile) => session.complete(profile).await,
+                Err(err) => {
+                    // Tell the client why before closing the connection
+                    let packet =
+                        LoginDisconnectPacket { reason: SessionRequest::FAILED_REASON.into() };
+                    channel.stats().sent();
+                    capture.sent(StatsState::Login, &packet);
+                    conn.send(packet).await?;
+                    return Err(IoError::other(err).into());
+                }
+            }
+        }
+
         let (mut read, mut write) = conn.into_split();
 
         let finished = AtomicBool::default();
@@ -45,6 +76,20 @@ impl LoginTrait for V1_21_0 {
                             pending.fetch_add(1, Ordering::Relaxed);
                         }
 
+                        // Enable compression before the login succeeds
+                        if let (Some(threshold), LoginClientboundPackets::LoginSuccess(..)) =
+                            (compression, packet.as_ref())
+                        {
+                            let compression =
+                                LoginCompressionPacket { compression_threshold: threshold };
+                            channel.stats().sent();
+                            capture.sent(StatsState::Login, &compression);
+                            write.send(compression).await?;
+                            write.set_compression(Some(threshold));
+                        }
+
+                        channel.stats().sent();
+                        capture.sent(StatsState::Login, packet.as_ref());
                         write.send_packet(&packet).await?;
                     } else {
                         break;
@@ -55,19 +100,24 @@ impl LoginTrait for V1_21_0 {
             async {
                 while !finished.load(Ordering::Relaxed) || pending.load(Ordering::Relaxed) > 0 {
                     let packet = read.recv().await?;
+                    channel.stats().received();
+                    capture.received(StatsState::Login, &packet);
                     #[cfg(debug_assertions)]
                     bevy::log::trace!(
                         "Received login packet: {packet:?}, pending: {}",
                         pending.load(Ordering::Relaxed)
                     );
 
-                    // Decrement the pending counter if the packet is a response
+                    // Decrement the pending counter if the packet is a response,
+                    // ignoring responses that were never requested
                     if matches!(
                         packet,
                         LoginServerboundPackets::CookieResponse(..)
                             | LoginServerboundPackets::LoginQueryResponse(..)
                     ) {
-                        pending.fetch_sub(1, Ordering::Relaxed);
+                        let _ = pending.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                            n.checked_sub(1)
+                        });
                     }
 
                     // If the client enters configuration, we can stop the loop
@@ -90,4 +140,210 @@ impl LoginTrait for V1_21_0 {
     fn send_profile(profile: &GameProfile, task: &LoginTask<Self>) {
         task.send(LoginSuccessPacket { profile: profile.clone(), strict_error_handling: false });
     }
+
+    fn send_query(task: &LoginTask<Self>, id: u32, channel: ResourceKey, data: Vec<u8>) {
+        task.send(LoginQueryRequestPacket { id, identifier: channel, payload: data.into() });
+    }
+
+    fn query_response(packet: &LoginServerboundPackets) -> Option<(u32, Option<&[u8]>)> {
+        match packet {
+            LoginServerboundPackets::LoginQueryResponse(response) => {
+                Some((response.id, response.payload.as_deref()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl DisconnectTrait<Login> for V1_21_0 {
+    fn disconnect_packet(reason: &str) -> LoginClientboundPackets {
+        LoginDisconnectPacket { reason: reason.into() }.into()
+    }
+}
+
+impl CookieTrait<Login> for V1_21_0 {
+    fn request_packet(key: ResourceKey) -> LoginClientboundPackets {
+        CookieRequestPacket { key }.into()
+    }
+
+    fn store_packet(_: ResourceKey, _: Vec<u8>) -> Option<LoginClientboundPackets> { None }
+
+    fn cookie_response(packet: &LoginServerboundPackets) -> Option<(ResourceKey, Option<Vec<u8>>)> {
+        match packet {
+            LoginServerboundPackets::CookieResponse(response) => {
+                Some((response.key.clone(), response.payload.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Exchange a shared secret with the client and enable encryption.
+///
+/// Returns the shared secret.
+async fn encrypt(
+    conn: &mut Connection<V1_21_0, Login, Clientbound>,
+    keys: &ServerKeyPair,
+    stats: &StatsCounter,
+    capture: &ConnectionCapture,
+) -> Result<[u8; 16], ConnectionError> {
+    // Send the public key and a random verify token
+    let token = ServerKeyPair::verify_token();
+    let hello = LoginHelloS2CPacket {
+        server_id: String::new(),
+        public_key: keys.public_key().to_vec(),
+        nonce: token.to_vec(),
+        needs_authentication: true,
+    };
+    stats.sent();
+    capture.sent(StatsState::Login, &hello);
+    conn.send(hello).await?;
+
+    // Receive the encrypted shared secret and verify token
+    let packet = conn.recv().await?;
+    stats.received();
+    capture.received(StatsState::Login, &packet);
+    let LoginServerboundPackets::LoginKey(response) = packet else {
+        return Err(invalid_data("Expected an encryption response"));
+    };
+
+    // Check that the verify token matches
+    if keys.decrypt(&response.nonce).as_deref() != Some(token.as_slice()) {
+        return Err(invalid_data("Verify token does not match"));
+    }
+
+    // Decrypt the shared secret
+    let Some(Ok(secret)) = keys.decrypt(&response.encrypted_secret_key).map(<[u8; 16]>::try_from)
+    else {
+        return Err(invalid_data("Invalid shared secret"));
+    };
+
+    // Enable AES/CFB8 encryption, using the shared secret as the key and IV
+    conn.enable_encryption(&secret);
+
+    Ok(secret)
+}
+
+/// Create a [`ConnectionError`] for invalid data sent by the client.
+fn invalid_data(message: &'static str) -> ConnectionError {
+    ConnectionError::from(IoError::new(ErrorKind::InvalidData, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use froglight::{
+        network::{
+            connection::AccountInformation,
+            versions::v1_21_0::{
+                configuration::ConfigurationServerboundPackets,
+                login::{LoginClientboundPackets, LoginHelloC2SPacket},
+                play::CustomPayloadC2SPacket,
+                V1_21_0,
+            },
+        },
+        prelude::*,
+        protocol::FrogWrite,
+    };
+
+    use crate::{
+        network::{common::Transferred, config::ConfigPacketEvent, login::LoginPlugin},
+        testing::{TestClient, TestServer},
+        ServerPlugins,
+    };
+
+    const THRESHOLD: usize = 64;
+
+    /// The size of every custom payload the server received.
+    #[derive(Debug, Default, Resource, Deref, DerefMut)]
+    struct Payloads(Vec<usize>);
+
+    fn record_payloads(
+        mut events: EventReader<ConfigPacketEvent<V1_21_0>>,
+        mut payloads: ResMut<Payloads>,
+    ) {
+        for event in events.read() {
+            if let ConfigurationServerboundPackets::CustomPayload(packet) = event.packet.as_ref() {
+                payloads.push(packet.payload.len());
+            }
+        }
+    }
+
+    /// Log in with compression and send packets above and below the threshold.
+    #[test]
+    fn compressed_login() {
+        let threshold = i32::try_from(THRESHOLD).unwrap();
+        let plugins = ServerPlugins::localhost().with_compression(threshold);
+        let mut server = TestServer::from_plugins(plugins);
+        server.app().init_resource::<Payloads>().add_systems(Update, record_payloads);
+
+        let client = server.run(|address| async move {
+            let mut client = TestClient::new(address, "Player");
+            let mut conn = client.login().await?;
+
+            for size in [THRESHOLD / 8, THRESHOLD * 16] {
+                conn.send(CustomPayloadC2SPacket {
+                    identifier: ResourceKey::const_new("froglight:test"),
+                    payload: vec![7u8; size].into(),
+                })
+                .await?;
+            }
+
+            client.configure(conn).await.map(|_| client)
+        });
+        let client = client.unwrap();
+
+        // The threshold was sent before the login succeeded
+        let [.., LoginClientboundPackets::LoginCompression(compression), LoginClientboundPackets::LoginSuccess(..)] =
+            client.received.login.as_slice()
+        else {
+            panic!("Expected compression before the login succeeded");
+        };
+        assert_eq!(compression.compression_threshold, threshold);
+
+        // Packets on both sides of the threshold reached the client
+        let sizes: Vec<usize> =
+            client.received.configuration.iter().map(|packet| packet.fg_to_bytes().len()).collect();
+        assert!(sizes.iter().any(|size| *size < THRESHOLD), "No small packets: {sizes:?}");
+        assert!(sizes.iter().any(|size| *size > THRESHOLD), "No large packets: {sizes:?}");
+
+        // And reached the server
+        let payloads = &server.world().resource::<Payloads>().0;
+        assert_eq!(payloads, &[THRESHOLD / 8, THRESHOLD * 16]);
+    }
+
+    /// Log in as a client transferred from another server,
+    /// returning the first login packet that is not a cookie request.
+    async fn transfer(client: TestClient) -> Result<LoginClientboundPackets, ConnectionError> {
+        let mut conn = client.connect(ConnectionIntent::Transfer).await?.login();
+        conn.send(LoginHelloC2SPacket {
+            username: client.username.clone(),
+            uuid: AccountInformation::offline_uuid(&client.username),
+        })
+        .await?;
+
+        loop {
+            match conn.recv().await? {
+                LoginClientboundPackets::CookieRequest(..) => {}
+                packet => return Ok(packet),
+            }
+        }
+    }
+
+    /// Transferred clients are rejected unless transfers are enabled.
+    #[test]
+    fn transfers() {
+        let mut server = TestServer::new();
+        let packet = server.run(|address| transfer(TestClient::new(address, "Player"))).unwrap();
+        let LoginClientboundPackets::LoginDisconnect(packet) = packet else {
+            panic!("Expected the transfer to be rejected, got {packet:?}");
+        };
+        assert_eq!(packet.reason, LoginPlugin::<V1_21_0>::TRANSFERS_DISABLED.into());
+
+        let mut server = TestServer::from_plugins(ServerPlugins::localhost().with_transfers(true));
+        let packet = server.run(|address| transfer(TestClient::new(address, "Player"))).unwrap();
+        assert!(matches!(packet, LoginClientboundPackets::LoginSuccess(..)), "{packet:?}");
+        let mut transferred = server.app().world_mut().query::<&Transferred>();
+        assert_eq!(transferred.iter(server.world()).count(), 1);
+    }
 }
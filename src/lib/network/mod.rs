@@ -1,34 +1,87 @@
 //! TODO
 
-use std::{marker::PhantomData, net::SocketAddr};
+use std::{
+    marker::PhantomData,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
 
 use bevy::{app::PluginGroupBuilder, prelude::*};
 use compact_str::CompactString;
 use froglight::{network::versions::v1_21_0::V1_21_0, prelude::Version};
 
+pub mod capture;
+pub use capture::CapturePlugin;
+
 pub mod common;
 
 pub mod config;
 pub use config::ConfigPlugin;
 
+pub mod diagnostics;
+pub use diagnostics::NetworkDiagnosticsPlugin;
+
+pub mod forwarding;
+pub use forwarding::ForwardingPlugin;
+use forwarding::PlayerForwarding;
+
 pub mod login;
 pub use login::LoginPlugin;
 
 pub mod play;
 pub use play::PlayPlugin;
 
+pub mod query;
+pub use query::QueryPlugin;
+
+pub mod rcon;
+pub use rcon::RconPlugin;
+
 pub mod socket;
+use common::ChannelSettings;
 pub use socket::SocketPlugin;
+use socket::{Motd, ProxyProtocol, RateLimits};
 
 /// A [`PluginGroup`] that adds network-related plugins to the app.
 ///
-/// When given a [`SocketAddr`] it will listen on it for incoming connections.
+/// When given one or more [`SocketAddr`]s, Unix domain sockets,
+/// or the in-memory transport it will listen on them for incoming connections.
 #[derive(Debug)]
 pub struct NetworkPlugins<V: Version> {
     /// The socket to listen on.
     pub socket: Option<SocketAddr>,
+    /// Any additional sockets to listen on.
+    pub extra_sockets: Vec<SocketAddr>,
+    /// The paths of Unix domain sockets to listen on.
+    pub unix: Vec<PathBuf>,
+    /// Whether to accept connections from the in-memory transport.
+    pub memory: bool,
     /// The address of the authentication server.
     pub auth_server: Option<CompactString>,
+    /// The minimum size of a packet before it is compressed.
+    pub compression_threshold: Option<i32>,
+    /// Whether clients transferred from another server are accepted.
+    ///
+    /// Disabled by default, like vanilla servers.
+    pub accept_transfers: bool,
+    /// The message of the day shown in the server list.
+    pub motd: Option<Motd>,
+    /// The path to the server icon.
+    pub favicon: Option<PathBuf>,
+    /// Settings for accepting connections using the `PROXY` protocol.
+    pub proxy: Option<ProxyProtocol>,
+    /// The [`RateLimits`] for incoming connections.
+    pub rate_limits: Option<RateLimits>,
+    /// How player information is forwarded by a proxy.
+    pub forwarding: Option<PlayerForwarding>,
+    /// The path to record a packet capture to.
+    pub capture: Option<PathBuf>,
+    /// The UDP socket to answer query requests on.
+    pub query: Option<SocketAddr>,
+    /// Settings for accepting RCON connections.
+    pub rcon: Option<RconPlugin>,
+    /// The [`ChannelSettings`] for new connections.
+    pub channels: Option<ChannelSettings>,
 
     _phantom: PhantomData<V>,
 }
@@ -42,6 +95,12 @@ impl<V: Version> NetworkPlugins<V> {
     #[must_use]
     pub const fn public() -> Self { Self::from_socket(SocketPlugin::<V1_21_0>::PUBLIC) }
 
+    /// Create a new [`NetworkPlugins`] that listens on `0.0.0.0` and `[::]`.
+    #[must_use]
+    pub fn dual_stack() -> Self {
+        Self::public().with_sockets([SocketPlugin::<V1_21_0>::PUBLIC_V6])
+    }
+
     /// Create a new [`NetworkPlugins`] [`PluginGroup`]
     /// that listens on the given socket.
     #[must_use]
@@ -51,7 +110,33 @@ impl<V: Version> NetworkPlugins<V> {
     /// that listens on the given socket, if it is [`Some`].
     #[must_use]
     pub const fn from_option(socket: Option<SocketAddr>) -> Self {
-        Self { socket, auth_server: None, _phantom: PhantomData }
+        Self {
+            socket,
+            extra_sockets: Vec::new(),
+            unix: Vec::new(),
+            memory: false,
+            auth_server: None,
+            compression_threshold: None,
+            accept_transfers: false,
+            motd: None,
+            favicon: None,
+            proxy: None,
+            rate_limits: None,
+            forwarding: None,
+            capture: None,
+            query: None,
+            rcon: None,
+            channels: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a new [`NetworkPlugins`] [`PluginGroup`]
+    /// that listens on all of the given sockets.
+    #[must_use]
+    pub fn from_sockets(sockets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        let mut sockets = sockets.into_iter();
+        Self::from_option(sockets.next()).with_sockets(sockets)
     }
 
     /// Set the socket to listen on.
@@ -71,6 +156,32 @@ impl<V: Version> NetworkPlugins<V> {
         }
     }
 
+    /// Add another socket to listen on.
+    pub fn add_socket(&mut self, socket: SocketAddr) { self.extra_sockets.push(socket); }
+
+    /// Add more sockets to listen on.
+    #[must_use]
+    pub fn with_sockets(mut self, sockets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.extra_sockets.extend(sockets);
+        self
+    }
+
+    /// Add a Unix domain socket to listen on.
+    #[must_use]
+    pub fn with_unix(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix.push(path.into());
+        self
+    }
+
+    /// Accept connections from the in-memory transport.
+    ///
+    /// See [`MemoryListener`](socket::MemoryListener).
+    #[must_use]
+    pub const fn with_memory(mut self) -> Self {
+        self.memory = true;
+        self
+    }
+
     /// Remove the authentication server.
     #[must_use]
     pub fn with_offline(mut self) -> Self {
@@ -98,6 +209,102 @@ impl<V: Version> NetworkPlugins<V> {
             self
         }
     }
+
+    /// Compress packets larger than the given threshold.
+    #[must_use]
+    pub const fn with_compression(mut self, threshold: i32) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Disable packet compression.
+    #[must_use]
+    pub const fn without_compression(mut self) -> Self {
+        self.compression_threshold = None;
+        self
+    }
+
+    /// Set whether clients transferred from another server are accepted.
+    #[must_use]
+    pub const fn with_transfers(mut self, accept: bool) -> Self {
+        self.accept_transfers = accept;
+        self
+    }
+
+    /// Set the message of the day shown in the server list.
+    #[must_use]
+    pub fn with_motd(mut self, motd: impl Into<Motd>) -> Self {
+        self.motd = Some(motd.into());
+        self
+    }
+
+    /// Set the path to the server icon.
+    #[must_use]
+    pub fn with_favicon(mut self, path: impl Into<PathBuf>) -> Self {
+        self.favicon = Some(path.into());
+        self
+    }
+
+    /// Accept connections using the `PROXY` protocol
+    /// from the given trusted addresses.
+    #[must_use]
+    pub fn with_proxy(mut self, trusted: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.proxy = Some(ProxyProtocol::new(trusted));
+        self
+    }
+
+    /// Rate limit incoming connections using the given [`RateLimits`].
+    #[must_use]
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limits = Some(limits);
+        self
+    }
+
+    /// Trust player information forwarded by a Velocity proxy
+    /// using the given secret.
+    #[must_use]
+    pub fn with_velocity(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.forwarding = Some(PlayerForwarding::velocity(secret));
+        self
+    }
+
+    /// Trust player information forwarded by a BungeeCord proxy.
+    #[must_use]
+    pub fn with_bungeecord(mut self) -> Self {
+        self.forwarding = Some(PlayerForwarding::BungeeCord);
+        self
+    }
+
+    /// Record every packet sent and received by players to the given path.
+    #[must_use]
+    pub fn with_capture(mut self, path: impl Into<PathBuf>) -> Self {
+        self.capture = Some(path.into());
+        self
+    }
+
+    /// Answer query requests on the given UDP socket.
+    #[must_use]
+    pub const fn with_query(mut self, socket: SocketAddr) -> Self {
+        self.query = Some(socket);
+        self
+    }
+
+    /// Accept RCON connections on the given socket using the given password.
+    #[must_use]
+    pub fn with_rcon(mut self, socket: SocketAddr, password: impl Into<CompactString>) -> Self {
+        self.rcon = Some(RconPlugin::new(socket, password));
+        self
+    }
+
+    /// Use the given [`ChannelSettings`] for new connections.
+    ///
+    /// Controls how many packets are queued for each client,
+    /// and how long slow clients are kept before they are disconnected.
+    #[must_use]
+    pub const fn with_channels(mut self, channels: ChannelSettings) -> Self {
+        self.channels = Some(channels);
+        self
+    }
 }
 
 impl<V: Version> Default for NetworkPlugins<V> {
@@ -110,17 +317,52 @@ where
     LoginPlugin<V>: Plugin,
     ConfigPlugin<V>: Plugin,
     PlayPlugin<V>: Plugin,
+    ForwardingPlugin<V>: Plugin,
+    QueryPlugin<V>: Plugin,
 {
     fn build(self) -> PluginGroupBuilder {
         let mut builder = PluginGroupBuilder::start::<Self>();
 
-        // If the `SocketAddr` is set, add the `SocketPlugin`.
-        if let Some(socket) = self.socket {
-            builder = builder.add(SocketPlugin::<V>::from(socket));
+        // If any addresses are set, add the `SocketPlugin`.
+        if self.socket.is_some()
+            || !self.extra_sockets.is_empty()
+            || !self.unix.is_empty()
+            || self.memory
+        {
+            let sockets = self.socket.into_iter().chain(self.extra_sockets);
+            let mut plugin = SocketPlugin::<V>::from_sockets(sockets);
+            plugin.unix = self.unix;
+            plugin.memory = self.memory;
+            plugin.motd = self.motd;
+            plugin.favicon = self.favicon;
+            plugin.proxy = self.proxy;
+            plugin.rate_limits = self.rate_limits;
+            builder = builder.add(plugin);
+        }
+        // If a query socket is set, add the `QueryPlugin`.
+        if let Some(socket) = self.query {
+            builder = builder.add(QueryPlugin::<V>::new(socket));
+        }
+        // If RCON settings are set, add the `RconPlugin`.
+        if let Some(rcon) = self.rcon {
+            builder = builder.add(rcon);
         }
 
-        // Add the `LoginPlugin` using the configured authentication server.
-        builder = builder.add(LoginPlugin::<V>::from_option(self.auth_server));
+        // Add the `LoginPlugin` using the configured authentication server
+        // and compression threshold.
+        let mut login = LoginPlugin::<V>::from_option(self.auth_server);
+        login.compression_threshold = self.compression_threshold;
+        login.accept_transfers = self.accept_transfers;
+        login.channels = self.channels;
+        builder = builder.add(login);
+        // If player forwarding is set, add the `ForwardingPlugin`.
+        if let Some(forwarding) = self.forwarding {
+            builder = builder.add(ForwardingPlugin::<V>::new(forwarding));
+        }
+        // If a capture path is set, add the `CapturePlugin`.
+        if let Some(path) = self.capture {
+            builder = builder.add(CapturePlugin::new(path));
+        }
         // Add the `ConfigPlugin and `PlayPlugin`.
         builder = builder.add(ConfigPlugin::<V>::default()).add(PlayPlugin::<V>::default());
 
@@ -5,7 +5,10 @@ use std::marker::PhantomData;
 use bevy::prelude::*;
 use froglight::{network::connection::NetworkDirection, prelude::*};
 
-use crate::dimension::{subapp::MainAppMarker, All, DimensionApp};
+use crate::{
+    dimension::{subapp::MainAppMarker, All, DimensionApp},
+    shutdown::{ShutdownState, ShutdownSystemSet},
+};
 
 mod version;
 pub use version::PlayTrait;
@@ -15,7 +18,13 @@ mod task;
 mod types;
 pub use types::*;
 
-use super::config::ConfigStateEvent;
+use super::{
+    common::{
+        ChannelSettings, CookieReceived, DisconnectPlayer, PendingCookies, RequestCookie,
+        StoreCookie, TransferPlayer,
+    },
+    config::ConfigStateEvent,
+};
 
 /// A [`Plugin`] that receives configured clients.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -31,7 +40,13 @@ where
         // Add events and initialize resources
         app.add_event::<PlayStateEvent<V>>();
         app.add_event::<PlayClientPacketEvent<V>>();
+        app.add_event::<DisconnectPlayer>();
+        app.add_event::<TransferPlayer>();
+        app.add_event::<RequestCookie>();
+        app.add_event::<StoreCookie>();
+        app.add_event::<CookieReceived>();
         app.init_resource::<PlayFilter<V>>();
+        app.init_resource::<ChannelSettings>();
 
         // Initialize and add required components
         let mut required = PlayRequiredComponents::<V>::new_empty();
@@ -51,7 +66,27 @@ where
         );
         app.add_systems(
             Update,
-            PlayTask::<V>::reconfigure_session
+            (
+                (
+                    PlayFilter::<V>::filter_tasks,
+                    PlayTask::<V>::disconnect_players.run_if(on_event::<DisconnectPlayer>),
+                    PlayTask::<V>::check_queues,
+                ),
+                PlayTask::<V>::reconfigure_session,
+            )
+                .chain()
+                .run_if(any_with_component::<PlayTask<V>>)
+                .ambiguous_with_all(),
+        );
+        app.add_systems(
+            Update,
+            (
+                PlayTask::<V>::transfer_players.run_if(on_event::<TransferPlayer>),
+                PlayTask::<V>::request_cookies.run_if(on_event::<RequestCookie>),
+                PlayTask::<V>::store_cookies.run_if(on_event::<StoreCookie>),
+                PlayTask::<V>::store_pending.run_if(any_with_component::<PendingCookies>),
+                PlayTask::<V>::receive_cookies.run_if(on_event::<PlayClientPacketEvent<V>>),
+            )
                 .run_if(any_with_component::<PlayTask<V>>)
                 .ambiguous_with_all(),
         );
@@ -63,6 +98,13 @@ where
             )
                 .ambiguous_with_all(),
         );
+        app.add_systems(
+            Last,
+            PlayTask::<V>::shutdown_tasks
+                .run_if(ShutdownState::is_disconnecting)
+                .in_set(ShutdownSystemSet::Disconnect)
+                .ambiguous_with_all(),
+        );
 
         // Initialize and insert the shared event queue
         let queue = PlayPacketEventQueue::<V>::default();
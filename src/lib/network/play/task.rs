@@ -9,7 +9,12 @@ use super::{
 };
 use crate::{
     dimension::subapp::{DimensionIdentifier, DimensionMarker, MainAppMarker, SubAppTracker},
-    network::{common::channel, config::ConfigStateEvent, login::ConnectionInstant},
+    network::{
+        capture::ConnectionCapture,
+        common::{channel, ChannelSettings, NetworkStats, StatsState},
+        config::ConfigStateEvent,
+        login::ConnectionInstant,
+    },
 };
 
 impl<V: Version + PlayTrait> PlayTask<V>
@@ -19,25 +24,46 @@ where
     Play: State<V>,
 {
     /// Create a new [`PlayTask`] with the given [`Connection`].
+    ///
+    /// Each direction of the connection holds up to `capacity` packets,
+    /// packets are counted in the [`NetworkStats`]
+    /// and recorded to the [`ConnectionCapture`].
     #[must_use]
-    pub fn new(conn: Connection<V, Configuration, Clientbound>) -> Self {
-        let (send, recv) = channel();
-        Self::spawn(send, V::play(conn.play(), recv))
+    pub fn new(
+        conn: Connection<V, Configuration, Clientbound>,
+        capacity: usize,
+        stats: &NetworkStats,
+        capture: ConnectionCapture,
+    ) -> Self {
+        let (send, recv) = channel(capacity, stats.counter(StatsState::Play));
+        Self::spawn(send, V::play(conn.play(), recv, capture))
     }
 
     /// A system that receives configured connections and
     /// starts play sessions for them.
     pub fn receive_configured(
-        query: Query<(&GameProfile, Option<&ConnectionInstant>)>,
+        query: Query<(
+            &GameProfile,
+            Option<&NetworkStats>,
+            Option<&ConnectionCapture>,
+            Option<&ConnectionInstant>,
+        )>,
         mut events: EventReader<ConfigStateEvent<V>>,
+        channels: Res<ChannelSettings>,
         mut commands: Commands,
     ) {
         for ConfigStateEvent { entity, connection } in events.read() {
-            if let Ok((profile, instant)) = query.get(*entity) {
+            if let Ok((profile, stats, capture, instant)) = query.get(*entity) {
                 if let Some(conn) = connection.lock().take() {
                     // Start the play session
                     let mut commands = commands.entity(*entity);
-                    commands.insert(PlayTask::<V>::new(conn));
+                    let stats = stats.cloned().unwrap_or_else(|| {
+                        let stats = NetworkStats::default();
+                        commands.insert(stats.clone());
+                        stats
+                    });
+                    let capture = capture.cloned().unwrap_or_default();
+                    commands.insert(PlayTask::<V>::new(conn, channels.capacity, &stats, capture));
 
                     if let Some(instant) = instant {
                         // If there is an instant, log the session and duration
@@ -3,20 +3,27 @@ use std::future::Future;
 use froglight::{network::connection::NetworkDirection, prelude::*};
 
 use super::PlayTask;
-use crate::network::common::AsyncPacketChannel;
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{AsyncPacketChannel, CookieTrait, DisconnectTrait, TransferTrait},
+};
 
 mod v1_21_0;
 
 ///  A trait that defines the behavior of playing clients.
-pub trait PlayTrait: Version
+pub trait PlayTrait:
+    Version + DisconnectTrait<Play> + CookieTrait<Play> + TransferTrait<Play>
 where
     Clientbound: NetworkDirection<Self, Play>,
     Play: State<Self>,
 {
     /// An async function that performs the playing process.
+    ///
+    /// Every packet is recorded to the [`ConnectionCapture`].
     fn play(
         conn: Connection<Self, Play, Clientbound>,
         channel: AsyncPacketChannel<Self, Play>,
+        capture: ConnectionCapture,
     ) -> impl Future<Output = Result<Connection<Self, Play, Clientbound>, ConnectionError>> + Send + Sync;
 
     /// Send a reconfigure packet to the client.
@@ -2,19 +2,27 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 use froglight::{
     network::versions::v1_21_0::{
-        play::{EnterReconfigurationPacket, PlayServerboundPackets},
+        play::{
+            CookieRequestPacket, DisconnectPacket, EnterReconfigurationPacket,
+            PlayClientboundPackets, PlayServerboundPackets, StoreCookiePacket, TransferPacket,
+        },
         V1_21_0,
     },
     prelude::*,
 };
 
 use super::PlayTrait;
-use crate::network::{common::AsyncPacketChannel, play::PlayTask};
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{AsyncPacketChannel, CookieTrait, DisconnectTrait, StatsState, TransferTrait},
+    play::PlayTask,
+};
 
 impl PlayTrait for V1_21_0 {
     async fn play(
         conn: Connection<Self, Play, Clientbound>,
         channel: AsyncPacketChannel<Self, Play>,
+        capture: ConnectionCapture,
     ) -> Result<Connection<Self, Play, Clientbound>, ConnectionError> {
         let (mut read, mut write) = conn.into_split();
 
@@ -26,6 +34,8 @@ impl PlayTrait for V1_21_0 {
                     if let Ok(packet) = channel.recv().await {
                         #[cfg(debug_assertions)]
                         bevy::log::trace!("Sending play packet: {packet:?}");
+                        channel.stats().sent();
+                        capture.sent(StatsState::Play, packet.as_ref());
                         write.send_packet(&packet).await?;
                     } else {
                         break;
@@ -36,6 +46,8 @@ impl PlayTrait for V1_21_0 {
             async {
                 while !finished.load(Ordering::Relaxed) {
                     let packet = read.recv().await?;
+                    channel.stats().received();
+                    capture.received(StatsState::Play, &packet);
                     #[cfg(debug_assertions)]
                     bevy::log::trace!("Received config packet: {packet:?}");
 
@@ -58,3 +70,34 @@ impl PlayTrait for V1_21_0 {
 
     fn send_reconfigure(task: &PlayTask<Self>) { task.send(EnterReconfigurationPacket); }
 }
+
+impl DisconnectTrait<Play> for V1_21_0 {
+    fn disconnect_packet(reason: &str) -> PlayClientboundPackets {
+        DisconnectPacket { reason: reason.into() }.into()
+    }
+}
+
+impl CookieTrait<Play> for V1_21_0 {
+    fn request_packet(key: ResourceKey) -> PlayClientboundPackets {
+        CookieRequestPacket { key }.into()
+    }
+
+    fn store_packet(key: ResourceKey, payload: Vec<u8>) -> Option<PlayClientboundPackets> {
+        Some(StoreCookiePacket { key, payload }.into())
+    }
+
+    fn cookie_response(packet: &PlayServerboundPackets) -> Option<(ResourceKey, Option<Vec<u8>>)> {
+        match packet {
+            PlayServerboundPackets::CookieResponse(response) => {
+                Some((response.key.clone(), response.payload.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TransferTrait<Play> for V1_21_0 {
+    fn transfer_packet(host: &str, port: u16) -> PlayClientboundPackets {
+        TransferPacket { host: host.into(), port: port.into() }.into()
+    }
+}
@@ -0,0 +1,83 @@
+//! TODO
+
+use std::{marker::PhantomData, net::SocketAddr, time::Duration};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use froglight::{network::connection::NetworkDirection, prelude::*};
+
+mod packet;
+pub use packet::{ChallengeTokens, QueryInfo, QueryRequest};
+
+mod task;
+pub use task::QueryTask;
+
+use crate::shutdown::{ShutdownState, ShutdownSystemSet};
+
+/// A [`Plugin`] that answers GameSpy4 query requests on a UDP socket.
+///
+/// Handshake, basic stat, and full stat requests are answered using the
+/// [`ServerStatus`] of the [`ListenTask`](super::socket::ListenTask)
+/// and the names of the players online.
+///
+/// This is the protocol used by `enable-query` in vanilla servers.
+/// Unlike game connections, a socket can only answer queries for one version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryPlugin<V: Version> {
+    /// The socket to answer queries on.
+    pub socket: SocketAddr,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> QueryPlugin<V> {
+    /// How often the [`QueryInfo`] is updated.
+    pub const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Create a new [`QueryPlugin`] that answers queries on the given socket.
+    #[must_use]
+    pub const fn new(socket: SocketAddr) -> Self { Self { socket, _phantom: PhantomData } }
+}
+
+impl<V: Version> From<SocketAddr> for QueryPlugin<V> {
+    fn from(socket: SocketAddr) -> Self { Self::new(socket) }
+}
+
+impl<V: Version> Plugin for QueryPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Login> + NetworkDirection<V, Play>,
+    Login: State<V>,
+    Play: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        // Add systems
+        app.add_systems(
+            Update,
+            QueryTask::<V>::update_info
+                .run_if(any_with_component::<QueryTask<V>>)
+                .run_if(on_timer(Self::UPDATE_INTERVAL)),
+        );
+        app.add_systems(
+            PostUpdate,
+            QueryTask::<V>::poll_tasks
+                .run_if(any_with_component::<QueryTask<V>>)
+                .ambiguous_with_all(),
+        );
+        app.add_systems(
+            Last,
+            QueryTask::<V>::stop_listening
+                .run_if(ShutdownState::is_disconnecting)
+                .in_set(ShutdownSystemSet::Disconnect),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        match QueryTask::<V>::bind(self.socket) {
+            Ok(task) => {
+                info!("Answering queries on {}", self.socket);
+                app.world_mut().spawn(task);
+            }
+            Err(err) => {
+                error!("Failed to bind query listener to {}: {err}", self.socket);
+            }
+        }
+    }
+}
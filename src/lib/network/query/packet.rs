@@ -0,0 +1,335 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use bevy::utils::HashMap;
+use compact_str::CompactString;
+use froglight::prelude::ServerStatus;
+use rand::Rng;
+use serde_json::Value;
+
+/// A request sent to a [`QueryTask`](super::QueryTask).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryRequest {
+    /// A request for a challenge token.
+    Handshake {
+        /// The session id of the client.
+        session: i32,
+    },
+    /// A request for the basic server information.
+    BasicStat {
+        /// The session id of the client.
+        session: i32,
+        /// The challenge token sent by the client.
+        token: i32,
+    },
+    /// A request for the full server information and player list.
+    FullStat {
+        /// The session id of the client.
+        session: i32,
+        /// The challenge token sent by the client.
+        token: i32,
+    },
+}
+
+impl QueryRequest {
+    /// The magic bytes at the start of every request.
+    pub const MAGIC: [u8; 2] = [0xFE, 0xFD];
+
+    /// The packet type of handshake requests and responses.
+    pub const HANDSHAKE: u8 = 0x09;
+    /// The packet type of stat requests and responses.
+    pub const STAT: u8 = 0x00;
+
+    /// Parse a request from a datagram.
+    ///
+    /// Returns `None` if the datagram is not a valid request.
+    #[must_use]
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let (magic, bytes) = bytes.split_first_chunk::<2>()?;
+        if *magic != Self::MAGIC {
+            return None;
+        }
+
+        let (kind, bytes) = bytes.split_first()?;
+        let (session, payload) = bytes.split_first_chunk::<4>()?;
+        let session = i32::from_be_bytes(*session);
+
+        match (*kind, payload.len()) {
+            (Self::HANDSHAKE, _) => Some(Self::Handshake { session }),
+            (Self::STAT, 4 | 8) => {
+                let token = i32::from_be_bytes(*payload.first_chunk::<4>()?);
+                if payload.len() == 4 {
+                    Some(Self::BasicStat { session, token })
+                } else {
+                    Some(Self::FullStat { session, token })
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Create the response to a handshake request.
+    #[must_use]
+    pub fn handshake_response(session: i32, token: i32) -> Vec<u8> {
+        let mut response = vec![Self::HANDSHAKE];
+        response.extend(session.to_be_bytes());
+        push_string(&mut response, &token.to_string());
+        response
+    }
+}
+
+/// The information sent in response to stat requests.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryInfo {
+    /// The message of the day, without formatting.
+    pub motd: String,
+    /// The name of the server version.
+    pub version: String,
+    /// The server software and plugins.
+    pub plugins: String,
+    /// The name of the default world.
+    pub map: String,
+    /// The number of players online.
+    pub online: i64,
+    /// The maximum number of players.
+    pub max: i64,
+    /// The names of the players online.
+    pub players: Vec<CompactString>,
+    /// The port players connect to.
+    pub host_port: u16,
+    /// The address players connect to.
+    pub host_ip: String,
+}
+
+impl Default for QueryInfo {
+    fn default() -> Self {
+        Self {
+            motd: String::new(),
+            version: String::new(),
+            plugins: String::from("FrogLight"),
+            map: String::from("world"),
+            online: 0,
+            max: 0,
+            players: Vec::new(),
+            host_port: 25565,
+            host_ip: String::from("127.0.0.1"),
+        }
+    }
+}
+
+impl QueryInfo {
+    /// The game type sent in every response.
+    const GAME_TYPE: &'static str = "SMP";
+    /// The game id sent in full stat responses.
+    const GAME_ID: &'static str = "MINECRAFT";
+    /// The padding before the key-value section of a full stat response.
+    const KEY_VALUE_PADDING: [u8; 11] = *b"splitnum\0\x80\0";
+    /// The padding before the player section of a full stat response.
+    const PLAYER_PADDING: [u8; 10] = *b"\x01player_\0\0";
+
+    /// Update the message of the day, version,
+    /// and player counts from a [`ServerStatus`].
+    pub fn set_status(&mut self, status: &ServerStatus) {
+        self.motd = serde_json::to_value(&status.description)
+            .map(|value| plain_text(&value))
+            .unwrap_or_default();
+        self.version = status.version.name.to_string();
+        self.online = i64::from(status.players.online);
+        self.max = i64::from(status.players.max);
+    }
+
+    /// Create the response to a basic stat request.
+    #[must_use]
+    pub fn basic_stat(&self, session: i32) -> Vec<u8> {
+        let mut response = vec![QueryRequest::STAT];
+        response.extend(session.to_be_bytes());
+        push_string(&mut response, &self.motd);
+        push_string(&mut response, Self::GAME_TYPE);
+        push_string(&mut response, &self.map);
+        push_string(&mut response, &self.online.to_string());
+        push_string(&mut response, &self.max.to_string());
+        response.extend(self.host_port.to_le_bytes());
+        push_string(&mut response, &self.host_ip);
+        response
+    }
+
+    /// Create the response to a full stat request.
+    #[must_use]
+    pub fn full_stat(&self, session: i32) -> Vec<u8> {
+        let mut response = vec![QueryRequest::STAT];
+        response.extend(session.to_be_bytes());
+
+        response.extend(Self::KEY_VALUE_PADDING);
+        for (key, value) in [
+            ("hostname", self.motd.as_str()),
+            ("gametype", Self::GAME_TYPE),
+            ("game_id", Self::GAME_ID),
+            ("version", self.version.as_str()),
+            ("plugins", self.plugins.as_str()),
+            ("map", self.map.as_str()),
+            ("numplayers", self.online.to_string().as_str()),
+            ("maxplayers", self.max.to_string().as_str()),
+            ("hostport", self.host_port.to_string().as_str()),
+            ("hostip", self.host_ip.as_str()),
+        ] {
+            push_string(&mut response, key);
+            push_string(&mut response, value);
+        }
+        response.push(0);
+
+        response.extend(Self::PLAYER_PADDING);
+        for player in &self.players {
+            push_string(&mut response, player);
+        }
+        response.push(0);
+
+        response
+    }
+}
+
+/// Challenge tokens issued to clients.
+///
+/// Stat requests are only answered if they include a token issued to the
+/// same address, which stops spoofed requests from being answered.
+#[derive(Debug, Clone)]
+pub struct ChallengeTokens {
+    tokens: HashMap<SocketAddr, (i32, Instant)>,
+    cleaned: Instant,
+}
+
+impl Default for ChallengeTokens {
+    fn default() -> Self { Self { tokens: HashMap::new(), cleaned: Instant::now() } }
+}
+
+impl ChallengeTokens {
+    /// How long a challenge token is valid for.
+    pub const LIFETIME: Duration = Duration::from_secs(30);
+
+    /// Issue a new challenge token to the given address.
+    pub fn issue(&mut self, address: SocketAddr, now: Instant) -> i32 {
+        // Remove expired tokens
+        if now.saturating_duration_since(self.cleaned) >= Self::LIFETIME {
+            self.tokens
+                .retain(|_, (_, issued)| now.saturating_duration_since(*issued) < Self::LIFETIME);
+            self.cleaned = now;
+        }
+
+        let token = rand::thread_rng().gen_range(0..=i32::MAX);
+        self.tokens.insert(address, (token, now));
+        token
+    }
+
+    /// Returns `true` if the token was issued to
+    /// the given address and has not expired.
+    #[must_use]
+    pub fn check(&self, address: SocketAddr, token: i32, now: Instant) -> bool {
+        self.tokens.get(&address).is_some_and(|(issued_token, issued)| {
+            *issued_token == token && now.saturating_duration_since(*issued) < Self::LIFETIME
+        })
+    }
+}
+
+/// Write a null-terminated string.
+fn push_string(buffer: &mut Vec<u8>, string: &str) {
+    buffer.extend(string.bytes().filter(|byte| *byte != 0));
+    buffer.push(0);
+}
+
+/// Get the plain text of a serialized text component.
+fn plain_text(value: &Value) -> String {
+    fn push_text(value: &Value, text: &mut String) {
+        match value {
+            Value::String(string) => text.push_str(string),
+            Value::Array(values) => values.iter().for_each(|value| push_text(value, text)),
+            Value::Object(object) => {
+                if let Some(Value::String(string)) = object.get("text") {
+                    text.push_str(string);
+                }
+                if let Some(extra) = object.get("extra") {
+                    push_text(extra, text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut text = String::new();
+    push_text(value, &mut text);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        time::Instant,
+    };
+
+    use super::{ChallengeTokens, QueryInfo, QueryRequest};
+
+    #[test]
+    fn requests() {
+        let handshake = [0xFE, 0xFD, 0x09, 0x00, 0x00, 0x00, 0x01];
+        assert_eq!(QueryRequest::parse(&handshake), Some(QueryRequest::Handshake { session: 1 }));
+
+        let basic = [0xFE, 0xFD, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x91, 0x29, 0x5B];
+        assert_eq!(
+            QueryRequest::parse(&basic),
+            Some(QueryRequest::BasicStat { session: 1, token: 9_513_307 })
+        );
+
+        let mut full = basic.to_vec();
+        full.extend([0x00; 4]);
+        assert_eq!(
+            QueryRequest::parse(&full),
+            Some(QueryRequest::FullStat { session: 1, token: 9_513_307 })
+        );
+
+        assert_eq!(QueryRequest::parse(&[0xFE, 0xFD, 0x09, 0x00]), None);
+        assert_eq!(QueryRequest::parse(&[0xFE, 0xFD, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00]), None);
+        assert_eq!(QueryRequest::parse(&[0xFE, 0x01, 0x09, 0x00, 0x00, 0x00, 0x01]), None);
+
+        let response = QueryRequest::handshake_response(1, 9_513_307);
+        assert_eq!(response, b"\x09\x00\x00\x00\x019513307\0");
+    }
+
+    #[test]
+    fn responses() {
+        let info = QueryInfo {
+            motd: String::from("A Minecraft Server"),
+            version: String::from("1.21.1"),
+            online: 2,
+            max: 20,
+            players: vec!["Alice".into(), "Bob".into()],
+            ..QueryInfo::default()
+        };
+
+        let basic = info.basic_stat(1);
+        assert_eq!(
+            basic,
+            b"\x00\x00\x00\x00\x01A Minecraft Server\0SMP\0world\x002\x0020\0\xDD\x63127.0.0.1\0"
+        );
+
+        let full = info.full_stat(1);
+        assert!(full.starts_with(b"\x00\x00\x00\x00\x01splitnum\0\x80\0hostname\0A Minecraft"));
+        assert!(full.ends_with(b"\0\x01player_\0\0Alice\0Bob\0\0"));
+    }
+
+    #[test]
+    fn challenge_tokens() {
+        let mut tokens = ChallengeTokens::default();
+        let alice = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), 25565));
+        let bob = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 2), 25565));
+
+        let now = Instant::now();
+        let token = tokens.issue(alice, now);
+        assert!(tokens.check(alice, token, now));
+        assert!(!tokens.check(bob, token, now));
+        assert!(!tokens.check(alice, token.wrapping_add(1), now));
+
+        // Tokens expire
+        assert!(!tokens.check(alice, token, now + ChallengeTokens::LIFETIME));
+    }
+}
@@ -0,0 +1,196 @@
+use std::{marker::PhantomData, net::SocketAddr, sync::Arc, time::Instant};
+
+use async_std::net::UdpSocket;
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, IoTaskPool, Task},
+};
+use compact_str::CompactString;
+use froglight::{network::connection::NetworkDirection, prelude::*};
+use parking_lot::RwLock;
+
+use super::{ChallengeTokens, QueryInfo, QueryRequest};
+use crate::network::{
+    play::PlayTask,
+    socket::{ListenAddress, ListenTask, RouterTask},
+};
+
+/// A task that answers query requests on a UDP socket.
+///
+/// The [`QueryInfo`] is updated using the [`ListenTask`] for the same version,
+/// see [`QueryTask::update_info`].
+#[derive(Component)]
+pub struct QueryTask<V: Version> {
+    socket: SocketAddr,
+    info: Arc<RwLock<QueryInfo>>,
+    task: Task<()>,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> QueryTask<V> {
+    /// The maximum size of a request.
+    const MAX_REQUEST: usize = 64;
+
+    /// Bind to the given socket and start answering requests.
+    ///
+    /// # Errors
+    /// Returns an error if the [`UdpSocket`] fails to bind to the socket.
+    pub fn bind(socket: SocketAddr) -> Result<Self, std::io::Error> {
+        let udp = block_on(UdpSocket::bind(socket))?;
+        let info = Arc::new(RwLock::new(QueryInfo::default()));
+        let task = IoTaskPool::get().spawn(Self::serve(udp, info.clone()));
+        Ok(Self { socket, info, task, _phantom: PhantomData })
+    }
+
+    /// The socket this task is listening on.
+    #[must_use]
+    pub const fn socket(&self) -> SocketAddr { self.socket }
+
+    /// Get the information sent in response to stat requests.
+    #[must_use]
+    pub fn info(&self) -> &RwLock<QueryInfo> { &self.info }
+
+    /// Answer requests in the background.
+    async fn serve(socket: UdpSocket, info: Arc<RwLock<QueryInfo>>) {
+        let mut tokens = ChallengeTokens::default();
+        let mut buffer = [0u8; Self::MAX_REQUEST];
+
+        loop {
+            let (read, peer) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(err) => {
+                    debug!("Failed to receive query request: {err}");
+                    continue;
+                }
+            };
+
+            let Some(request) = QueryRequest::parse(&buffer[..read]) else {
+                trace!("Received invalid query request from {peer}");
+                continue;
+            };
+
+            let now = Instant::now();
+            let response = match request {
+                QueryRequest::Handshake { session } => {
+                    QueryRequest::handshake_response(session, tokens.issue(peer, now))
+                }
+                QueryRequest::BasicStat { session, token } if tokens.check(peer, token, now) => {
+                    info.read().basic_stat(session)
+                }
+                QueryRequest::FullStat { session, token } if tokens.check(peer, token, now) => {
+                    info.read().full_stat(session)
+                }
+                _ => {
+                    trace!("Received invalid challenge token from {peer}");
+                    continue;
+                }
+            };
+
+            if let Err(err) = socket.send_to(&response, peer).await {
+                debug!("Failed to send query response to {peer}: {err}");
+            }
+        }
+    }
+
+    /// The address advertised to clients querying the given socket.
+    ///
+    /// Prefers the TCP address the server listens on with the same IP as the
+    /// query socket, then the TCP address with the lowest port,
+    /// and finally the query socket itself.
+    #[must_use]
+    pub fn advertised_host(socket: SocketAddr, listening: &[SocketAddr]) -> SocketAddr {
+        listening
+            .iter()
+            .find(|address| address.ip() == socket.ip())
+            .or_else(|| listening.iter().min_by_key(|address| (address.port(), address.ip())))
+            .copied()
+            .unwrap_or(socket)
+    }
+
+    /// A system that updates the [`QueryInfo`] of all [`QueryTask`]s
+    /// from the [`ServerStatus`] and the players online.
+    ///
+    /// The status is taken from the [`ListenTask`] of the same version,
+    /// and the host from [`QueryTask::advertised_host`].
+    pub fn update_info(
+        query: Query<&QueryTask<V>>,
+        players: Query<&GameProfile, With<PlayTask<V>>>,
+        listeners: Query<&ListenTask<V>>,
+        routers: Query<&RouterTask>,
+    ) where
+        Clientbound: NetworkDirection<V, Login> + NetworkDirection<V, Play>,
+        Login: State<V>,
+        Play: State<V>,
+    {
+        let names: Vec<CompactString> =
+            players.iter().map(|profile| profile.username.as_str().into()).collect();
+        let listening: Vec<SocketAddr> = routers
+            .iter()
+            .filter_map(|router| match router.address() {
+                ListenAddress::Tcp(socket) => Some(*socket),
+                _ => None,
+            })
+            .collect();
+
+        // Each version has a single `ListenTask`
+        let listener = listeners.get_single().ok();
+
+        for task in &query {
+            let mut info = task.info.write();
+            if let Some(listener) = listener {
+                info.set_status(&listener.status().read());
+            }
+            info.players.clone_from(&names);
+
+            let host = Self::advertised_host(task.socket, &listening);
+            info.host_port = host.port();
+            info.host_ip = host.ip().to_string();
+        }
+    }
+
+    /// A system that stops all query tasks when the server shuts down.
+    pub fn stop_listening(query: Query<(Entity, &QueryTask<V>)>, mut commands: Commands) {
+        for (entity, task) in &query {
+            info!("Stopped answering queries on {}", task.socket);
+            commands.entity(entity).despawn();
+        }
+    }
+
+    /// A system that polls all query tasks and
+    /// despawns them if they are done.
+    pub fn poll_tasks(mut query: Query<(Entity, &mut QueryTask<V>)>, mut commands: Commands) {
+        for (entity, mut task) in &mut query {
+            if let Some(()) = block_on(poll_once(&mut task.task)) {
+                warn!("Stopped answering queries on {}", task.socket);
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use froglight::network::versions::v1_21_0::V1_21_0;
+
+    use super::QueryTask;
+
+    #[test]
+    fn advertised_host() {
+        let query: SocketAddr = "10.0.0.2:25565".parse().unwrap();
+        let local: SocketAddr = "127.0.0.1:25566".parse().unwrap();
+        let public: SocketAddr = "10.0.0.2:25570".parse().unwrap();
+        let other: SocketAddr = "10.0.0.3:25560".parse().unwrap();
+
+        // The listener sharing the query socket's IP is preferred
+        let host = QueryTask::<V1_21_0>::advertised_host(query, &[local, other, public]);
+        assert_eq!(host, public);
+
+        // Then the one with the lowest port
+        assert_eq!(QueryTask::<V1_21_0>::advertised_host(query, &[local, other]), other);
+
+        // And the query socket if there are no TCP listeners
+        assert_eq!(QueryTask::<V1_21_0>::advertised_host(query, &[]), query);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use bevy::utils::HashMap;
+use compact_str::CompactString;
+use parking_lot::Mutex;
+
+use crate::network::socket::RateLimit;
+
+/// Checks RCON passwords and rate limits failed attempts.
+///
+/// Every failed attempt takes a token from the address's [`RateLimit`]
+/// bucket, and addresses without any tokens left are refused.
+///
+/// Buckets that have fully refilled should be removed
+/// every [`RconAuth::PRUNE_INTERVAL`], see [`RconAuth::prune`].
+#[derive(Debug)]
+pub struct RconAuth {
+    password: CompactString,
+    limit: RateLimit,
+    failures: Mutex<HashMap<IpAddr, (f64, Instant)>>,
+}
+
+impl RconAuth {
+    /// How often buckets that have fully refilled are removed.
+    pub const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Create a new [`RconAuth`] for the given password.
+    #[must_use]
+    pub fn new(password: impl Into<CompactString>, limit: RateLimit) -> Self {
+        Self { password: password.into(), limit, failures: Mutex::default() }
+    }
+
+    /// Check the password sent by the given address.
+    ///
+    /// Returns `false` if the password is wrong
+    /// or the address has failed too many times.
+    pub fn authenticate(&self, address: IpAddr, password: &str, now: Instant) -> bool {
+        if self.is_blocked(address, now) {
+            return false;
+        }
+
+        if constant_time_eq(self.password.as_bytes(), password.as_bytes()) {
+            return true;
+        }
+
+        let mut failures = self.failures.lock();
+        let bucket = failures.entry(address).or_insert((f64::from(self.limit.burst), now));
+        *bucket = ((self.refill(*bucket, now) - 1.0).max(0.0), now);
+        false
+    }
+
+    /// Returns `true` if the address has failed too many times.
+    #[must_use]
+    pub fn is_blocked(&self, address: IpAddr, now: Instant) -> bool {
+        self.failures.lock().get(&address).is_some_and(|bucket| self.refill(*bucket, now) < 1.0)
+    }
+
+    /// Remove the buckets of addresses that have fully refilled,
+    /// as they no longer limit anything.
+    pub fn prune(&self, now: Instant) {
+        let burst = f64::from(self.limit.burst);
+        self.failures.lock().retain(|_, bucket| self.refill(*bucket, now) < burst);
+    }
+
+    /// The number of tokens in a bucket after refilling it.
+    fn refill(&self, (tokens, updated): (f64, Instant), now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(updated);
+        let regained = elapsed.as_secs_f64() / self.limit.interval.as_secs_f64().max(f64::EPSILON);
+        (tokens + regained).min(f64::from(self.limit.burst))
+    }
+}
+
+/// Compare two byte slices in constant time,
+/// so the password can't be guessed using timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use super::RconAuth;
+    use crate::network::socket::RateLimit;
+
+    #[test]
+    fn failed_attempts() {
+        let auth = RconAuth::new("hunter2", RateLimit::new(2, Duration::from_secs(10)));
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let now = Instant::now();
+
+        assert!(auth.authenticate(address, "hunter2", now));
+        assert!(!auth.authenticate(address, "hunter", now));
+        assert!(!auth.authenticate(address, "password", now));
+
+        // Even the right password is refused once blocked
+        assert!(auth.is_blocked(address, now));
+        assert!(!auth.authenticate(address, "hunter2", now));
+        assert!(auth.authenticate(other, "hunter2", now));
+
+        // Blocks wear off
+        let later = now + Duration::from_secs(10);
+        assert!(auth.authenticate(address, "hunter2", later));
+    }
+
+    #[test]
+    fn prune_refilled() {
+        let auth = RconAuth::new("hunter2", RateLimit::new(2, Duration::from_secs(10)));
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let now = Instant::now();
+
+        assert!(!auth.authenticate(address, "hunter", now));
+        assert!(!auth.authenticate(address, "password", now));
+        assert!(!auth.authenticate(other, "hunter", now));
+
+        // Neither bucket has refilled yet
+        auth.prune(now);
+        assert_eq!(auth.failures.lock().len(), 2);
+
+        // Only the bucket with one missing token has refilled
+        auth.prune(now + Duration::from_secs(10));
+        assert!(auth.failures.lock().contains_key(&address));
+        assert!(!auth.failures.lock().contains_key(&other));
+
+        // Both buckets have refilled
+        auth.prune(now + Duration::from_secs(20));
+        assert!(auth.failures.lock().is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! TODO
+
+use std::{net::SocketAddr, time::Duration};
+
+use async_channel::Sender;
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use compact_str::CompactString;
+
+mod auth;
+pub use auth::RconAuth;
+
+mod packet;
+pub use packet::{RconError, RconPacket};
+
+mod task;
+pub use task::RconTask;
+
+use super::socket::RateLimit;
+use crate::shutdown::{ShutdownState, ShutdownSystemSet};
+
+/// A [`Plugin`] that accepts RCON connections on its own socket.
+///
+/// Clients must log in using the password before running commands.
+/// Each command is sent as an [`RconCommandEvent`], and the first response
+/// is sent back to the client.
+///
+/// Failed logins are rate limited per address using the [`RateLimit`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RconPlugin {
+    /// The socket to accept connections on.
+    pub socket: SocketAddr,
+    /// The password clients log in with.
+    pub password: CompactString,
+    /// The [`RateLimit`] for failed logins.
+    pub auth_limit: RateLimit,
+}
+
+impl RconPlugin {
+    /// The default [`RateLimit`] for failed logins.
+    pub const DEFAULT_AUTH_LIMIT: RateLimit = RateLimit::new(3, Duration::from_secs(30));
+
+    /// Create a new [`RconPlugin`] that accepts connections
+    /// on the given socket using the given password.
+    #[must_use]
+    pub fn new(socket: SocketAddr, password: impl Into<CompactString>) -> Self {
+        Self { socket, password: password.into(), auth_limit: Self::DEFAULT_AUTH_LIMIT }
+    }
+
+    /// Set the [`RateLimit`] for failed logins.
+    #[must_use]
+    pub const fn with_auth_limit(mut self, limit: RateLimit) -> Self {
+        self.auth_limit = limit;
+        self
+    }
+
+    /// A system that stops the server when the `stop` command is run.
+    pub fn stop_command(mut events: EventReader<RconCommandEvent>, mut exit: EventWriter<AppExit>) {
+        for event in events.read().filter(|event| event.command.trim() == "stop") {
+            event.respond("Stopping the server");
+            exit.send(AppExit::Success);
+        }
+    }
+}
+
+impl Plugin for RconPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RconCommandEvent>();
+
+        // Add systems
+        app.add_systems(
+            PreUpdate,
+            RconTask::receive_commands.run_if(any_with_component::<RconTask>).ambiguous_with_all(),
+        );
+        app.add_systems(
+            Update,
+            RconPlugin::stop_command.run_if(on_event::<RconCommandEvent>).ambiguous_with_all(),
+        );
+        app.add_systems(
+            Update,
+            RconTask::prune_auth.run_if(on_timer(RconAuth::PRUNE_INTERVAL)).ambiguous_with_all(),
+        );
+        app.add_systems(
+            PostUpdate,
+            RconTask::poll_tasks.run_if(any_with_component::<RconTask>).ambiguous_with_all(),
+        );
+        app.add_systems(
+            Last,
+            RconTask::stop_listening
+                .run_if(ShutdownState::is_disconnecting)
+                .in_set(ShutdownSystemSet::Disconnect),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Like vanilla servers, refuse to start without a password
+        if self.password.is_empty() {
+            error!("Not accepting RCON connections: No password set");
+            return;
+        }
+
+        let auth = RconAuth::new(self.password.clone(), self.auth_limit);
+        match RconTask::bind(self.socket, auth) {
+            Ok(task) => {
+                info!("Accepting RCON connections on {}", self.socket);
+                app.world_mut().spawn(task);
+            }
+            Err(err) => {
+                error!("Failed to bind RCON listener to {}: {err}", self.socket);
+            }
+        }
+    }
+}
+
+/// A command sent by an RCON client.
+///
+/// Systems that handle the command should call [`RconCommandEvent::respond`]
+/// with its output. If no system responds, the client is told the command is
+/// unknown.
+#[derive(Debug, Event)]
+pub struct RconCommandEvent {
+    /// The command, without a leading `/`.
+    pub command: String,
+    /// The address of the client.
+    pub socket: SocketAddr,
+    response: Sender<String>,
+}
+
+impl RconCommandEvent {
+    /// Send the output of the command to the client.
+    ///
+    /// Returns `false` if another system already responded,
+    /// or the client disconnected.
+    pub fn respond(&self, output: impl Into<String>) -> bool {
+        self.response.try_send(output.into()).is_ok()
+    }
+
+    /// Send a command to the app and wait for its output.
+    async fn execute(command: String, socket: SocketAddr, channel: &Sender<Self>) -> String {
+        let command = command.trim_start_matches('/').to_string();
+        let (response, output) = async_channel::bounded(1);
+
+        let event = Self { command: command.clone(), socket, response };
+        if channel.send(event).await.is_err() {
+            return String::from("The server is not accepting commands");
+        }
+
+        // The sender is dropped with the event if no system responds
+        output.recv().await.unwrap_or_else(|_| format!("Unknown command: {command}"))
+    }
+}
@@ -0,0 +1,161 @@
+use futures_lite::{AsyncRead, AsyncReadExt};
+
+/// A packet using the Source RCON framing.
+///
+/// Every packet is prefixed by its length as a little-endian `i32`,
+/// followed by the id, type, and a null-terminated body.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RconPacket {
+    /// The id of the request, which is copied into the response.
+    pub id: i32,
+    /// The type of the packet.
+    pub kind: i32,
+    /// The body of the packet.
+    pub body: String,
+}
+
+/// An error that occurred while reading an [`RconPacket`].
+#[derive(Debug, thiserror::Error)]
+pub enum RconError {
+    /// The packet could not be read.
+    #[error("Failed to read packet: {0}")]
+    Io(#[from] std::io::Error),
+    /// The packet length was out of bounds.
+    #[error("Invalid packet length: {0}")]
+    Length(i32),
+    /// The packet was not valid.
+    #[error("Invalid packet: {0}")]
+    Invalid(&'static str),
+}
+
+impl RconPacket {
+    /// The type of a request to log in.
+    pub const AUTH: i32 = 3;
+    /// The type of a response to an [`RconPacket::AUTH`] request.
+    pub const AUTH_RESPONSE: i32 = 2;
+    /// The type of a request to run a command.
+    pub const EXEC_COMMAND: i32 = 2;
+    /// The type of a response to an [`RconPacket::EXEC_COMMAND`] request.
+    pub const RESPONSE_VALUE: i32 = 0;
+
+    /// The id sent in response to a failed login.
+    pub const AUTH_FAILED: i32 = -1;
+
+    /// The length of a packet with an empty body.
+    const MIN_LENGTH: usize = 10;
+    /// The maximum length of a request.
+    const MAX_LENGTH: usize = 1460;
+    /// The maximum length of a response body.
+    pub const MAX_RESPONSE: usize = 4096;
+
+    /// Create a new [`RconPacket`].
+    #[must_use]
+    pub fn new(id: i32, kind: i32, body: impl Into<String>) -> Self {
+        Self { id, kind, body: body.into() }
+    }
+
+    /// Read a packet from the stream.
+    ///
+    /// # Errors
+    /// Returns an error if the packet could not be read or is invalid.
+    pub async fn read(stream: &mut (impl AsyncRead + Unpin)) -> Result<Self, RconError> {
+        let mut length = [0u8; 4];
+        stream.read_exact(&mut length).await?;
+
+        let length = i32::from_le_bytes(length);
+        let Some(size) = usize::try_from(length)
+            .ok()
+            .filter(|size| (Self::MIN_LENGTH..=Self::MAX_LENGTH).contains(size))
+        else {
+            return Err(RconError::Length(length));
+        };
+
+        let mut data = vec![0u8; size];
+        stream.read_exact(&mut data).await?;
+        Self::decode(&data)
+    }
+
+    /// Decode a packet, without the length prefix.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is invalid.
+    pub fn decode(data: &[u8]) -> Result<Self, RconError> {
+        let (id, data) = data.split_first_chunk::<4>().ok_or(RconError::Invalid("Missing id"))?;
+        let (kind, data) =
+            data.split_first_chunk::<4>().ok_or(RconError::Invalid("Missing type"))?;
+
+        let end = data.iter().position(|byte| *byte == 0);
+        let body = end.map(|end| &data[..end]).ok_or(RconError::Invalid("Unterminated body"))?;
+
+        Ok(Self {
+            id: i32::from_le_bytes(*id),
+            kind: i32::from_le_bytes(*kind),
+            body: String::from_utf8_lossy(body).into_owned(),
+        })
+    }
+
+    /// Encode the packet, including the length prefix.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let length = i32::try_from(Self::MIN_LENGTH + self.body.len()).unwrap_or(i32::MAX);
+
+        let mut data = Vec::with_capacity(Self::MIN_LENGTH + self.body.len() + 4);
+        data.extend(length.to_le_bytes());
+        data.extend(self.id.to_le_bytes());
+        data.extend(self.kind.to_le_bytes());
+        data.extend(self.body.as_bytes());
+        data.extend([0, 0]);
+        data
+    }
+
+    /// Split a command's output into [`RconPacket::RESPONSE_VALUE`] packets
+    /// with bodies no longer than [`RconPacket::MAX_RESPONSE`].
+    #[must_use]
+    pub fn responses(id: i32, output: &str) -> Vec<Self> {
+        let mut responses = Vec::new();
+        let mut rest = output;
+        loop {
+            let mut split = rest.len().min(Self::MAX_RESPONSE);
+            while !rest.is_char_boundary(split) {
+                split -= 1;
+            }
+
+            let (body, remaining) = rest.split_at(split);
+            responses.push(Self::new(id, Self::RESPONSE_VALUE, body));
+            if remaining.is_empty() {
+                return responses;
+            }
+            rest = remaining;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::block_on;
+
+    use super::{RconError, RconPacket};
+
+    #[test]
+    fn framing() {
+        let packet = RconPacket::new(7, RconPacket::EXEC_COMMAND, "list");
+        let encoded = packet.encode();
+        assert_eq!(encoded, b"\x0e\0\0\0\x07\0\0\0\x02\0\0\0list\0\0");
+
+        let decoded = block_on(RconPacket::read(&mut encoded.as_slice())).unwrap();
+        assert_eq!(decoded, packet);
+
+        // Lengths are bounded
+        let long = [0xff, 0xff, 0, 0];
+        let result = block_on(RconPacket::read(&mut long.as_slice()));
+        assert!(matches!(result, Err(RconError::Length(65535))));
+        assert!(RconPacket::decode(b"\x07\0\0\0\x02\0\0\0list").is_err());
+
+        // Long responses are split
+        let output = "a".repeat(RconPacket::MAX_RESPONSE + 1);
+        let responses = RconPacket::responses(7, &output);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[1].body, "a");
+        assert_eq!(RconPacket::responses(7, "").len(), 1);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{net::SocketAddr, sync::Arc, time::Instant};
+
+use async_channel::{Receiver, Sender};
+use async_std::net::{TcpListener, TcpStream};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, IoTaskPool, Task},
+};
+use futures_lite::AsyncWriteExt;
+
+use super::{RconAuth, RconCommandEvent, RconError, RconPacket};
+
+/// A task that accepts RCON connections.
+#[derive(Component)]
+pub struct RconTask {
+    socket: SocketAddr,
+    auth: Arc<RconAuth>,
+    recv: Receiver<RconCommandEvent>,
+    task: Task<()>,
+}
+
+impl RconTask {
+    /// Bind to the given socket and start accepting connections.
+    ///
+    /// # Errors
+    /// Returns an error if the [`TcpListener`] fails to bind to the socket.
+    pub fn bind(socket: SocketAddr, auth: RconAuth) -> Result<Self, std::io::Error> {
+        let listener = block_on(TcpListener::bind(socket))?;
+
+        let auth = Arc::new(auth);
+        let (send, recv) = async_channel::unbounded();
+        let task = IoTaskPool::get().spawn(Self::accept(listener, auth.clone(), send));
+        Ok(Self { socket, auth, recv, task })
+    }
+
+    /// The socket this task is listening on.
+    #[must_use]
+    pub const fn socket(&self) -> SocketAddr { self.socket }
+
+    /// Accept connections and handle them in the background.
+    async fn accept(listener: TcpListener, auth: Arc<RconAuth>, channel: Sender<RconCommandEvent>) {
+        let taskpool = IoTaskPool::get();
+        while let Ok((stream, socket)) = listener.accept().await {
+            trace!("Incoming RCON connection from {socket}");
+
+            // Refuse addresses that have failed to log in too many times.
+            if auth.is_blocked(socket.ip(), Instant::now()) {
+                warn!("Refused RCON connection from {socket}: Too many failed logins");
+                continue;
+            }
+
+            // Spawn a task and detach it.
+            let (auth, channel) = (auth.clone(), channel.clone());
+            taskpool
+                .spawn(async move {
+                    if let Err(err) = Self::handle(stream, socket, &auth, &channel).await {
+                        debug!("RCON connection from {socket} closed: {err}");
+                    }
+                })
+                .detach();
+        }
+    }
+
+    /// Handle a connection until it closes.
+    ///
+    /// Commands are only run after the client logs in.
+    async fn handle(
+        mut stream: TcpStream,
+        socket: SocketAddr,
+        auth: &RconAuth,
+        channel: &Sender<RconCommandEvent>,
+    ) -> Result<(), RconError> {
+        let mut authenticated = false;
+        loop {
+            let packet = RconPacket::read(&mut stream).await?;
+            match packet.kind {
+                RconPacket::AUTH => {
+                    if auth.authenticate(socket.ip(), &packet.body, Instant::now()) {
+                        info!("RCON client {socket} logged in");
+                        authenticated = true;
+
+                        let response = RconPacket::new(packet.id, RconPacket::AUTH_RESPONSE, "");
+                        stream.write_all(&response.encode()).await?;
+                    } else {
+                        warn!("RCON client {socket} failed to log in");
+                        authenticated = false;
+
+                        let response =
+                            RconPacket::new(RconPacket::AUTH_FAILED, RconPacket::AUTH_RESPONSE, "");
+                        stream.write_all(&response.encode()).await?;
+
+                        if auth.is_blocked(socket.ip(), Instant::now()) {
+                            return Err(RconError::Invalid("Too many failed logins"));
+                        }
+                    }
+                }
+                RconPacket::EXEC_COMMAND if authenticated => {
+                    let output = RconCommandEvent::execute(packet.body, socket, channel).await;
+                    for response in RconPacket::responses(packet.id, &output) {
+                        stream.write_all(&response.encode()).await?;
+                    }
+                }
+                _ => {
+                    let response =
+                        RconPacket::new(RconPacket::AUTH_FAILED, RconPacket::AUTH_RESPONSE, "");
+                    stream.write_all(&response.encode()).await?;
+                    return Err(RconError::Invalid("Not logged in"));
+                }
+            }
+        }
+    }
+
+    /// A system that receives commands and sends them as [`RconCommandEvent`]s.
+    pub fn receive_commands(query: Query<&RconTask>, mut events: EventWriter<RconCommandEvent>) {
+        for task in &query {
+            while let Ok(event) = task.recv.try_recv() {
+                info!("RCON client {} ran command: {}", event.socket, event.command);
+                events.send(event);
+            }
+        }
+    }
+
+    /// A system that periodically removes refilled
+    /// failed login buckets, see [`RconAuth::prune`].
+    pub fn prune_auth(query: Query<&RconTask>) {
+        let now = Instant::now();
+        for task in &query {
+            task.auth.prune(now);
+        }
+    }
+
+    /// A system that stops all RCON tasks when the server shuts down.
+    pub fn stop_listening(query: Query<(Entity, &RconTask)>, mut commands: Commands) {
+        for (entity, task) in &query {
+            info!("Stopped accepting RCON connections on {}", task.socket);
+            commands.entity(entity).despawn();
+        }
+    }
+
+    /// A system that polls all RCON tasks and
+    /// despawns them if they are done.
+    pub fn poll_tasks(mut query: Query<(Entity, &mut RconTask)>, mut commands: Commands) {
+        for (entity, mut task) in &mut query {
+            if let Some(()) = block_on(poll_once(&mut task.task)) {
+                warn!("Stopped accepting RCON connections on {}", task.socket);
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
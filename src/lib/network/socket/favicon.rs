@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// The default path of the server icon.
+pub const FAVICON_PATH: &str = "server-icon.png";
+
+/// The width and height a server icon must have.
+pub const FAVICON_SIZE: u32 = 64;
+
+/// The maximum size of a server icon file, in bytes.
+pub const FAVICON_MAX_BYTES: usize = 64 * 1024;
+
+/// The signature at the start of every PNG file.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// An error that occurred while loading a server icon.
+#[derive(Debug, thiserror::Error)]
+pub enum FaviconError {
+    /// The file could not be read.
+    #[error("Failed to read server icon: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is larger than [`FAVICON_MAX_BYTES`].
+    #[error("Server icon is too large: {0} bytes, expected at most {FAVICON_MAX_BYTES}")]
+    TooLarge(usize),
+    /// The file is not a valid PNG image.
+    #[error("Server icon is not a valid PNG image")]
+    NotPng,
+    /// The file is a PNG image, but a chunk is truncated or corrupt.
+    #[error("Server icon is corrupt")]
+    Corrupt,
+    /// The image is not [`FAVICON_SIZE`] pixels wide and tall.
+    #[error("Server icon must be {FAVICON_SIZE}x{FAVICON_SIZE} pixels, found {0}x{1}")]
+    InvalidSize(u32, u32),
+}
+
+/// Read a server icon from the given path and encode it.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is not a valid icon.
+pub fn load_favicon(path: impl AsRef<Path>) -> Result<String, FaviconError> {
+    encode_favicon(&std::fs::read(path)?)
+}
+
+/// Validate a PNG image and encode it as a `data:` URI,
+/// as expected by [`ServerStatus::favicon`].
+///
+/// Every chunk is checked against its CRC, the first chunk must be
+/// the `IHDR` header and the last chunk must be `IEND`.
+///
+/// # Errors
+/// Returns an error if the image is not a valid `64x64` PNG.
+///
+/// [`ServerStatus::favicon`]: froglight::prelude::ServerStatus
+pub fn encode_favicon(bytes: &[u8]) -> Result<String, FaviconError> {
+    if bytes.len() > FAVICON_MAX_BYTES {
+        return Err(FaviconError::TooLarge(bytes.len()));
+    }
+
+    let Some(mut chunks) = bytes.strip_prefix(&PNG_SIGNATURE) else {
+        return Err(FaviconError::NotPng);
+    };
+
+    // Read the width and height from the `IHDR` header
+    let (kind, header) = next_chunk(&mut chunks)?;
+    if kind != *b"IHDR" || header.len() != 13 {
+        return Err(FaviconError::NotPng);
+    }
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    if width != FAVICON_SIZE || height != FAVICON_SIZE {
+        return Err(FaviconError::InvalidSize(width, height));
+    }
+
+    // Check the remaining chunks, up to the `IEND` chunk
+    loop {
+        let (kind, _) = next_chunk(&mut chunks)?;
+        if kind == *b"IEND" {
+            break;
+        }
+    }
+    if !chunks.is_empty() {
+        return Err(FaviconError::Corrupt);
+    }
+
+    Ok(format!("data:image/png;base64,{}", STANDARD.encode(bytes)))
+}
+
+/// Read the next PNG chunk and check its CRC.
+///
+/// Returns the chunk type and data.
+fn next_chunk<'a>(bytes: &mut &'a [u8]) -> Result<([u8; 4], &'a [u8]), FaviconError> {
+    let (length, rest) = bytes.split_first_chunk::<4>().ok_or(FaviconError::Corrupt)?;
+    let length = usize::try_from(u32::from_be_bytes(*length)).map_err(|_| FaviconError::Corrupt)?;
+
+    // The CRC covers the chunk type and data
+    let checked = rest.get(..length + 4).ok_or(FaviconError::Corrupt)?;
+    let (crc, rest) = rest[length + 4..].split_first_chunk::<4>().ok_or(FaviconError::Corrupt)?;
+    if crc32(checked) != u32::from_be_bytes(*crc) {
+        return Err(FaviconError::Corrupt);
+    }
+
+    *bytes = rest;
+    let (kind, data) = checked.split_at(4);
+    Ok((kind.try_into().map_err(|_| FaviconError::Corrupt)?, data))
+}
+
+/// Compute the CRC-32 used by PNG chunks.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, encode_favicon, FaviconError, PNG_SIGNATURE};
+
+    /// Create a PNG chunk with a valid CRC.
+    fn chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut checked = kind.to_vec();
+        checked.extend_from_slice(data);
+
+        let mut bytes = u32::try_from(data.len()).unwrap().to_be_bytes().to_vec();
+        bytes.extend_from_slice(&checked);
+        bytes.extend_from_slice(&crc32(&checked).to_be_bytes());
+        bytes
+    }
+
+    /// Create a PNG file with the given dimensions.
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend(chunk(b"IHDR", &header));
+        bytes.extend(chunk(b"IDAT", &[0; 16]));
+        bytes.extend(chunk(b"IEND", &[]));
+        bytes
+    }
+
+    #[test]
+    fn favicon_validation() {
+        // The CRC of `IEND`, which is the same in every PNG
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+
+        let favicon = encode_favicon(&png(64, 64)).unwrap();
+        assert!(favicon.starts_with("data:image/png;base64,iVBORw0KGgo"));
+
+        assert!(matches!(encode_favicon(&png(128, 64)), Err(FaviconError::InvalidSize(128, 64))));
+        assert!(matches!(encode_favicon(b"GIF89a"), Err(FaviconError::NotPng)));
+
+        // Truncated and corrupt files are rejected
+        let valid = png(64, 64);
+        assert!(matches!(encode_favicon(&valid[..valid.len() - 4]), Err(FaviconError::Corrupt)));
+        let mut corrupt = valid.clone();
+        corrupt[40] ^= 0xff;
+        assert!(matches!(encode_favicon(&corrupt), Err(FaviconError::Corrupt)));
+        assert!(matches!(encode_favicon(&vec![0; 70_000]), Err(FaviconError::TooLarge(70_000))));
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use froglight::{
+    network::connection::NetworkDirection,
+    prelude::{State, *},
+};
+use parking_lot::Mutex;
+
+use super::ConnectionRequest;
+use crate::network::common::FilterResult;
+
+/// A token bucket configuration.
+///
+/// Each address can connect up to `burst` times at once,
+/// and regains one connection every `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    /// The maximum number of connections allowed at once.
+    pub burst: u32,
+    /// The time it takes to regain one connection.
+    pub interval: Duration,
+}
+
+impl RateLimit {
+    /// Create a new [`RateLimit`].
+    #[must_use]
+    pub const fn new(burst: u32, interval: Duration) -> Self { Self { burst, interval } }
+}
+
+/// The [`RateLimit`]s for each connection intent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimits {
+    /// The limit for clients logging in.
+    pub login: RateLimit,
+    /// The limit for clients requesting the server status.
+    pub status: RateLimit,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            login: RateLimit::new(3, Duration::from_secs(4)),
+            status: RateLimit::new(10, Duration::from_secs(1)),
+        }
+    }
+}
+
+/// A per-address rate limiter for incoming connections.
+///
+/// Login attempts are limited by adding [`RateLimiter::filter`] to the
+/// [`SocketFilter`](super::SocketFilter), while status requests are
+/// limited by the [`ListenTask`](super::ListenTask) itself.
+#[derive(Debug, Clone, Resource)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    login: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+    status: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// The number of tokens in the bucket at the given time.
+    fn refill(&self, limit: RateLimit, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let regained = elapsed.as_secs_f64() / limit.interval.as_secs_f64().max(f64::EPSILON);
+        (self.tokens + regained).min(f64::from(limit.burst))
+    }
+}
+
+impl RateLimiter {
+    /// The reason given to clients that are logging in too quickly.
+    pub const DENY_REASON: &'static str = "Connection throttled! Please wait before reconnecting.";
+
+    /// How often buckets that have fully refilled are removed.
+    pub const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Create a new [`RateLimiter`] using the given [`RateLimits`].
+    #[must_use]
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits, login: Arc::default(), status: Arc::default() }
+    }
+
+    /// The [`RateLimits`] used by this [`RateLimiter`].
+    #[must_use]
+    pub const fn limits(&self) -> &RateLimits { &self.limits }
+
+    /// Try to take a login token for the given address.
+    ///
+    /// Returns `false` if the address is connecting too quickly.
+    #[must_use]
+    pub fn try_login(&self, address: IpAddr) -> bool {
+        Self::take(&mut self.login.lock(), self.limits.login, address, Instant::now())
+    }
+
+    /// Try to take a status token for the given address.
+    ///
+    /// Returns `false` if the address is requesting the status too quickly.
+    #[must_use]
+    pub fn try_status(&self, address: IpAddr) -> bool {
+        Self::take(&mut self.status.lock(), self.limits.status, address, Instant::now())
+    }
+
+    /// Remove the buckets of addresses that have fully refilled.
+    pub fn prune(&self) {
+        let now = Instant::now();
+        Self::prune_buckets(&mut self.login.lock(), self.limits.login, now);
+        Self::prune_buckets(&mut self.status.lock(), self.limits.status, now);
+    }
+
+    /// A system that periodically removes full buckets.
+    pub fn prune_buckets_system(limiter: Res<Self>) { limiter.prune(); }
+
+    /// Create a [`SocketFilter`](super::SocketFilter) function that
+    /// denies clients that are logging in too quickly.
+    #[must_use]
+    pub fn filter<V: Version>(
+        &self,
+    ) -> impl Fn(&ConnectionRequest<V>, &World) -> FilterResult + Send + Sync + 'static
+    where
+        Clientbound: NetworkDirection<V, Login>,
+        Login: State<V>,
+    {
+        let limiter = self.clone();
+        move |request: &ConnectionRequest<V>, _: &World| {
+            if limiter.try_login(request.information.socket.ip()) {
+                FilterResult::Allow
+            } else {
+                FilterResult::Deny(Some(Self::DENY_REASON.into()))
+            }
+        }
+    }
+
+    /// Forget addresses that have fully recovered.
+    fn prune_buckets(buckets: &mut HashMap<IpAddr, Bucket>, limit: RateLimit, now: Instant) {
+        let burst = f64::from(limit.burst);
+        buckets.retain(|_, bucket| bucket.refill(limit, now) < burst);
+    }
+
+    /// Refill the bucket for an address and try to take a token.
+    fn take(
+        buckets: &mut HashMap<IpAddr, Bucket>,
+        limit: RateLimit,
+        address: IpAddr,
+        now: Instant,
+    ) -> bool {
+        let burst = f64::from(limit.burst);
+        let bucket = buckets.entry(address).or_insert(Bucket { tokens: burst, updated: now });
+        bucket.tokens = bucket.refill(limit, now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use bevy::utils::HashMap;
+
+    use super::{RateLimit, RateLimiter};
+
+    #[test]
+    fn token_bucket() {
+        let limit = RateLimit::new(2, Duration::from_secs(1));
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+        let mut buckets = HashMap::new();
+        let now = Instant::now();
+
+        // Use the whole burst
+        assert!(RateLimiter::take(&mut buckets, limit, address, now));
+        assert!(RateLimiter::take(&mut buckets, limit, address, now));
+        assert!(!RateLimiter::take(&mut buckets, limit, address, now));
+
+        // Other addresses are not affected
+        assert!(RateLimiter::take(&mut buckets, limit, other, now));
+
+        // Regain a single token
+        let later = now + Duration::from_secs(1);
+        assert!(RateLimiter::take(&mut buckets, limit, address, later));
+        assert!(!RateLimiter::take(&mut buckets, limit, address, later));
+    }
+
+    #[test]
+    fn prune_full_buckets() {
+        let limit = RateLimit::new(2, Duration::from_secs(1));
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+        let mut buckets = HashMap::new();
+        let now = Instant::now();
+        assert!(RateLimiter::take(&mut buckets, limit, address, now));
+        assert!(RateLimiter::take(&mut buckets, limit, address, now));
+        assert!(RateLimiter::take(&mut buckets, limit, other, now));
+
+        // Neither bucket has refilled yet
+        RateLimiter::prune_buckets(&mut buckets, limit, now);
+        assert_eq!(buckets.len(), 2);
+
+        // Only the bucket with one missing token has refilled
+        RateLimiter::prune_buckets(&mut buckets, limit, now + Duration::from_secs(1));
+        assert!(buckets.contains_key(&address));
+        assert!(!buckets.contains_key(&other));
+
+        // Both buckets have refilled
+        RateLimiter::prune_buckets(&mut buckets, limit, now + Duration::from_secs(2));
+        assert!(buckets.is_empty());
+    }
+}
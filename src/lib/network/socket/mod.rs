@@ -2,44 +2,128 @@
 
 use std::{
     marker::PhantomData,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use bevy::prelude::*;
+use bevy::{prelude::*, time::common_conditions::on_timer};
 use froglight::{
     network::connection::NetworkDirection,
     prelude::{State, *},
 };
+use parking_lot::RwLock;
+
+use super::{
+    capture::PacketCapture,
+    common::{DisconnectTrait, GlobalNetworkStats},
+};
+use crate::shutdown::{ShutdownState, ShutdownSystemSet};
 
 mod event;
 pub use event::ConnectionRequestEvent;
 
+mod favicon;
+pub use favicon::{
+    encode_favicon, load_favicon, FaviconError, FAVICON_MAX_BYTES, FAVICON_PATH, FAVICON_SIZE,
+};
+
 mod filter;
 pub use filter::SocketFilter;
 
+mod limiter;
+pub use limiter::{RateLimit, RateLimiter, RateLimits};
+
+mod proxy;
+pub use proxy::{ProxyError, ProxyProtocol};
+
+mod status;
+pub use status::{Motd, StatusError, StatusOverrides};
+
+mod router;
+pub use router::{RouteError, RoutedStream, RouterTask, VersionRouter};
+
+mod transport;
+use transport::Listener;
+pub use transport::{ClientStream, ListenAddress, ListenStream, MemoryListener, MemoryStream};
+
 mod version;
 pub use version::SocketTrait;
 
 mod task;
 pub use task::{ConnectionRequest, ListenTask};
 
-/// A [`Plugin`] that listens on a socket for incoming connections.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A [`Plugin`] that listens on one or more sockets for incoming connections.
+///
+/// Each socket is bound by a [`RouterTask`], which reads the protocol version
+/// from the handshake and sends the connection to that version's
+/// [`ListenTask`]. Adding a [`SocketPlugin`] for multiple versions with the
+/// same sockets serves all of them on the same port.
+///
+/// Connections can also be accepted from Unix domain sockets,
+/// or from the app's [`MemoryListener`] without using a socket at all.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SocketPlugin<V: Version> {
-    socket: SocketAddr,
+    socket: Option<SocketAddr>,
+    extra_sockets: Vec<SocketAddr>,
+    /// The paths of Unix domain sockets to listen on.
+    pub unix: Vec<PathBuf>,
+    /// Whether to accept connections from the [`MemoryListener`].
+    pub memory: bool,
+    /// The message of the day shown in the server list.
+    pub motd: Option<Motd>,
+    /// The path to the server icon.
+    ///
+    /// If [`None`], [`FAVICON_PATH`] is used if it exists.
+    ///
+    /// Building the plugin panics if a configured icon is invalid.
+    pub favicon: Option<PathBuf>,
+    /// Settings for accepting connections from a proxy
+    /// using the `PROXY` protocol.
+    ///
+    /// If [`None`], the `PROXY` protocol is disabled.
+    pub proxy: Option<ProxyProtocol>,
+    /// The [`RateLimits`] for incoming connections.
+    ///
+    /// If [`None`], connections are not rate limited.
+    pub rate_limits: Option<RateLimits>,
     _phantom: PhantomData<V>,
 }
 
 impl<V: Version> SocketPlugin<V> {
     /// Create a new [`SocketPlugin`] that listens on the given socket.
     #[must_use]
-    pub const fn new(socket: SocketAddr) -> Self { Self { socket, _phantom: PhantomData } }
+    pub const fn new(socket: SocketAddr) -> Self { Self::from_option(Some(socket)) }
 
     /// Create a new [`SocketPlugin`] that listens on the given socket.
     #[inline]
     #[must_use]
     pub const fn from_socket(socket: SocketAddr) -> Self { Self::new(socket) }
 
+    /// Create a new [`SocketPlugin`] that listens on all of the given sockets.
+    #[must_use]
+    pub fn from_sockets(sockets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        let mut sockets = sockets.into_iter();
+        Self::from_option(sockets.next()).with_sockets(sockets)
+    }
+
+    /// Create a new [`SocketPlugin`] that listens on the given socket,
+    /// if it is [`Some`].
+    #[must_use]
+    pub const fn from_option(socket: Option<SocketAddr>) -> Self {
+        Self {
+            socket,
+            extra_sockets: Vec::new(),
+            unix: Vec::new(),
+            memory: false,
+            motd: None,
+            favicon: None,
+            proxy: None,
+            rate_limits: None,
+            _phantom: PhantomData,
+        }
+    }
+
     /// A [`SocketAddr`] that listens on [`Ipv4Addr::LOCALHOST`]
     /// and port `25565`.
     pub const LOCALHOST: SocketAddr = SocketAddr::new(Self::LOCALHOST_ADDR, 25565);
@@ -55,24 +139,184 @@ impl<V: Version> SocketPlugin<V> {
     pub const PUBLIC: SocketAddr = SocketAddr::new(Self::PUBLIC_ADDR, 25565);
     const PUBLIC_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
 
+    /// A [`SocketAddr`] that listens on [`Ipv6Addr::UNSPECIFIED`]
+    /// and port `25565`.
+    pub const PUBLIC_V6: SocketAddr = SocketAddr::new(Self::PUBLIC_V6_ADDR, 25565);
+    const PUBLIC_V6_ADDR: IpAddr = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+
     /// Create a new [`SocketPlugin`] that listens on [`Self::PUBLIC`].
     #[inline]
     #[must_use]
     pub const fn public() -> Self { Self::from_socket(Self::PUBLIC) }
+
+    /// Set the socket to listen on.
+    #[must_use]
+    pub const fn with_socket(mut self, socket: SocketAddr) -> Self {
+        self.socket = Some(socket);
+        self
+    }
+
+    /// Add another socket to listen on.
+    pub fn add_socket(&mut self, socket: SocketAddr) { self.extra_sockets.push(socket); }
+
+    /// Add more sockets to listen on.
+    #[must_use]
+    pub fn with_sockets(mut self, sockets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.extra_sockets.extend(sockets);
+        self
+    }
+
+    /// The sockets the [`SocketPlugin`] will listen on.
+    pub fn sockets(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.socket.into_iter().chain(self.extra_sockets.iter().copied())
+    }
+
+    /// Add a Unix domain socket to listen on.
+    ///
+    /// Useful for a proxy running on the same machine.
+    #[must_use]
+    pub fn with_unix(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix.push(path.into());
+        self
+    }
+
+    /// Accept connections from the [`MemoryListener`].
+    #[must_use]
+    pub const fn with_memory(mut self) -> Self {
+        self.memory = true;
+        self
+    }
+
+    /// Every [`ListenAddress`] the [`SocketPlugin`] will listen on.
+    #[must_use]
+    pub fn addresses(&self) -> Vec<ListenAddress> {
+        let sockets = self.sockets().map(ListenAddress::Tcp);
+        let unix = self.unix.iter().cloned().map(ListenAddress::Unix);
+        sockets.chain(unix).chain(self.memory.then_some(ListenAddress::Memory)).collect()
+    }
+
+    /// Set the message of the day shown in the server list.
+    ///
+    /// Accepts plain text or a JSON text component, see [`Motd`].
+    #[must_use]
+    pub fn with_motd(mut self, motd: impl Into<Motd>) -> Self {
+        self.motd = Some(motd.into());
+        self
+    }
+
+    /// Set the path to the server icon.
+    #[must_use]
+    pub fn with_favicon(mut self, path: impl Into<PathBuf>) -> Self {
+        self.favicon = Some(path.into());
+        self
+    }
+
+    /// Accept connections using the `PROXY` protocol
+    /// from the given trusted addresses.
+    #[must_use]
+    pub fn with_proxy(mut self, trusted: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.proxy = Some(ProxyProtocol::new(trusted));
+        self
+    }
+
+    /// Rate limit incoming connections using the given [`RateLimits`].
+    #[must_use]
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limits = Some(limits);
+        self
+    }
 }
+impl<V: Version + SocketTrait> SocketPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Login>,
+    Login: State<V>,
+{
+    /// Create the [`ServerStatus`] using the configured
+    /// message of the day and server icon.
+    ///
+    /// If no icon was configured, an invalid [`FAVICON_PATH`]
+    /// is logged and skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the message of the day or
+    /// the configured server icon is invalid.
+    pub fn status(&self) -> Result<ServerStatus, StatusError> {
+        let mut status = V::status();
+
+        if let Some(motd) = &self.motd {
+            motd.apply(&mut status)?;
+        }
+
+        if let Some(path) = &self.favicon {
+            let favicon = load_favicon(path)
+                .map_err(|source| StatusError::Favicon { path: path.clone(), source })?;
+            status.favicon = Some(favicon.into());
+        } else if Path::new(FAVICON_PATH).exists() {
+            match load_favicon(Path::new(FAVICON_PATH)) {
+                Ok(favicon) => status.favicon = Some(favicon.into()),
+                Err(err) => error!("Failed to load \"{FAVICON_PATH}\": {err}"),
+            }
+        }
+
+        Ok(status)
+    }
+}
+
 impl<V: Version> From<SocketAddr> for SocketPlugin<V> {
     fn from(socket: SocketAddr) -> Self { Self::from_socket(socket) }
 }
 
-impl<V: Version + SocketTrait> Plugin for SocketPlugin<V>
+impl<V: Version + SocketTrait + DisconnectTrait<Login>> Plugin for SocketPlugin<V>
 where
     Clientbound: NetworkDirection<V, Login>,
     Login: State<V>,
 {
     fn build(&self, app: &mut App) {
+        // Fail early if the configured status is invalid
+        if let Err(err) = self.status() {
+            panic!("Failed to build SocketPlugin: {err}");
+        }
+
         // Add events and initialize resources
         app.add_event::<ConnectionRequestEvent<V>>();
         app.init_resource::<SocketFilter<V>>();
+        app.init_resource::<GlobalNetworkStats>();
+        app.init_resource::<StatusOverrides>();
+        if self.memory {
+            app.init_resource::<MemoryListener>();
+        }
+
+        // Insert the `RateLimiter` and filter logins
+        if let Some(limits) = self.rate_limits {
+            // Only add the pruning system if another version hasn't already
+            if !app.world().contains_resource::<RateLimiter>() {
+                app.add_systems(
+                    Update,
+                    RateLimiter::prune_buckets_system.run_if(on_timer(RateLimiter::PRUNE_INTERVAL)),
+                );
+            }
+
+            let limiter = RateLimiter::new(limits);
+            app.world_mut().resource_mut::<SocketFilter<V>>().add_filter(limiter.filter::<V>());
+            app.insert_resource(limiter);
+        }
+
+        // Only add `RouterTask` systems if another version hasn't already
+        if !app.world().contains_resource::<VersionRouter>() {
+            app.init_resource::<VersionRouter>();
+            app.add_systems(
+                PostUpdate,
+                RouterTask::poll_tasks
+                    .run_if(any_with_component::<RouterTask>)
+                    .ambiguous_with_all(),
+            );
+            app.add_systems(
+                Last,
+                RouterTask::stop_listening
+                    .run_if(ShutdownState::is_disconnecting)
+                    .in_set(ShutdownSystemSet::Disconnect),
+            );
+        }
 
         // Add systems
         app.add_systems(
@@ -87,16 +331,63 @@ where
                 .run_if(any_with_component::<ListenTask<V>>)
                 .ambiguous_with_all(),
         );
+        app.add_systems(
+            Last,
+            ListenTask::<V>::stop_listening
+                .run_if(ShutdownState::is_disconnecting)
+                .in_set(ShutdownSystemSet::Disconnect),
+        );
     }
 
     fn finish(&self, app: &mut App) {
-        match ListenTask::<V>::new(self.socket, None) {
-            Ok(task) => {
-                app.world_mut().spawn(task);
+        let router = app.world().resource::<VersionRouter>().clone();
+        let limiter = app.world().get_resource::<RateLimiter>().cloned();
+        let overrides = app.world().resource::<StatusOverrides>().clone();
+        let stats = (**app.world().resource::<GlobalNetworkStats>()).clone();
+        let capture = app.world().get_resource::<PacketCapture>().cloned();
+
+        // Receive connections for this version from the router
+        let status = self.status().unwrap_or_else(|err| panic!("{err}"));
+        let status = Arc::new(RwLock::new(status));
+        let task = ListenTask::<V>::new(&router, status, limiter, overrides, stats, capture);
+        app.world_mut().spawn(task);
+        debug!("Accepting {:?} connections (protocol {})", V::default(), V::ID);
+
+        // Bind any addresses that another version hasn't already
+        let addresses = self.addresses();
+        let mut bound = 0usize;
+        for address in &addresses {
+            if !router.claim(address.clone()) {
+                bound += 1;
+                continue;
             }
-            Err(err) => {
-                error!("Failed to spawn {:?} listener: {err}", V::default());
+
+            let (routes, proxy) = (router.clone(), self.proxy.clone());
+            let result = match address {
+                ListenAddress::Tcp(socket) => RouterTask::bind(*socket, routes, proxy),
+                ListenAddress::Unix(path) => RouterTask::bind_unix(path, routes, proxy),
+                ListenAddress::Memory => {
+                    let listener = app.world().resource::<MemoryListener>();
+                    Ok(RouterTask::from_memory(listener, routes, proxy))
+                }
+            };
+
+            match result {
+                Ok(task) => {
+                    info!("Listening on {address}");
+                    app.world_mut().spawn(task);
+                    bound += 1;
+                }
+                Err(err) => {
+                    error!("Failed to bind listener to {address}: {err}");
+                    // Let another version try to bind the address
+                    router.release(address);
+                }
             }
         }
+
+        if bound < addresses.len() {
+            warn!("Bound {bound} of {} listeners", addresses.len());
+        }
     }
 }
@@ -0,0 +1,192 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use futures_lite::{AsyncRead, AsyncReadExt};
+
+/// Settings for accepting connections using the `PROXY` protocol.
+///
+/// When enabled, every connection must start with a `PROXY` protocol
+/// v1 or v2 header, which is used to find the real client address.
+///
+/// Connections from addresses that are not trusted are rejected.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyProtocol {
+    /// The addresses of trusted proxies.
+    pub trusted: Vec<IpAddr>,
+}
+
+/// An error that occurred while reading a `PROXY` protocol header.
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyError {
+    /// The header could not be read.
+    #[error("Failed to read header: {0}")]
+    Io(#[from] std::io::Error),
+    /// The header was not valid.
+    #[error("Invalid header: {0}")]
+    Invalid(&'static str),
+}
+
+impl ProxyProtocol {
+    /// The signature at the start of every v2 header.
+    const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+    /// The maximum length of a v1 header, including the `\r\n`.
+    const V1_MAX_LENGTH: usize = 107;
+
+    /// Create a new [`ProxyProtocol`] that trusts the given addresses.
+    #[must_use]
+    pub fn new(trusted: impl IntoIterator<Item = IpAddr>) -> Self {
+        Self { trusted: trusted.into_iter().collect() }
+    }
+
+    /// Returns `true` if connections from the given address are trusted.
+    #[must_use]
+    pub fn is_trusted(&self, address: IpAddr) -> bool {
+        self.trusted.iter().any(|trusted| *trusted == address.to_canonical())
+    }
+
+    /// Read a `PROXY` protocol header from the stream.
+    ///
+    /// Returns the address of the client,
+    /// or `None` if the proxy did not forward one.
+    ///
+    /// # Errors
+    /// Returns an error if the header could not be read or is invalid.
+    pub async fn read_header(
+        stream: &mut (impl AsyncRead + Unpin),
+    ) -> Result<Option<SocketAddr>, ProxyError> {
+        // Both versions are at least 12 bytes long
+        let mut header = [0u8; 12];
+        stream.read_exact(&mut header).await?;
+
+        if header == Self::V2_SIGNATURE {
+            // Read the version, family and length
+            let mut info = [0u8; 4];
+            stream.read_exact(&mut info).await?;
+
+            let length = u16::from_be_bytes([info[2], info[3]]);
+            let mut body = vec![0u8; usize::from(length)];
+            stream.read_exact(&mut body).await?;
+
+            Self::parse_v2(info[0], info[1], &body)
+        } else if header.starts_with(b"PROXY ") {
+            // Read until the end of the line
+            let mut line = header.to_vec();
+            while !line.ends_with(b"\r\n") {
+                if line.len() >= Self::V1_MAX_LENGTH {
+                    return Err(ProxyError::Invalid("v1 header is too long"));
+                }
+
+                let mut byte = [0u8; 1];
+                stream.read_exact(&mut byte).await?;
+                line.push(byte[0]);
+            }
+
+            Self::parse_v1(&line)
+        } else {
+            Err(ProxyError::Invalid("Missing signature"))
+        }
+    }
+
+    /// Parse a v1 (text) header.
+    fn parse_v1(line: &[u8]) -> Result<Option<SocketAddr>, ProxyError> {
+        let line = std::str::from_utf8(line)
+            .ok()
+            .and_then(|line| line.strip_suffix("\r\n"))
+            .ok_or(ProxyError::Invalid("v1 header is not valid text"))?;
+
+        let mut parts = line.split(' ').skip(1);
+        match parts.next() {
+            Some("TCP4" | "TCP6") => {}
+            Some("UNKNOWN") => return Ok(None),
+            _ => return Err(ProxyError::Invalid("Unknown v1 protocol")),
+        }
+
+        let (Some(source), Some(_), Some(port), Some(_), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(ProxyError::Invalid("Wrong number of v1 fields"));
+        };
+
+        let source = source.parse().map_err(|_| ProxyError::Invalid("Invalid v1 address"))?;
+        let port = port.parse().map_err(|_| ProxyError::Invalid("Invalid v1 port"))?;
+        Ok(Some(SocketAddr::new(source, port)))
+    }
+
+    /// Parse a v2 (binary) header.
+    fn parse_v2(command: u8, family: u8, body: &[u8]) -> Result<Option<SocketAddr>, ProxyError> {
+        if command >> 4 != 2 {
+            return Err(ProxyError::Invalid("Unknown v2 version"));
+        }
+
+        match command & 0x0F {
+            // `LOCAL` connections are health checks from the proxy itself
+            0x0 => return Ok(None),
+            0x1 => {}
+            _ => return Err(ProxyError::Invalid("Unknown v2 command")),
+        }
+
+        match family >> 4 {
+            // IPv4: source (4), destination (4), source port (2), destination port (2)
+            0x1 if body.len() >= 12 => {
+                let source = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+                let port = u16::from_be_bytes([body[8], body[9]]);
+                Ok(Some(SocketAddr::new(IpAddr::V4(source), port)))
+            }
+            // IPv6: source (16), destination (16), source port (2), destination port (2)
+            0x2 if body.len() >= 36 => {
+                let mut source = [0u8; 16];
+                source.copy_from_slice(&body[..16]);
+                let port = u16::from_be_bytes([body[32], body[33]]);
+                Ok(Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(source)), port)))
+            }
+            // Unspecified or unix sockets do not carry an address
+            0x0 | 0x3 => Ok(None),
+            _ => Err(ProxyError::Invalid("Invalid v2 address")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use bevy::tasks::block_on;
+
+    use super::ProxyProtocol;
+
+    /// Parse a header and check that nothing after it was consumed.
+    fn read(bytes: &[u8]) -> Option<SocketAddr> {
+        let stream = [bytes, b"rest".as_slice()].concat();
+        let mut slice = stream.as_slice();
+        let result = block_on(ProxyProtocol::read_header(&mut slice)).unwrap();
+        assert_eq!(slice, b"rest");
+        result
+    }
+
+    #[test]
+    fn proxy_headers() {
+        // Version 1
+        assert_eq!(
+            read(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 25565\r\n"),
+            Some("192.168.0.1:56324".parse().unwrap())
+        );
+        assert_eq!(
+            read(b"PROXY TCP6 ::1 ::1 56324 25565\r\n"),
+            Some("[::1]:56324".parse().unwrap())
+        );
+        assert_eq!(read(b"PROXY UNKNOWN\r\n"), None);
+
+        // Version 2
+        let mut header = ProxyProtocol::V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0x63, 0xDD]);
+        assert_eq!(read(&header), Some("10.0.0.1:8080".parse().unwrap()));
+
+        let mut local = ProxyProtocol::V2_SIGNATURE.to_vec();
+        local.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        assert_eq!(read(&local), None);
+
+        // Invalid
+        let mut slice: &[u8] = b"\x10\x00\x0Clocalhost\x63\xDD\x02";
+        assert!(block_on(ProxyProtocol::read_header(&mut slice)).is_err());
+    }
+}
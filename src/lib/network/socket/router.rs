@@ -0,0 +1,445 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use async_channel::Sender;
+use async_std::future::timeout;
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, IoTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+use parking_lot::RwLock;
+
+use super::{ListenAddress, ListenStream, Listener, MemoryListener, ProxyProtocol};
+
+/// A connection routed to the listener for the client's protocol version.
+#[derive(Debug)]
+pub struct RoutedStream {
+    /// The connection to the client.
+    pub stream: ListenStream,
+    /// The address of the client.
+    pub socket: SocketAddr,
+    /// The protocol version the client sent in its handshake.
+    pub protocol: i32,
+    /// Whether the client's protocol version is supported.
+    ///
+    /// Unsupported clients are routed to the newest version,
+    /// which tells them their client or the server is outdated.
+    pub supported: bool,
+}
+
+/// Routes incoming connections to a [`ListenTask`](super::ListenTask)
+/// based on the protocol version in the client's handshake.
+///
+/// This allows multiple versions to share the same sockets.
+#[derive(Debug, Default, Clone, Resource)]
+pub struct VersionRouter {
+    routes: Arc<RwLock<HashMap<i32, Sender<RoutedStream>>>>,
+    bound: Arc<RwLock<HashSet<ListenAddress>>>,
+}
+
+/// An error that occurred while reading the handshake.
+#[derive(Debug, thiserror::Error)]
+pub enum RouteError {
+    /// The handshake could not be read.
+    #[error("Failed to read handshake: {0}")]
+    Io(#[from] std::io::Error),
+    /// The handshake was not valid.
+    #[error("Invalid handshake: {0}")]
+    Invalid(&'static str),
+}
+
+impl VersionRouter {
+    /// The number of bytes needed to read any handshake's protocol version.
+    const PEEK_LENGTH: usize = 16;
+
+    /// Add a route for the given protocol version.
+    ///
+    /// Replaces any existing route for the same version.
+    pub fn add_route(&self, protocol: i32, sender: Sender<RoutedStream>) {
+        self.routes.write().insert(protocol, sender);
+    }
+
+    /// Get all protocol versions that have a route.
+    #[must_use]
+    pub fn protocols(&self) -> Vec<i32> {
+        let mut protocols: Vec<i32> = self.routes.read().keys().copied().collect();
+        protocols.sort_unstable();
+        protocols
+    }
+
+    /// Mark an address as bound.
+    ///
+    /// Returns `false` if the address was already bound by another version.
+    #[must_use]
+    pub fn claim(&self, address: impl Into<ListenAddress>) -> bool {
+        self.bound.write().insert(address.into())
+    }
+
+    /// Release an address claimed with [`VersionRouter::claim`],
+    /// such as when it failed to bind.
+    ///
+    /// Returns `false` if the address was not claimed.
+    pub fn release(&self, address: &ListenAddress) -> bool { self.bound.write().remove(address) }
+
+    /// Send a stream to the route for the given protocol version.
+    ///
+    /// Unsupported versions are sent to the newest version.
+    async fn route(&self, stream: ListenStream, socket: SocketAddr, protocol: i32) {
+        let (sender, supported) = {
+            let routes = self.routes.read();
+            match routes.get(&protocol) {
+                Some(sender) => (Some(sender.clone()), true),
+                None => {
+                    let newest = routes.iter().max_by_key(|(protocol, _)| **protocol);
+                    (newest.map(|(_, sender)| sender.clone()), false)
+                }
+            }
+        };
+
+        let Some(sender) = sender else {
+            warn!("No listeners for connection from {socket}");
+            return;
+        };
+
+        let routed = RoutedStream { stream, socket, protocol, supported };
+        if let Err(err) = sender.send(routed).await {
+            error!("Failed to route connection from {socket}: {err}");
+        }
+    }
+
+    /// Read the protocol version from the handshake without consuming it.
+    async fn peek_protocol(stream: &mut ListenStream) -> Result<i32, RouteError> {
+        loop {
+            if stream.peek_more(Self::PEEK_LENGTH).await? == 0 {
+                return Err(RouteError::Invalid("Connection closed"));
+            }
+
+            match parse_protocol(stream.peeked())? {
+                Some(protocol) => return Ok(protocol),
+                None if stream.peeked().len() >= Self::PEEK_LENGTH => {
+                    return Err(RouteError::Invalid("Handshake is too long"));
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Parse the protocol version from the start of a handshake packet.
+///
+/// Returns `None` if more bytes are needed.
+fn parse_protocol(bytes: &[u8]) -> Result<Option<i32>, RouteError> {
+    // Legacy server list pings start with `0xFE`
+    if bytes.first() == Some(&0xFE) {
+        return Err(RouteError::Invalid("Legacy ping"));
+    }
+
+    // Packet length, packet id, then the protocol version
+    let Some((_, bytes)) = read_var_int(bytes)? else { return Ok(None) };
+    let Some((id, bytes)) = read_var_int(bytes)? else { return Ok(None) };
+    if id != 0x00 {
+        return Err(RouteError::Invalid("Not a handshake"));
+    }
+    Ok(read_var_int(bytes)?.map(|(protocol, _)| protocol))
+}
+
+/// Read a `VarInt`, returning the value and the remaining bytes.
+///
+/// Returns `None` if more bytes are needed.
+fn read_var_int(bytes: &[u8]) -> Result<Option<(i32, &[u8])>, RouteError> {
+    let mut value = 0u32;
+    for (index, byte) in bytes.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7F) << (index * 7);
+        if byte & 0x80 == 0 {
+            return Ok(Some((i32::from_ne_bytes(value.to_ne_bytes()), &bytes[index + 1..])));
+        }
+    }
+
+    if bytes.len() >= 5 {
+        Err(RouteError::Invalid("VarInt is too long"))
+    } else {
+        Ok(None)
+    }
+}
+
+/// A task that accepts connections on a [`ListenAddress`]
+/// and routes them using a [`VersionRouter`].
+#[derive(Component)]
+pub struct RouterTask {
+    address: ListenAddress,
+    task: Task<()>,
+}
+
+impl RouterTask {
+    /// The timeout for reading the `PROXY` header and handshake.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Bind to the given socket and start routing connections.
+    ///
+    /// If [`ProxyProtocol`] settings are given, connections from untrusted
+    /// addresses are rejected and the client address is read from the
+    /// `PROXY` protocol header.
+    ///
+    /// IPv6 sockets are bound with `IPV6_V6ONLY`,
+    /// so the same port can be bound for both IPv4 and IPv6.
+    ///
+    /// # Errors
+    /// Returns an error if the listener fails to bind to the socket.
+    pub fn bind(
+        socket: SocketAddr,
+        router: VersionRouter,
+        proxy: Option<ProxyProtocol>,
+    ) -> Result<Self, std::io::Error> {
+        let listener = Listener::bind_tcp(socket)?;
+        Ok(Self::spawn(ListenAddress::Tcp(socket), listener, router, proxy))
+    }
+
+    /// Bind to a Unix domain socket at the given path
+    /// and start routing connections.
+    ///
+    /// Each client is given its own [`ListenAddress::local_peer`] address.
+    ///
+    /// If [`ProxyProtocol`] settings are given, every connection must start
+    /// with a `PROXY` protocol header, which is used to find the real client
+    /// address. Proxies are not checked against the trusted addresses,
+    /// as access to the socket is controlled by its file permissions.
+    ///
+    /// A socket left behind at the path is replaced,
+    /// unless another listener is still accepting connections on it.
+    ///
+    /// # Errors
+    /// Returns an error if the listener fails to bind to the path,
+    /// or if Unix domain sockets are not supported.
+    pub fn bind_unix(
+        path: impl Into<PathBuf>,
+        router: VersionRouter,
+        proxy: Option<ProxyProtocol>,
+    ) -> Result<Self, std::io::Error> {
+        let path = path.into();
+        let listener = block_on(Listener::bind_unix(&path))?;
+        Ok(Self::spawn(ListenAddress::Unix(path), listener, router, proxy))
+    }
+
+    /// Start routing connections made to the [`MemoryListener`].
+    ///
+    /// Each client is given its own [`ListenAddress::local_peer`] address,
+    /// unless the `PROXY` protocol is enabled.
+    #[must_use]
+    pub fn from_memory(
+        listener: &MemoryListener,
+        router: VersionRouter,
+        proxy: Option<ProxyProtocol>,
+    ) -> Self {
+        Self::spawn(ListenAddress::Memory, Listener::Memory(listener.clone()), router, proxy)
+    }
+
+    /// Spawn a task that accepts connections from the [`Listener`].
+    fn spawn(
+        address: ListenAddress,
+        listener: Listener,
+        router: VersionRouter,
+        proxy: Option<ProxyProtocol>,
+    ) -> Self {
+        let task = IoTaskPool::get().spawn(Self::accept(listener, router, proxy.map(Arc::new)));
+        Self { address, task }
+    }
+
+    /// The address this task is listening on.
+    #[must_use]
+    pub const fn address(&self) -> &ListenAddress { &self.address }
+
+    /// Accept connections and route them in the background.
+    async fn accept(listener: Listener, router: VersionRouter, proxy: Option<Arc<ProxyProtocol>>) {
+        let taskpool = IoTaskPool::get();
+        while let Ok((mut stream, peer)) = listener.accept().await {
+            let sock = peer.unwrap_or_else(ListenAddress::local_peer);
+            trace!("Incoming connection from {sock}");
+
+            // Reject TCP connections that did not come from a trusted proxy.
+            // Unix domain sockets are protected by their file permissions instead.
+            if peer.is_some() && proxy.as_ref().is_some_and(|proxy| !proxy.is_trusted(sock.ip())) {
+                warn!("Rejected connection from untrusted proxy {sock}");
+                continue;
+            }
+
+            // Spawn a task and detach it.
+            let router = router.clone();
+            let proxied = proxy.is_some();
+
+            let task = taskpool.spawn(async move {
+                let result = timeout(Self::TIMEOUT, async move {
+                    let mut sock = sock;
+
+                    // Read the `PROXY` header and use the client's address.
+                    if proxied {
+                        match ProxyProtocol::read_header(&mut stream).await {
+                            Ok(Some(client)) => {
+                                trace!("Connection from {client} proxied by {sock}");
+                                sock = client;
+                            }
+                            Ok(None) => {}
+                            Err(error) => {
+                                error!("Failed to read proxy header from {sock}: {error}");
+                                return None;
+                            }
+                        }
+                    }
+
+                    // Read the protocol version from the handshake.
+                    match VersionRouter::peek_protocol(&mut stream).await {
+                        Ok(protocol) => Some((stream, sock, protocol)),
+                        Err(error) => {
+                            debug!("Failed to route connection from {sock}: {error}");
+                            None
+                        }
+                    }
+                })
+                .await;
+
+                match result {
+                    Ok(Some((stream, sock, protocol))) => {
+                        router.route(stream, sock, protocol).await;
+                    }
+                    Ok(None) => {}
+                    Err(_) => error!("Connection from {sock} timed out"),
+                }
+            });
+            task.detach();
+        }
+    }
+
+    /// A system that stops all router tasks when the server shuts down.
+    pub fn stop_listening(query: Query<(Entity, &RouterTask)>, mut commands: Commands) {
+        for (entity, task) in &query {
+            info!("Stopped listening on {}", task.address);
+            commands.entity(entity).despawn();
+
+            // Remove the Unix domain socket
+            if let ListenAddress::Unix(path) = &task.address {
+                if let Err(err) = std::fs::remove_file(path) {
+                    debug!("Failed to remove \"{}\": {err}", path.display());
+                }
+            }
+        }
+    }
+
+    /// A system that polls all router tasks and
+    /// despawns them if they are done.
+    pub fn poll_tasks(mut query: Query<(Entity, &mut RouterTask)>, mut commands: Commands) {
+        for (entity, mut task) in &mut query {
+            if let Some(()) = block_on(poll_once(&mut task.task)) {
+                warn!("Stopped listening on {}", task.address);
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use bevy::tasks::{block_on, IoTaskPool, TaskPool};
+    use futures_lite::AsyncWriteExt;
+
+    use super::{parse_protocol, read_var_int, RouterTask, VersionRouter};
+    use crate::network::socket::{ListenAddress, MemoryListener, ProxyProtocol};
+
+    /// Length, id, protocol `767`, then the rest of the handshake
+    const HANDSHAKE: [u8; 8] = [0x10, 0x00, 0xff, 0x05, 0x09, b'l', b'o', b'c'];
+
+    #[test]
+    fn handshake_protocol() {
+        assert_eq!(read_var_int(&[0x00]).unwrap(), Some((0, [].as_slice())));
+        assert_eq!(read_var_int(&[0xff, 0x05, 0x01]).unwrap(), Some((767, [0x01].as_slice())));
+        assert_eq!(read_var_int(&[0xff, 0xff, 0xff, 0xff, 0x0f]).unwrap().unwrap().0, -1);
+        assert_eq!(read_var_int(&[0xff]).unwrap(), None);
+        assert!(read_var_int(&[0xff; 5]).is_err());
+
+        assert_eq!(parse_protocol(&HANDSHAKE).unwrap(), Some(767));
+        assert_eq!(parse_protocol(&HANDSHAKE[..3]).unwrap(), None);
+        assert_eq!(parse_protocol(&[]).unwrap(), None);
+
+        assert!(parse_protocol(&[0x10, 0x01, 0xff, 0x05]).is_err());
+        assert!(parse_protocol(&[0xfe, 0x01, 0xfa]).is_err());
+    }
+
+    #[test]
+    fn claims() {
+        let router = VersionRouter::default();
+        let address = ListenAddress::Tcp(SocketAddr::from(([127, 0, 0, 1], 25565)));
+        assert!(router.claim(address.clone()));
+        assert!(!router.claim(address.clone()));
+
+        // Released addresses can be claimed again
+        assert!(router.release(&address));
+        assert!(!router.release(&address));
+        assert!(router.claim(address));
+    }
+
+    /// Clients without a socket address are each given their own.
+    #[test]
+    fn local_peers() {
+        IoTaskPool::get_or_init(TaskPool::new);
+        let router = VersionRouter::default();
+        let (send, recv) = async_channel::unbounded();
+        router.add_route(767, send);
+
+        let listener = MemoryListener::default();
+        let _task = RouterTask::from_memory(&listener, router, None);
+
+        block_on(async {
+            let mut first = listener.connect().unwrap();
+            first.write_all(&HANDSHAKE).await.unwrap();
+            let mut second = listener.connect().unwrap();
+            second.write_all(&HANDSHAKE).await.unwrap();
+
+            let first = recv.recv().await.unwrap().socket;
+            let second = recv.recv().await.unwrap().socket;
+            assert!(first.ip().is_loopback() && second.ip().is_loopback());
+            assert_ne!(first, second);
+        });
+    }
+
+    /// Unix domain socket clients must send a `PROXY` header when enabled,
+    /// and only sockets that are no longer listened on are replaced.
+    #[cfg(unix)]
+    #[test]
+    fn unix_proxy() {
+        use async_std::os::unix::net::UnixStream;
+        use futures_lite::AsyncReadExt;
+
+        IoTaskPool::get_or_init(TaskPool::new);
+        let router = VersionRouter::default();
+        let (send, recv) = async_channel::unbounded();
+        router.add_route(767, send);
+
+        // A socket left behind is replaced
+        let path = std::env::temp_dir().join(format!("router-{}.sock", std::process::id()));
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let proxy = Some(ProxyProtocol::default());
+        let task = RouterTask::bind_unix(&path, router.clone(), proxy.clone()).unwrap();
+        assert!(RouterTask::bind_unix(&path, router.clone(), proxy).is_err());
+
+        block_on(async {
+            // The address in the header is used
+            let mut stream = UnixStream::connect(&path).await.unwrap();
+            stream.write_all(b"PROXY TCP4 203.0.113.7 127.0.0.1 51234 25565\r\n").await.unwrap();
+            stream.write_all(&HANDSHAKE).await.unwrap();
+            assert_eq!(recv.recv().await.unwrap().socket, "203.0.113.7:51234".parse().unwrap());
+
+            // Connections without a header are closed
+            let mut stream = UnixStream::connect(&path).await.unwrap();
+            stream.write_all(&HANDSHAKE).await.unwrap();
+            stream.shutdown(std::net::Shutdown::Write).unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await.unwrap();
+            assert!(response.is_empty());
+            assert!(recv.try_recv().is_err());
+        });
+
+        drop(task);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use std::{path::PathBuf, sync::Arc};
+
+use bevy::prelude::*;
+use compact_str::CompactString;
+use froglight::prelude::ServerStatus;
+use parking_lot::RwLock;
+
+use super::FaviconError;
+
+/// An error that occurred while creating the [`ServerStatus`].
+#[derive(Debug, thiserror::Error)]
+pub enum StatusError {
+    /// The message of the day is not a valid text component.
+    #[error("Invalid message of the day: {0}")]
+    Motd(#[from] serde_json::Error),
+    /// The server icon failed to load.
+    #[error("Failed to load server icon \"{}\": {source}", path.display())]
+    Favicon {
+        /// The path to the server icon.
+        path: PathBuf,
+        /// The reason the server icon failed to load.
+        source: FaviconError,
+    },
+}
+
+/// The message of the day shown in the server list.
+///
+/// Either plain text, which may contain legacy `§` formatting codes,
+/// or a JSON text component with colors and formatting.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Motd {
+    /// Plain text, which may contain legacy `§` formatting codes.
+    Text(CompactString),
+    /// A serialized JSON text component.
+    Component(CompactString),
+}
+
+impl Motd {
+    /// Create a [`Motd`] from a JSON text component.
+    #[must_use]
+    pub fn component(component: &serde_json::Value) -> Self {
+        Self::Component(component.to_string().into())
+    }
+
+    /// Set the description of the [`ServerStatus`].
+    ///
+    /// # Errors
+    /// Returns an error if the text component is invalid.
+    pub fn apply(&self, status: &mut ServerStatus) -> Result<(), serde_json::Error> {
+        match self {
+            Self::Text(text) => status.description = text.as_str().into(),
+            Self::Component(json) => status.description = serde_json::from_str(json)?,
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for Motd {
+    fn from(text: &str) -> Self { Self::Text(text.into()) }
+}
+
+impl From<String> for Motd {
+    fn from(text: String) -> Self { Self::Text(text.into()) }
+}
+
+impl From<CompactString> for Motd {
+    fn from(text: CompactString) -> Self { Self::Text(text) }
+}
+
+impl From<serde_json::Value> for Motd {
+    fn from(component: serde_json::Value) -> Self { Self::component(&component) }
+}
+
+type OverrideFn = dyn Fn(&str, &mut ServerStatus) + Send + Sync;
+
+/// Functions that change the [`ServerStatus`] shown to a client,
+/// based on the address it sent in its handshake.
+///
+/// As a shared reference, this resource can be
+/// cheaply cloned and accessed in any [`World`].
+#[derive(Default, Clone, Resource)]
+pub struct StatusOverrides(Arc<RwLock<Vec<Box<OverrideFn>>>>);
+
+impl StatusOverrides {
+    /// Add a function to the [`StatusOverrides`].
+    ///
+    /// Functions are called in the order they were added.
+    pub fn add_override<F: Fn(&str, &mut ServerStatus) + Send + Sync + 'static>(
+        &self,
+        function: F,
+    ) {
+        self.0.write().push(Box::new(function));
+    }
+
+    /// Apply every function to the [`ServerStatus`]
+    /// shown to a client that connected to the given address.
+    pub fn apply(&self, address: &str, status: &mut ServerStatus) {
+        for function in self.0.read().iter() {
+            function(address, status);
+        }
+    }
+}
+
+impl std::fmt::Debug for StatusOverrides {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StatusOverrides").field(&self.0.read().len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use froglight::{network::versions::v1_21_0::V1_21_0, prelude::*};
+
+    use super::{Motd, StatusError};
+    use crate::network::socket::{FaviconError, SocketPlugin, SocketTrait};
+
+    #[test]
+    fn motd_component() {
+        let component = serde_json::json!({ "text": "Hello", "color": "green", "bold": true });
+
+        let mut status = V1_21_0::status();
+        Motd::component(&component).apply(&mut status).unwrap();
+        let description = serde_json::to_value(&status.description).unwrap();
+        assert_eq!(description["color"], "green");
+        assert_eq!(description["bold"], true);
+
+        let mut status = V1_21_0::status();
+        assert!(Motd::Component("{".into()).apply(&mut status).is_err());
+    }
+
+    #[test]
+    fn configured_favicon() {
+        // A configured icon that fails to load is an error
+        let plugin = SocketPlugin::<V1_21_0>::localhost().with_favicon("missing-icon.png");
+        let Err(StatusError::Favicon { path, source }) = plugin.status() else {
+            panic!("Expected the missing icon to fail");
+        };
+        assert_eq!(path.to_str(), Some("missing-icon.png"));
+        assert!(matches!(source, FaviconError::Io(..)));
+    }
+}
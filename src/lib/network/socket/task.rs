@@ -1,7 +1,7 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::sync::Arc;
 
-use async_channel::Receiver;
-use async_std::net::TcpListener;
+use async_channel::{Receiver, Sender};
+use async_std::future::timeout;
 use bevy::{
     ecs::system::SystemState,
     prelude::*,
@@ -14,8 +14,14 @@ use froglight::{
 };
 use parking_lot::{Mutex, RwLock};
 
-use super::{ConnectionRequestEvent, SocketFilter, SocketTrait};
-use crate::network::common::FilterResult;
+use super::{
+    ConnectionRequestEvent, RateLimiter, RoutedStream, SocketFilter, SocketTrait, StatusOverrides,
+    VersionRouter,
+};
+use crate::network::{
+    capture::{ConnectionCapture, PacketCapture},
+    common::{disconnect, DisconnectTrait, FilterResult, NetworkStats, DEFAULT_REASON},
+};
 
 /// A task that listens for incoming connections.
 #[derive(Component)]
@@ -34,21 +40,78 @@ where
     Clientbound: NetworkDirection<V, Login>,
     Login: State<V>,
 {
-    /// Create a new [`ListenTask`] that listens on the given socket.
+    /// Create a new [`ListenTask`] that receives connections
+    /// for this version from the [`VersionRouter`].
     ///
-    /// # Errors
-    /// Returns an error if the [`TcpListener`] fails to bind to the socket.
-    pub fn new(socket: SocketAddr, status: Option<ServerStatus>) -> Result<Self, std::io::Error>
+    /// If a [`RateLimiter`] is given, it is used to limit status requests.
+    ///
+    /// Status requests are answered using the [`StatusOverrides`].
+    ///
+    /// Every connection's [`NetworkStats`] also add to the given stats.
+    ///
+    /// If a [`PacketCapture`] is given, every connection is recorded to it.
+    pub fn new(
+        router: &VersionRouter,
+        status: Arc<RwLock<ServerStatus>>,
+        limiter: Option<RateLimiter>,
+        overrides: StatusOverrides,
+        stats: NetworkStats,
+        capture: Option<PacketCapture>,
+    ) -> Self
     where
         V: SocketTrait,
     {
-        let listener = block_on(TcpListener::bind(socket))?;
-        let status = Arc::new(RwLock::new(status.unwrap_or_else(V::status)));
+        let (route_send, route_recv) = async_channel::unbounded();
+        router.add_route(V::ID, route_send);
 
         let (send, recv) = async_channel::unbounded();
-        let task = IoTaskPool::get().spawn(V::listen(listener, status.clone(), send));
+        let task = IoTaskPool::get().spawn(Self::serve(
+            route_recv,
+            status.clone(),
+            limiter,
+            overrides,
+            stats,
+            capture,
+            send,
+        ));
+
+        Self { recv, status, task }
+    }
 
-        Ok(Self { recv, status, task })
+    /// Handle every [`RoutedStream`] in the background.
+    async fn serve(
+        streams: Receiver<RoutedStream>,
+        status: Arc<RwLock<ServerStatus>>,
+        limiter: Option<RateLimiter>,
+        overrides: StatusOverrides,
+        stats: NetworkStats,
+        capture: Option<PacketCapture>,
+        channel: Sender<ConnectionRequest<V>>,
+    ) where
+        V: SocketTrait,
+    {
+        let taskpool = IoTaskPool::get();
+        while let Ok(routed) = streams.recv().await {
+            let socket = routed.socket;
+            let future = V::handle(
+                routed,
+                status.clone(),
+                limiter.clone(),
+                overrides.clone(),
+                stats.connection(),
+                capture.as_ref().map(PacketCapture::connection).unwrap_or_default(),
+                channel.clone(),
+            );
+
+            // Spawn a task and detach it.
+            taskpool
+                .spawn(async move {
+                    if timeout(V::TIMEOUT, future).await.is_err() {
+                        error!("Connection from {socket} timed out");
+                    }
+                })
+                .detach();
+        }
     }
 
     /// Try to receive any incoming connection requests.
@@ -80,10 +143,13 @@ where
     #[must_use]
     pub fn poll(&mut self) -> Option<()> { block_on(poll_once(&mut self.task)) }
 
-    const DEFAULT_REASON: &'static str = "Denied by server";
-
     /// A system that receives, filters, and sends connection request events.
-    pub fn receive_requests(world: &mut World, mut cache: Local<Vec<ConnectionRequestEvent<V>>>) {
+    ///
+    /// Denied clients are sent the reason before being disconnected.
+    pub fn receive_requests(world: &mut World, mut cache: Local<Vec<ConnectionRequestEvent<V>>>)
+    where
+        V: DisconnectTrait<Login>,
+    {
         // Query for all incoming connection requests and filter them.
         {
             let mut state =
@@ -101,11 +167,15 @@ where
                             cache.push(ConnectionRequestEvent { listener: entity, request });
                         }
                         FilterResult::Deny(reason) => {
-                            let reason = reason.as_deref().unwrap_or(Self::DEFAULT_REASON);
+                            let reason = reason.as_deref().unwrap_or(DEFAULT_REASON);
                             warn!(
                                 "Denied connection request from {} ({}): {reason}",
                                 request.username, request.information.socket
                             );
+
+                            if let Some(conn) = request.connection.lock().take() {
+                                disconnect(conn, reason);
+                            }
                         }
                     }
                 }
@@ -117,6 +187,14 @@ where
         world.send_event_batch(cache.drain(..));
     }
 
+    /// A system that stops all listener tasks when the server shuts down.
+    pub fn stop_listening(query: Query<(Entity, &ListenTask<V>)>, mut commands: Commands) {
+        for (entity, _) in &query {
+            debug!("Stopped listening for connections");
+            commands.entity(entity).despawn();
+        }
+    }
+
     /// A system that polls all listener tasks and
     /// despawns them if they are done.
     pub fn poll_tasks(mut query: Query<(Entity, &mut ListenTask<V>)>, mut commands: Commands) {
@@ -140,6 +218,12 @@ where
     pub uuid: Uuid,
     /// Information about the connection.
     pub information: ConnectionInformation,
+    /// Whether the client was transferred from another server.
+    pub transfer: bool,
+    /// The network statistics of the connection.
+    pub stats: NetworkStats,
+    /// The packet capture of the connection.
+    pub capture: ConnectionCapture,
     /// The connection to the server.
     pub connection: Mutex<Option<Connection<V, Login, Clientbound>>>,
 }
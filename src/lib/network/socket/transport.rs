@@ -0,0 +1,370 @@
+use std::{
+    fmt::{Debug, Display},
+    io::{Error as IoError, ErrorKind},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{ready, Context, Poll},
+};
+
+use async_channel::{Receiver, Sender};
+use async_std::net::TcpListener;
+#[cfg(unix)]
+use async_std::os::unix::net::{UnixListener, UnixStream};
+use bevy::prelude::Resource;
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, Stream};
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// An address a [`RouterTask`](super::RouterTask) can listen on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ListenAddress {
+    /// A TCP socket.
+    Tcp(SocketAddr),
+    /// A Unix domain socket at the given path.
+    ///
+    /// Only supported on Unix platforms.
+    Unix(PathBuf),
+    /// The app's [`MemoryListener`].
+    Memory,
+}
+
+impl ListenAddress {
+    /// Create an address for a client connected
+    /// using a Unix domain socket or in memory.
+    ///
+    /// Every call returns a different loopback address,
+    /// so each client is rate limited and banned on its own.
+    ///
+    /// If the `PROXY` protocol is enabled,
+    /// the address in the header is used instead.
+    #[must_use]
+    pub fn local_peer() -> SocketAddr {
+        // Start at `127.0.0.1:0`
+        static NEXT: AtomicU64 = AtomicU64::new(1 << 16);
+
+        let [.., a, b, c, high, low] = NEXT.fetch_add(1, Ordering::Relaxed).to_be_bytes();
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, a, b, c)), u16::from_be_bytes([high, low]))
+    }
+}
+
+impl Display for ListenAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(socket) => write!(f, "{socket}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+            Self::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+impl From<SocketAddr> for ListenAddress {
+    fn from(socket: SocketAddr) -> Self { Self::Tcp(socket) }
+}
+
+/// A stream that a client can connect with.
+pub trait ClientStream: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static {}
+impl<T: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static> ClientStream for T {}
+
+/// A connection accepted by a [`RouterTask`](super::RouterTask).
+///
+/// Bytes read while routing the connection are
+/// kept and read again before the rest of the stream.
+pub struct ListenStream {
+    stream: Box<dyn ClientStream>,
+    peeked: Vec<u8>,
+}
+
+impl ListenStream {
+    /// Create a new [`ListenStream`] from a [`ClientStream`].
+    #[must_use]
+    pub fn new(stream: impl ClientStream) -> Self {
+        Self { stream: Box::new(stream), peeked: Vec::new() }
+    }
+
+    /// The bytes that have been peeked but not read.
+    #[must_use]
+    pub fn peeked(&self) -> &[u8] { &self.peeked }
+
+    /// Peek more bytes from the stream without consuming them,
+    /// until at most `limit` bytes have been peeked.
+    ///
+    /// Returns the number of new bytes, or `0` if the stream is closed.
+    ///
+    /// # Errors
+    /// Returns an error if the stream could not be read.
+    pub async fn peek_more(&mut self, limit: usize) -> Result<usize, IoError> {
+        let start = self.peeked.len();
+        if start >= limit {
+            return Ok(0);
+        }
+
+        self.peeked.resize(limit, 0);
+        let result = self.stream.read(&mut self.peeked[start..]).await;
+        self.peeked.truncate(start + *result.as_ref().unwrap_or(&0));
+        result
+    }
+}
+
+impl Debug for ListenStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListenStream").field("peeked", &self.peeked).finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for ListenStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        if this.peeked.is_empty() {
+            return Pin::new(&mut this.stream).poll_read(cx, buf);
+        }
+
+        let read = buf.len().min(this.peeked.len());
+        buf[..read].copy_from_slice(&this.peeked[..read]);
+        this.peeked.drain(..read);
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl AsyncWrite for ListenStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+/// One end of an in-memory duplex pipe.
+///
+/// Bytes written to one end are read from the other.
+/// Writes never wait, as the pipe is not bounded.
+#[derive(Debug)]
+pub struct MemoryStream {
+    send: Sender<Vec<u8>>,
+    recv: Pin<Box<Receiver<Vec<u8>>>>,
+    buffer: Vec<u8>,
+}
+
+impl MemoryStream {
+    /// Create both ends of a new pipe.
+    #[must_use]
+    pub fn pair() -> (Self, Self) {
+        let (first_send, first_recv) = async_channel::unbounded();
+        let (second_send, second_recv) = async_channel::unbounded();
+        (
+            Self { send: first_send, recv: Box::pin(second_recv), buffer: Vec::new() },
+            Self { send: second_send, recv: Box::pin(first_recv), buffer: Vec::new() },
+        )
+    }
+}
+
+impl AsyncRead for MemoryStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        while this.buffer.is_empty() {
+            match ready!(this.recv.as_mut().poll_next(cx)) {
+                Some(data) => this.buffer = data,
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+
+        let read = buf.len().min(this.buffer.len());
+        buf[..read].copy_from_slice(&this.buffer[..read]);
+        this.buffer.drain(..read);
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl AsyncWrite for MemoryStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        match self.send.try_send(buf.to_vec()) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.send.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A [`Resource`] that clients in the same process
+/// can connect to without using a socket.
+///
+/// Connections are accepted by a [`RouterTask`](super::RouterTask)
+/// created using [`RouterTask::from_memory`](super::RouterTask::from_memory).
+#[derive(Debug, Clone, Resource)]
+pub struct MemoryListener {
+    send: Sender<MemoryStream>,
+    recv: Receiver<MemoryStream>,
+}
+
+impl Default for MemoryListener {
+    fn default() -> Self {
+        let (send, recv) = async_channel::unbounded();
+        Self { send, recv }
+    }
+}
+
+impl MemoryListener {
+    /// Connect to the listener.
+    ///
+    /// The connection waits until the listener accepts it.
+    ///
+    /// # Errors
+    /// Returns an error if the listener is closed.
+    pub fn connect(&self) -> Result<MemoryStream, IoError> {
+        let (client, server) = MemoryStream::pair();
+        match self.send.try_send(server) {
+            Ok(()) => Ok(client),
+            Err(_) => Err(ErrorKind::ConnectionRefused.into()),
+        }
+    }
+
+    /// Wait for the next connection.
+    async fn accept(&self) -> Result<MemoryStream, IoError> {
+        self.recv.recv().await.map_err(|_| ErrorKind::NotConnected.into())
+    }
+}
+
+/// A listener that accepts connections for a [`ListenAddress`].
+pub(super) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+    Memory(MemoryListener),
+}
+
+impl Listener {
+    /// The maximum number of pending connections.
+    const BACKLOG: i32 = 128;
+
+    /// Bind a listener to a TCP socket.
+    ///
+    /// IPv6 sockets only accept IPv6 connections,
+    /// so the same port can also be bound for IPv4.
+    pub(super) fn bind_tcp(socket: SocketAddr) -> Result<Self, IoError> {
+        let listener = Socket::new(Domain::for_address(socket), Type::STREAM, Some(Protocol::TCP))?;
+        if socket.is_ipv6() {
+            listener.set_only_v6(true)?;
+        }
+        #[cfg(unix)]
+        listener.set_reuse_address(true)?;
+
+        listener.bind(&socket.into())?;
+        listener.listen(Self::BACKLOG)?;
+        Ok(Self::Tcp(TcpListener::from(std::net::TcpListener::from(listener))))
+    }
+
+    /// Bind a listener to a Unix domain socket.
+    ///
+    /// A socket left behind at the path is removed first,
+    /// unless another listener is still accepting connections on it.
+    #[cfg(unix)]
+    pub(super) async fn bind_unix(path: &Path) -> Result<Self, IoError> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(IoError::new(
+                    ErrorKind::AddrInUse,
+                    format!("\"{}\" is already being listened on", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Ok(Self::Unix(UnixListener::bind(path.as_os_str()).await?))
+    }
+
+    /// Bind a listener to a Unix domain socket.
+    #[cfg(not(unix))]
+    pub(super) async fn bind_unix(_: &Path) -> Result<Self, IoError> {
+        Err(IoError::new(ErrorKind::Unsupported, "Unix domain sockets are not supported"))
+    }
+
+    /// Wait for the next connection.
+    ///
+    /// Returns the stream and the address of the peer, if it has one.
+    pub(super) async fn accept(&self) -> Result<(ListenStream, Option<SocketAddr>), IoError> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, socket) = listener.accept().await?;
+                Ok((ListenStream::new(stream), Some(socket)))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok((ListenStream::new(stream), None))
+            }
+            Self::Memory(listener) => Ok((ListenStream::new(listener.accept().await?), None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::block_on;
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{ListenStream, MemoryListener};
+
+    #[test]
+    fn memory_stream() {
+        block_on(async {
+            let listener = MemoryListener::default();
+            let mut client = listener.connect().unwrap();
+            let mut server = ListenStream::new(listener.accept().await.unwrap());
+
+            client.write_all(b"hello").await.unwrap();
+            client.write_all(b" world").await.unwrap();
+
+            // Peeked bytes are read again
+            assert_eq!(server.peek_more(3).await.unwrap(), 3);
+            assert_eq!(server.peeked(), b"hel");
+            assert_eq!(server.peek_more(3).await.unwrap(), 0);
+
+            let mut buffer = [0u8; 11];
+            server.read_exact(&mut buffer).await.unwrap();
+            assert_eq!(&buffer, b"hello world");
+
+            // Both directions work, and closing is seen as the end of the stream
+            server.write_all(b"ok").await.unwrap();
+            server.close().await.unwrap();
+
+            let mut response = Vec::new();
+            client.read_to_end(&mut response).await.unwrap();
+            assert_eq!(response, b"ok");
+        });
+    }
+}
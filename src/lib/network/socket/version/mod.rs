@@ -1,11 +1,11 @@
 use std::{future::Future, sync::Arc, time::Duration};
 
 use async_channel::Sender;
-use async_std::net::TcpListener;
 use froglight::{network::connection::NetworkDirection, prelude::*};
 use parking_lot::RwLock;
 
-use super::ConnectionRequest;
+use super::{ConnectionRequest, RateLimiter, RoutedStream, StatusOverrides};
+use crate::network::{capture::ConnectionCapture, common::NetworkStats};
 
 mod v1_21_0;
 
@@ -23,10 +23,30 @@ where
     /// The default status of the server.
     fn status() -> ServerStatus;
 
-    /// An async function that listens for incoming connections.
-    fn listen(
-        listener: TcpListener,
+    /// An async function that handles a [`RoutedStream`].
+    ///
+    /// Login requests are sent to the [`ListenTask`](super::ListenTask)
+    /// using the channel, while status requests are answered directly.
+    ///
+    /// Clients using an unsupported version are told
+    /// their client or the server is outdated.
+    ///
+    /// If a [`RateLimiter`] is given, status requests
+    /// from addresses over the limit are dropped.
+    ///
+    /// Status requests are answered after applying the [`StatusOverrides`]
+    /// for the handshake address.
+    ///
+    /// Packets are counted in the connection's [`NetworkStats`]
+    /// and recorded to its [`ConnectionCapture`],
+    /// which are passed along with the [`ConnectionRequest`].
+    fn handle(
+        routed: RoutedStream,
         status: Arc<RwLock<ServerStatus>>,
+        limiter: Option<RateLimiter>,
+        overrides: StatusOverrides,
+        stats: NetworkStats,
+        capture: ConnectionCapture,
         channel: Sender<ConnectionRequest<Self>>,
-    ) -> impl Future<Output = ()> + Send + Sync;
+    ) -> impl Future<Output = ()> + Send;
 }
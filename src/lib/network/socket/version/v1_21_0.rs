@@ -1,10 +1,8 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use async_channel::Sender;
-use async_std::{future::timeout, net::TcpListener};
 use bevy::{
     log::{debug, error, trace, warn},
-    tasks::IoTaskPool,
     utils::HashMap,
 };
 use froglight::{
@@ -12,7 +10,7 @@ use froglight::{
         connection::ConnectionInformation,
         versions::v1_21_0::{
             handshake::HandshakeServerboundPackets,
-            login::LoginServerboundPackets,
+            login::{LoginDisconnectPacket, LoginServerboundPackets},
             play::PingResultPacket,
             status::{QueryResponsePacket, StatusServerboundPackets},
             V1_21_0,
@@ -23,7 +21,11 @@ use froglight::{
 use parking_lot::{Mutex, RwLock};
 
 use super::SocketTrait;
-use crate::network::socket::ConnectionRequest;
+use crate::network::{
+    capture::ConnectionCapture,
+    common::{NetworkStats, StatsState, StatsStream},
+    socket::{ConnectionRequest, RateLimiter, RoutedStream, StatusOverrides},
+};
 
 impl SocketTrait for V1_21_0 {
     fn status() -> ServerStatus {
@@ -37,56 +39,86 @@ impl SocketTrait for V1_21_0 {
         }
     }
 
-    async fn listen(
-        listener: TcpListener,
+    async fn handle(
+        routed: RoutedStream,
         status: Arc<RwLock<ServerStatus>>,
+        limiter: Option<RateLimiter>,
+        overrides: StatusOverrides,
+        stats: NetworkStats,
+        capture: ConnectionCapture,
         channel: Sender<ConnectionRequest<Self>>,
     ) {
-        let taskpool = IoTaskPool::get();
-        while let Ok((stream, sock)) = listener.accept().await {
-            trace!("Incoming connection from {sock}");
-
-            // Create a connection from the stream.
-            let conn = match Connection::from_async_stream(stream) {
-                Ok(conn) => conn,
-                Err(error) => {
-                    error!("Failed to create connection from {sock}: {error}");
-                    continue;
-                }
-            };
+        let RoutedStream { stream, socket, protocol, supported } = routed;
 
-            // Spawn a task and detach it.
-            let channel = channel.clone();
-            let status = status.clone();
+        // Create a connection from the stream, counting every byte.
+        let conn = match Connection::from_async_stream(StatsStream::new(stream, stats.clone())) {
+            Ok(conn) => conn,
+            Err(error) => {
+                error!("Failed to create connection from {socket}: {error}");
+                return;
+            }
+        };
 
-            let task = taskpool.spawn(async move {
-                if timeout(Self::TIMEOUT, handle(conn, sock, status, channel)).await.is_err() {
-                    error!("Connection from {sock} timed out");
-                }
-            });
-            task.detach();
-        }
+        handle(
+            conn, socket, protocol, supported, status, limiter, overrides, stats, capture, channel,
+        )
+        .await;
     }
 }
 
+#[expect(clippy::too_many_arguments)]
 async fn handle(
     mut conn: Connection<V1_21_0, Handshake, Clientbound>,
     socket: SocketAddr,
+    protocol: i32,
+    supported: bool,
     status: Arc<RwLock<ServerStatus>>,
+    limiter: Option<RateLimiter>,
+    overrides: StatusOverrides,
+    stats: NetworkStats,
+    capture: ConnectionCapture,
     channel: Sender<ConnectionRequest<V1_21_0>>,
 ) {
     let Ok(HandshakeServerboundPackets::Handshake(handshake)) = conn.recv().await else {
         error!("Failed to receive handshake from {socket}");
         return;
     };
+    stats.counter(StatsState::Handshake).received();
+    capture.received(StatsState::Handshake, &handshake);
 
     match handshake.intent {
         ConnectionIntent::Login | ConnectionIntent::Transfer => {
             debug!("Received login intent from {socket}");
 
-            // Receive the login hello packet.
+            // Tell clients using an unsupported version who is outdated.
             let mut conn = conn.login();
-            let Ok(LoginServerboundPackets::LoginHello(hello)) = conn.recv().await else {
+            let counter = stats.counter(StatsState::Login);
+            if !supported {
+                let name = status.read().version.name.clone();
+                let reason = if protocol < V1_21_0::ID {
+                    format!("Outdated client! Please use {name}")
+                } else {
+                    format!("Outdated server! I'm still on {name}")
+                };
+
+                debug!("Disconnecting {socket} using protocol {protocol}: {reason}");
+                let packet = LoginDisconnectPacket { reason: reason.into() };
+                counter.sent();
+                capture.sent(StatsState::Login, &packet);
+                if let Err(err) = conn.send(packet).await {
+                    error!("Failed to send disconnect to {socket}: {err}");
+                }
+                return;
+            }
+
+            // Receive the login hello packet.
+            let Ok(packet) = conn.recv().await else {
+                error!("Failed to receive login hello from {socket}");
+                return;
+            };
+            counter.received();
+            capture.received(StatsState::Login, &packet);
+            let LoginServerboundPackets::LoginHello(hello) = packet else {
                 error!("Failed to receive login hello from {socket}");
                 return;
             };
@@ -97,6 +129,9 @@ async fn handle(
                     username: hello.username,
                     uuid: hello.uuid,
                     information: ConnectionInformation { address: Some(handshake.address), socket },
+                    transfer: matches!(handshake.intent, ConnectionIntent::Transfer),
+                    stats,
+                    capture,
                     connection: Mutex::new(Some(conn)),
                 })
                 .await
@@ -107,17 +142,34 @@ async fn handle(
         ConnectionIntent::Status => {
             debug!("Received status intent from {socket}");
 
+            // Drop the connection if the status is requested too quickly.
+            if limiter.is_some_and(|limiter| !limiter.try_status(socket.ip())) {
+                warn!("Status requests from {socket} are being rate limited");
+                return;
+            }
+
             let mut conn = conn.status();
+            let stats = stats.counter(StatsState::Status);
             let mut counter = 0;
 
             loop {
-                match conn.recv().await {
+                let packet = conn.recv().await;
+                if let Ok(packet) = &packet {
+                    stats.received();
+                    capture.received(StatsState::Status, packet);
+                }
+
+                match packet {
                     // Send a query response.
                     Ok(StatusServerboundPackets::QueryRequest(..)) => {
                         trace!("Received status request from {socket}");
 
-                        let status = status.read().clone();
-                        if let Err(err) = conn.send(QueryResponsePacket { status }).await {
+                        let mut status = status.read().clone();
+                        overrides.apply(&handshake.address, &mut status);
+                        let packet = QueryResponsePacket { status };
+                        stats.sent();
+                        capture.sent(StatsState::Status, &packet);
+                        if let Err(err) = conn.send(packet).await {
                             error!("Failed to send status response to {socket}: {err}");
                             return;
                         }
@@ -126,7 +178,10 @@ async fn handle(
                     Ok(StatusServerboundPackets::QueryPing(request)) => {
                         trace!("Received ping request from {socket}");
 
-                        if let Err(err) = conn.send(PingResultPacket { pong: request.ping }).await {
+                        let packet = PingResultPacket { pong: request.ping };
+                        stats.sent();
+                        capture.sent(StatsState::Status, &packet);
+                        if let Err(err) = conn.send(packet).await {
                             error!("Failed to send pong to {socket}: {err}");
                         }
 
@@ -0,0 +1,316 @@
+use std::{fmt::Write, net::IpAddr};
+
+use chrono::{DateTime, FixedOffset, Local};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+/// An entry in one of the vanilla access list files.
+pub trait AccessEntry: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The key used to look up entries.
+    type Key: std::fmt::Debug + Clone + Ord + std::hash::Hash + Send + Sync + 'static;
+
+    /// The name of the file the entries are stored in.
+    const FILE_NAME: &'static str;
+
+    /// The key of this entry.
+    fn key(&self) -> Self::Key;
+
+    /// Returns `true` if the entry has expired at the given time.
+    fn is_expired(&self, _now: DateTime<FixedOffset>) -> bool { false }
+}
+
+/// The details shared by [`BannedPlayer`] and [`BannedIp`] entries.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BanDetails {
+    /// When the ban was created.
+    #[serde(with = "date")]
+    pub created: DateTime<FixedOffset>,
+    /// Who created the ban.
+    pub source: String,
+    /// When the ban expires, or [`None`] if it is permanent.
+    #[serde(with = "expiry")]
+    pub expires: Option<DateTime<FixedOffset>>,
+    /// The reason shown to the player.
+    pub reason: String,
+}
+
+impl BanDetails {
+    /// The default source of a ban.
+    pub const DEFAULT_SOURCE: &'static str = "Server";
+    /// The default reason for a ban.
+    pub const DEFAULT_REASON: &'static str = "Banned by an operator.";
+
+    /// Create a new permanent [`BanDetails`] created now.
+    #[must_use]
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            created: Local::now().fixed_offset(),
+            source: Self::DEFAULT_SOURCE.to_string(),
+            expires: None,
+            reason: reason.into(),
+        }
+    }
+
+    /// Set who created the ban.
+    #[must_use]
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    /// Set when the ban expires.
+    #[must_use]
+    pub fn with_expiry(mut self, expires: DateTime<FixedOffset>) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Returns `true` if the ban has expired at the given time.
+    #[must_use]
+    pub fn is_expired(&self, now: DateTime<FixedOffset>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Create the message shown to a banned player.
+    #[must_use]
+    pub fn message(&self, header: &str) -> String {
+        let mut message = format!("{header}\nReason: {}", self.reason);
+        if let Some(expires) = self.expires {
+            let expires = expires.format(date::FORMAT);
+            let _ = write!(message, "\nYour ban will be removed on {expires}");
+        }
+        message
+    }
+}
+
+impl Default for BanDetails {
+    fn default() -> Self { Self::new(Self::DEFAULT_REASON) }
+}
+
+/// A player in `banned-players.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BannedPlayer {
+    /// The player's [`Uuid`].
+    pub uuid: Uuid,
+    /// The player's username.
+    pub name: String,
+    /// The details of the ban.
+    #[serde(flatten)]
+    pub ban: BanDetails,
+}
+
+impl BannedPlayer {
+    /// The message shown to banned players.
+    pub const HEADER: &'static str = "You are banned from this server.";
+
+    /// Create a new [`BannedPlayer`].
+    #[must_use]
+    pub fn new(uuid: Uuid, name: impl Into<String>, ban: BanDetails) -> Self {
+        Self { uuid, name: name.into(), ban }
+    }
+}
+
+impl AccessEntry for BannedPlayer {
+    type Key = Uuid;
+    const FILE_NAME: &'static str = "banned-players.json";
+
+    fn key(&self) -> Self::Key { self.uuid }
+
+    fn is_expired(&self, now: DateTime<FixedOffset>) -> bool { self.ban.is_expired(now) }
+}
+
+/// An address in `banned-ips.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BannedIp {
+    /// The banned address.
+    pub ip: IpAddr,
+    /// The details of the ban.
+    #[serde(flatten)]
+    pub ban: BanDetails,
+}
+
+impl BannedIp {
+    /// The message shown to banned addresses.
+    pub const HEADER: &'static str = "Your IP address is banned from this server.";
+
+    /// Create a new [`BannedIp`].
+    #[must_use]
+    pub fn new(ip: IpAddr, ban: BanDetails) -> Self { Self { ip: ip.to_canonical(), ban } }
+}
+
+impl AccessEntry for BannedIp {
+    type Key = IpAddr;
+    const FILE_NAME: &'static str = "banned-ips.json";
+
+    fn key(&self) -> Self::Key { self.ip.to_canonical() }
+
+    fn is_expired(&self, now: DateTime<FixedOffset>) -> bool { self.ban.is_expired(now) }
+}
+
+/// A player in `whitelist.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WhitelistedPlayer {
+    /// The player's [`Uuid`].
+    pub uuid: Uuid,
+    /// The player's username.
+    pub name: String,
+}
+
+impl WhitelistedPlayer {
+    /// Create a new [`WhitelistedPlayer`].
+    #[must_use]
+    pub fn new(uuid: Uuid, name: impl Into<String>) -> Self { Self { uuid, name: name.into() } }
+}
+
+impl AccessEntry for WhitelistedPlayer {
+    type Key = Uuid;
+    const FILE_NAME: &'static str = "whitelist.json";
+
+    fn key(&self) -> Self::Key { self.uuid }
+}
+
+/// A player in `ops.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Operator {
+    /// The player's [`Uuid`].
+    pub uuid: Uuid,
+    /// The player's username.
+    pub name: String,
+    /// The player's permission level, from `1` to `4`.
+    pub level: u8,
+    /// Whether the player can join when the server is full.
+    #[serde(rename = "bypassesPlayerLimit")]
+    pub bypasses_player_limit: bool,
+}
+
+impl Operator {
+    /// The default permission level of an [`Operator`].
+    pub const DEFAULT_LEVEL: u8 = 4;
+
+    /// Create a new [`Operator`] with the default permission level.
+    #[must_use]
+    pub fn new(uuid: Uuid, name: impl Into<String>) -> Self {
+        Self { uuid, name: name.into(), level: Self::DEFAULT_LEVEL, bypasses_player_limit: false }
+    }
+
+    /// Set the player's permission level.
+    #[must_use]
+    pub const fn with_level(mut self, level: u8) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set whether the player can join when the server is full.
+    #[must_use]
+    pub const fn with_bypass(mut self, bypasses_player_limit: bool) -> Self {
+        self.bypasses_player_limit = bypasses_player_limit;
+        self
+    }
+}
+
+impl AccessEntry for Operator {
+    type Key = Uuid;
+    const FILE_NAME: &'static str = "ops.json";
+
+    fn key(&self) -> Self::Key { self.uuid }
+}
+
+/// Dates in the vanilla format, `2024-01-01 12:00:00 +0000`.
+mod date {
+    use super::{DateTime, Deserialize, Deserializer, FixedOffset, Serializer};
+
+    pub(super) const FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+    pub(super) fn serialize<S: Serializer>(
+        date: &DateTime<FixedOffset>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&date.format(FORMAT))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<FixedOffset>, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        DateTime::parse_from_str(&date, FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Expiry dates in the vanilla format, where `forever` never expires.
+mod expiry {
+    use super::{date, DateTime, Deserialize, Deserializer, FixedOffset, Serializer};
+
+    const FOREVER: &str = "forever";
+
+    #[allow(clippy::ref_option)]
+    pub(super) fn serialize<S: Serializer>(
+        expires: &Option<DateTime<FixedOffset>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match expires {
+            Some(expires) => date::serialize(expires, serializer),
+            None => serializer.serialize_str(FOREVER),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<FixedOffset>>, D::Error> {
+        let expires = String::deserialize(deserializer)?;
+        if expires.eq_ignore_ascii_case(FOREVER) {
+            Ok(None)
+        } else {
+            DateTime::parse_from_str(&expires, date::FORMAT)
+                .map(Some)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{BannedPlayer, Operator};
+
+    #[test]
+    fn vanilla_format() {
+        let json = r#"[
+  {
+    "uuid": "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+    "name": "Notch",
+    "created": "2024-01-01 12:00:00 +0000",
+    "source": "Server",
+    "expires": "forever",
+    "reason": "Banned by an operator."
+  },
+  {
+    "uuid": "853c80ef-3c37-49fd-aa49-938b674adae6",
+    "name": "jeb_",
+    "created": "2024-01-01 12:00:00 +0100",
+    "source": "Notch",
+    "expires": "2024-02-01 12:00:00 +0100",
+    "reason": "Temporary"
+  }
+]"#;
+
+        let bans: Vec<BannedPlayer> = serde_json::from_str(json).unwrap();
+        assert_eq!(bans[0].name, "Notch");
+        assert_eq!(bans[0].ban.expires, None);
+
+        let before = DateTime::parse_from_rfc3339("2024-01-15T00:00:00+00:00").unwrap();
+        let after = DateTime::parse_from_rfc3339("2024-03-01T00:00:00+00:00").unwrap();
+        assert!(!bans[0].ban.is_expired(after));
+        assert!(!bans[1].ban.is_expired(before));
+        assert!(bans[1].ban.is_expired(after));
+
+        // Entries are written back in the same format
+        assert_eq!(serde_json::to_string_pretty(&bans).unwrap(), json);
+
+        let op: Operator = serde_json::from_str(
+            r#"{"uuid":"069a79f4-44e9-4726-a5be-fca90e38aaf5","name":"Notch","level":4,"bypassesPlayerLimit":true}"#,
+        )
+        .unwrap();
+        assert!(op.bypasses_player_limit);
+    }
+}
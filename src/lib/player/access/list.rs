@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{prelude::*, utils::HashMap};
+use chrono::Local;
+
+use super::AccessEntry;
+
+/// An error that occurred while reading or writing an [`AccessList`].
+#[derive(Debug, thiserror::Error)]
+pub enum AccessError {
+    /// The file could not be read or written.
+    #[error("Failed to access file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is not valid json.
+    #[error("Failed to parse file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A list of [`AccessEntry`]s backed by a vanilla json file.
+///
+/// Every change is written back to the file immediately,
+/// unless the list is only kept in memory.
+#[derive(Debug, Resource)]
+pub struct AccessList<T: AccessEntry> {
+    path: Option<PathBuf>,
+    entries: HashMap<T::Key, T>,
+}
+
+impl<T: AccessEntry> AccessList<T> {
+    /// Create a new empty [`AccessList`] stored in the given directory.
+    #[must_use]
+    pub fn new_empty(directory: impl AsRef<Path>) -> Self {
+        Self { path: Some(directory.as_ref().join(T::FILE_NAME)), entries: HashMap::new() }
+    }
+
+    /// Create a new empty [`AccessList`] that is only kept in memory.
+    #[must_use]
+    pub fn in_memory() -> Self { Self { path: None, entries: HashMap::new() } }
+
+    /// Load an [`AccessList`] from the given directory.
+    ///
+    /// If the file does not exist, an empty list is returned.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(directory: impl AsRef<Path>) -> Result<Self, AccessError> {
+        let path = directory.as_ref().join(T::FILE_NAME);
+        let mut entries = HashMap::new();
+        if path.exists() {
+            let list: Vec<T> = serde_json::from_slice(&std::fs::read(&path)?)?;
+            entries = list.into_iter().map(|entry| (entry.key(), entry)).collect();
+        }
+        Ok(Self { path: Some(path), entries })
+    }
+
+    /// Write the [`AccessList`] to its file.
+    ///
+    /// Expired entries are removed first, and the remaining entries
+    /// are sorted by key. The list is written to a temporary file
+    /// which then replaces the original, so the file is never left
+    /// partially written.
+    ///
+    /// Lists kept in memory are not written.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save(&mut self) -> Result<(), AccessError> {
+        let now = Local::now().fixed_offset();
+        self.entries.retain(|_, entry| !entry.is_expired(now));
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut entries: Vec<(&T::Key, &T)> = self.entries.iter().collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let entries: Vec<&T> = entries.into_iter().map(|(_, entry)| entry).collect();
+
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_string_pretty(&entries)?)?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    /// The path of the file backing the [`AccessList`],
+    /// or [`None`] if it is only kept in memory.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> { self.path.as_deref() }
+
+    /// Get the entry for the given key, if it has not expired.
+    #[must_use]
+    pub fn get(&self, key: &T::Key) -> Option<&T> {
+        self.entries.get(key).filter(|entry| !entry.is_expired(Local::now().fixed_offset()))
+    }
+
+    /// Returns `true` if the list contains an entry for the given key
+    /// that has not expired.
+    #[must_use]
+    pub fn contains(&self, key: &T::Key) -> bool { self.get(key).is_some() }
+
+    /// Iterate over all entries in the list, including expired ones.
+    pub fn iter(&self) -> impl Iterator<Item = &T> { self.entries.values() }
+
+    /// The number of entries in the list.
+    #[must_use]
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Returns `true` if the list has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// Add an entry to the list and save it,
+    /// returning the entry it replaced.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn insert(&mut self, entry: T) -> Result<Option<T>, AccessError> {
+        let previous = self.entries.insert(entry.key(), entry);
+        self.save()?;
+        Ok(previous)
+    }
+
+    /// Remove an entry from the list and save it,
+    /// returning the removed entry.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn remove(&mut self, key: &T::Key) -> Result<Option<T>, AccessError> {
+        let removed = self.entries.remove(key);
+        if removed.is_some() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::AccessList;
+    use crate::player::access::{AccessEntry, WhitelistedPlayer};
+
+    #[test]
+    fn sorted_save() {
+        let directory =
+            std::env::temp_dir().join(format!("froglight-access-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mut list = AccessList::<WhitelistedPlayer>::new_empty(&directory);
+        let (first, second) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        list.insert(WhitelistedPlayer::new(second, "Second")).unwrap();
+        list.insert(WhitelistedPlayer::new(first, "First")).unwrap();
+
+        // Entries are written in order, without leaving the temporary file behind
+        let saved: Vec<WhitelistedPlayer> =
+            serde_json::from_slice(&std::fs::read(list.path().unwrap()).unwrap()).unwrap();
+        assert_eq!(saved.iter().map(AccessEntry::key).collect::<Vec<_>>(), [first, second]);
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+        let loaded = AccessList::<WhitelistedPlayer>::load(&directory).unwrap();
+        assert!(loaded.contains(&first) && loaded.contains(&second));
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        // Lists kept in memory are never written
+        let mut memory = AccessList::<WhitelistedPlayer>::in_memory();
+        memory.insert(WhitelistedPlayer::new(first, "First")).unwrap();
+        assert!(memory.path().is_none() && memory.contains(&first));
+    }
+}
@@ -0,0 +1,203 @@
+//! TODO
+
+use std::{
+    marker::PhantomData,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use froglight::{
+    network::connection::{ConnectionInformation, NetworkDirection},
+    prelude::{State, *},
+};
+
+mod entry;
+pub use entry::{AccessEntry, BanDetails, BannedIp, BannedPlayer, Operator, WhitelistedPlayer};
+
+mod list;
+pub use list::{AccessError, AccessList};
+
+use crate::network::{
+    common::FilterResult,
+    forwarding::ForwardedPlayer,
+    login::{AuthenticatedProfile, LoginFilter},
+    play::PlayFilter,
+    socket::{ConnectionRequest, SocketFilter},
+};
+
+/// The players banned in `banned-players.json`.
+pub type BannedPlayers = AccessList<BannedPlayer>;
+/// The addresses banned in `banned-ips.json`.
+pub type BannedIps = AccessList<BannedIp>;
+/// The players allowed in `whitelist.json`.
+pub type Whitelist = AccessList<WhitelistedPlayer>;
+/// The operators in `ops.json`.
+pub type Operators = AccessList<Operator>;
+
+/// The reason given to players that are not whitelisted.
+pub const NOT_WHITELISTED: &str = "You are not white-listed on this server!";
+
+/// Whether only players in the [`Whitelist`] or [`Operators`] can join.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Resource, Deref, DerefMut)]
+pub struct WhitelistEnabled(pub bool);
+
+/// A [`Plugin`] that loads and enforces the vanilla
+/// ban lists, whitelist and operator list.
+///
+/// Banned addresses are denied by the [`SocketFilter`],
+/// banned players and players not in the [`Whitelist`] by the [`LoginFilter`].
+/// Addresses forwarded by a proxy are checked again by the [`LoginFilter`].
+/// Players banned while online are disconnected by the [`PlayFilter`].
+///
+/// By default the lists are only kept in memory,
+/// use [`PlayerAccessPlugin::new`] to store them in a directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerAccessPlugin<V: Version> {
+    /// The directory the json files are stored in.
+    ///
+    /// If [`None`], the lists are only kept in memory.
+    pub directory: Option<PathBuf>,
+    /// Whether the [`Whitelist`] is enabled.
+    pub whitelist: bool,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> Default for PlayerAccessPlugin<V> {
+    fn default() -> Self { Self::in_memory() }
+}
+
+impl<V: Version> PlayerAccessPlugin<V> {
+    /// Create a new [`PlayerAccessPlugin`] using files in the given directory.
+    #[must_use]
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: Some(directory.into()), whitelist: false, _phantom: PhantomData }
+    }
+
+    /// Create a new [`PlayerAccessPlugin`] that only keeps the lists in memory.
+    #[must_use]
+    pub const fn in_memory() -> Self {
+        Self { directory: None, whitelist: false, _phantom: PhantomData }
+    }
+
+    /// Enable or disable the [`Whitelist`].
+    #[must_use]
+    pub fn with_whitelist(mut self, whitelist: bool) -> Self {
+        self.whitelist = whitelist;
+        self
+    }
+}
+
+impl<V: Version> Plugin for PlayerAccessPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Login> + NetworkDirection<V, Play>,
+    Login: State<V>,
+    Play: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        // Load the lists, unless another version already has
+        let directory = self.directory.as_deref();
+        load_list::<BannedPlayer>(app, directory);
+        load_list::<BannedIp>(app, directory);
+        load_list::<WhitelistedPlayer>(app, directory);
+        load_list::<Operator>(app, directory);
+        if !app.world().contains_resource::<WhitelistEnabled>() {
+            app.insert_resource(WhitelistEnabled(self.whitelist));
+        }
+
+        // Add filters
+        app.init_resource::<SocketFilter<V>>();
+        app.init_resource::<LoginFilter<V>>();
+        app.init_resource::<PlayFilter<V>>();
+        app.world_mut().resource_mut::<SocketFilter<V>>().add_filter(
+            |request: &ConnectionRequest<V>, world: &World| {
+                check_address(request.information.socket.ip(), world)
+            },
+        );
+        app.world_mut().resource_mut::<LoginFilter<V>>().add_filter(login_filter);
+        app.world_mut().resource_mut::<PlayFilter<V>>().add_filter(play_filter);
+    }
+}
+
+/// Load an [`AccessList`] into the [`App`] if it does not already exist.
+///
+/// If no directory is given, an empty list is kept in memory.
+/// If the file cannot be loaded, an empty list is used instead.
+fn load_list<T: AccessEntry>(app: &mut App, directory: Option<&Path>) {
+    if app.world().contains_resource::<AccessList<T>>() {
+        return;
+    }
+
+    let Some(directory) = directory else {
+        app.insert_resource(AccessList::<T>::in_memory());
+        return;
+    };
+
+    let list = AccessList::<T>::load(directory).unwrap_or_else(|err| {
+        error!("Failed to load \"{}\": {err}", T::FILE_NAME);
+        AccessList::new_empty(directory)
+    });
+    debug!("Loaded {} entries from \"{}\"", list.len(), directory.join(T::FILE_NAME).display());
+    app.insert_resource(list);
+}
+
+/// Deny addresses in the [`BannedIps`] list.
+fn check_address(address: IpAddr, world: &World) -> FilterResult {
+    match world.get_resource::<BannedIps>().and_then(|list| list.get(&address.to_canonical())) {
+        Some(entry) => FilterResult::Deny(Some(entry.ban.message(BannedIp::HEADER).into())),
+        None => FilterResult::Allow,
+    }
+}
+
+/// Deny players in the [`BannedPlayers`] list.
+fn check_profile(profile: &GameProfile, world: &World) -> FilterResult {
+    match world.get_resource::<BannedPlayers>().and_then(|list| list.get(&profile.uuid)) {
+        Some(entry) => FilterResult::Deny(Some(entry.ban.message(BannedPlayer::HEADER).into())),
+        None => FilterResult::Allow,
+    }
+}
+
+/// Deny banned players and players that are not whitelisted.
+///
+/// Players are only checked once their [`GameProfile`] is authenticated.
+///
+/// The [`SocketFilter`] only saw the proxy's address,
+/// so forwarded players have their own address checked here.
+fn login_filter(entity: Entity, world: &World) -> FilterResult {
+    if world.get::<AuthenticatedProfile>(entity).is_none() {
+        return FilterResult::Allow;
+    }
+    if world.get::<ForwardedPlayer>(entity).is_some() {
+        if let Some(information) = world.get::<ConnectionInformation>(entity) {
+            if let FilterResult::Deny(reason) = check_address(information.socket.ip(), world) {
+                return FilterResult::Deny(reason);
+            }
+        }
+    }
+    let Some(profile) = world.get::<GameProfile>(entity) else {
+        return FilterResult::Allow;
+    };
+
+    if let FilterResult::Deny(reason) = check_profile(profile, world) {
+        return FilterResult::Deny(reason);
+    }
+
+    let whitelisted = world.get_resource::<Whitelist>().is_some_and(|l| l.contains(&profile.uuid))
+        || world.get_resource::<Operators>().is_some_and(|l| l.contains(&profile.uuid));
+    if world.get_resource::<WhitelistEnabled>().is_some_and(|enabled| **enabled) && !whitelisted {
+        return FilterResult::Deny(Some(NOT_WHITELISTED.into()));
+    }
+
+    FilterResult::Allow
+}
+
+/// Disconnect players that were banned while online.
+fn play_filter(entity: Entity, world: &World) -> FilterResult {
+    if let Some(information) = world.get::<ConnectionInformation>(entity) {
+        if let FilterResult::Deny(reason) = check_address(information.socket.ip(), world) {
+            return FilterResult::Deny(reason);
+        }
+    }
+
+    world.get::<GameProfile>(entity).map_or(FilterResult::Allow, |p| check_profile(p, world))
+}
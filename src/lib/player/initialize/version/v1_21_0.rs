@@ -15,7 +15,10 @@ use crate::{
     dimension::{subapp::DimensionIdentifier, DimensionList},
     entity::EntityIds,
     network::play::PlayServerPacketEvent,
-    player::{initialize::HasJoinPacket, spawner::PlayerSpawnerArc},
+    player::{
+        initialize::HasJoinPacket,
+        spawner::{ConnectionSpawn, PlayerSpawnerArc},
+    },
 };
 
 impl InitializeTrait for V1_21_0 {
@@ -57,10 +60,12 @@ impl InitializeTrait for V1_21_0 {
             .copied()
             .unwrap_or_else(|| world.resource::<EntityIds>().create());
 
-        // Get the player spawnpoint
+        // Get the player spawnpoint, preferring the connection's own
 
-        let spawner = world.resource::<PlayerSpawnerArc>();
-        let spawn = *spawner.write().get_or_set_default(profile.uuid);
+        let spawn = entity_ref.get::<ConnectionSpawn>().copied().unwrap_or_else(|| {
+            let spawner = world.resource::<PlayerSpawnerArc>();
+            ConnectionSpawn::from(*spawner.write().get_or_set_default(profile.uuid))
+        });
         let (block_pos, gamemode) = (spawn.position, spawn.game_mode);
 
         entity_com.insert(Transform::from_translation(Vec3::from(block_pos)));
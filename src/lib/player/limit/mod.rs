@@ -0,0 +1,418 @@
+//! TODO
+
+use std::{collections::VecDeque, marker::PhantomData, time::Duration};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use froglight::{
+    network::connection::NetworkDirection,
+    prelude::{State, *},
+};
+use uuid::Uuid;
+
+mod version;
+pub use version::QueueTrait;
+
+use super::access::{Operators, Whitelist};
+use crate::network::{
+    common::FilterResult,
+    config::{ConfigFilter, ConfigRequiredComponents, ConfigTask},
+    login::ConnectionInstant,
+};
+
+/// A [`Plugin`] that limits the number of players on the server.
+///
+/// Every player needs a [`PlayerSlot`] to finish configuring.
+/// Players that do not get one are either disconnected,
+/// or held in the [`JoinQueue`] until a slot frees up.
+///
+/// Queued players are sent their position on the
+/// [`QueueTrait::QUEUE_CHANNEL`] whenever it changes, and periodically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayerLimitPlugin<V: Version> {
+    /// The maximum number of players.
+    pub max_players: usize,
+    /// Whether extra players wait in the [`JoinQueue`]
+    /// instead of being disconnected.
+    pub queue: bool,
+    /// Whether [`Operators`] can join when the server is full.
+    pub bypass_ops: bool,
+    /// Whether players in the [`Whitelist`] can join when the server is full.
+    pub bypass_whitelist: bool,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> Default for PlayerLimitPlugin<V> {
+    fn default() -> Self { Self::new(PlayerLimit::DEFAULT_MAX_PLAYERS) }
+}
+
+impl<V: Version> PlayerLimitPlugin<V> {
+    /// Create a new [`PlayerLimitPlugin`] with the given maximum number of
+    /// players.
+    #[must_use]
+    pub const fn new(max_players: usize) -> Self {
+        Self {
+            max_players,
+            queue: false,
+            bypass_ops: true,
+            bypass_whitelist: false,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Enable or disable the [`JoinQueue`].
+    #[must_use]
+    pub const fn with_queue(mut self, queue: bool) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Set whether [`Operators`] can join when the server is full.
+    #[must_use]
+    pub const fn with_ops_bypass(mut self, bypass: bool) -> Self {
+        self.bypass_ops = bypass;
+        self
+    }
+
+    /// Set whether players in the [`Whitelist`] can join when the server is
+    /// full.
+    #[must_use]
+    pub const fn with_whitelist_bypass(mut self, bypass: bool) -> Self {
+        self.bypass_whitelist = bypass;
+        self
+    }
+}
+
+impl<V: Version + QueueTrait> Plugin for PlayerLimitPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Configuration>,
+    Configuration: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        // Only insert the `PlayerLimit` if another version hasn't already
+        if !app.world().contains_resource::<PlayerLimit>() {
+            app.insert_resource(PlayerLimit {
+                max_players: self.max_players,
+                queue: self.queue,
+                bypass_ops: self.bypass_ops,
+                bypass_whitelist: self.bypass_whitelist,
+            });
+        }
+        app.init_resource::<JoinQueue>();
+
+        // Require a `PlayerSlot` to finish configuring
+        app.init_resource::<ConfigRequiredComponents<V>>();
+        app.world_mut().resource_mut::<ConfigRequiredComponents<V>>().add_required::<PlayerSlot>();
+        app.init_resource::<ConfigFilter<V>>();
+        app.world_mut().resource_mut::<ConfigFilter<V>>().add_filter(PlayerLimit::filter);
+
+        // Add systems
+        app.add_systems(
+            Update,
+            (
+                JoinQueue::enqueue_players::<V>,
+                JoinQueue::admit_players,
+                JoinQueue::update_positions::<V>,
+            )
+                .chain()
+                .before(ConfigFilter::<V>::filter_tasks)
+                .run_if(any_with_component::<ConfigTask<V>>)
+                .ambiguous_with_all(),
+        );
+        app.add_systems(
+            Update,
+            JoinQueue::send_positions::<V>
+                .run_if(any_with_component::<QueuePosition>)
+                .run_if(on_timer(JoinQueue::UPDATE_INTERVAL))
+                .ambiguous_with_all(),
+        );
+    }
+}
+
+/// The player limit shared by all versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct PlayerLimit {
+    /// The maximum number of players.
+    pub max_players: usize,
+    /// Whether extra players wait in the [`JoinQueue`]
+    /// instead of being disconnected.
+    pub queue: bool,
+    /// Whether [`Operators`] can join when the server is full.
+    pub bypass_ops: bool,
+    /// Whether players in the [`Whitelist`] can join when the server is full.
+    pub bypass_whitelist: bool,
+}
+
+impl PlayerLimit {
+    /// The default maximum number of players.
+    pub const DEFAULT_MAX_PLAYERS: usize = 20;
+
+    /// The reason given to players when the server is full.
+    pub const FULL_REASON: &'static str = "The server is full!";
+
+    /// Returns `true` if the player can join when the server is full.
+    ///
+    /// Operators with `bypassesPlayerLimit` set can always join.
+    #[must_use]
+    pub fn can_bypass(
+        &self,
+        uuid: &Uuid,
+        ops: Option<&Operators>,
+        whitelist: Option<&Whitelist>,
+    ) -> bool {
+        let op = ops.and_then(|ops| ops.get(uuid));
+        op.is_some_and(|op| self.bypass_ops || op.bypasses_player_limit)
+            || (self.bypass_whitelist && whitelist.is_some_and(|list| list.contains(uuid)))
+    }
+
+    /// A [`ConfigFilter`] function that disconnects
+    /// players without a slot when the queue is disabled.
+    fn filter(entity: Entity, world: &World) -> FilterResult {
+        if world.get::<QueuePosition>(entity).is_some()
+            && world.get_resource::<PlayerLimit>().is_some_and(|limit| !limit.queue)
+        {
+            FilterResult::Deny(Some(Self::FULL_REASON.into()))
+        } else {
+            FilterResult::Allow
+        }
+    }
+}
+
+/// A marker [`Component`] for players that have been admitted to the server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Component)]
+#[component(storage = "SparseSet")]
+pub struct PlayerSlot;
+
+/// A player's position in the [`JoinQueue`], starting at `1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Deref)]
+#[component(storage = "SparseSet")]
+pub struct QueuePosition(pub usize);
+
+/// Players waiting for a [`PlayerSlot`], in the order they joined.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource, Deref)]
+pub struct JoinQueue(VecDeque<Entity>);
+
+impl JoinQueue {
+    /// How often queued players are sent their position.
+    pub const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// A system that adds configuring players without
+    /// a [`PlayerSlot`] to the back of the queue.
+    #[expect(clippy::type_complexity)]
+    pub fn enqueue_players<V: Version>(
+        query: Query<
+            (Entity, Option<&ConnectionInstant>),
+            (With<ConfigTask<V>>, Without<PlayerSlot>, Without<QueuePosition>),
+        >,
+        mut queue: ResMut<Self>,
+        mut commands: Commands,
+    ) where
+        Clientbound: NetworkDirection<V, Configuration>,
+        Configuration: State<V>,
+    {
+        let mut waiting: Vec<_> = query.iter().collect();
+        waiting.sort_by_key(|(_, instant)| instant.map(|instant| **instant));
+
+        for (entity, _) in waiting {
+            queue.0.push_back(entity);
+            commands.entity(entity).insert(QueuePosition(queue.len()));
+        }
+    }
+
+    /// A system that gives free [`PlayerSlot`]s to
+    /// queued players in the order they joined.
+    pub fn admit_players(
+        slots: Query<(), With<PlayerSlot>>,
+        waiting: Query<(&GameProfile, &QueuePosition), Without<PlayerSlot>>,
+        limit: Res<PlayerLimit>,
+        ops: Option<Res<Operators>>,
+        whitelist: Option<Res<Whitelist>>,
+        mut queue: ResMut<Self>,
+        mut commands: Commands,
+    ) {
+        let mut online = slots.iter().count();
+        let mut position = 0;
+
+        queue.0.retain(|&entity| {
+            // Forget players that disconnected
+            let Ok((profile, current)) = waiting.get(entity) else {
+                return false;
+            };
+
+            if online < limit.max_players
+                || limit.can_bypass(&profile.uuid, ops.as_deref(), whitelist.as_deref())
+            {
+                debug!("Admitting {}", profile.username);
+                commands.entity(entity).remove::<QueuePosition>().insert(PlayerSlot);
+                online += 1;
+                false
+            } else {
+                position += 1;
+                if **current != position {
+                    commands.entity(entity).insert(QueuePosition(position));
+                }
+                true
+            }
+        });
+    }
+
+    /// A system that sends queued players their position
+    /// whenever it changes.
+    pub fn update_positions<V: Version + QueueTrait>(
+        query: Query<(Ref<QueuePosition>, &ConfigTask<V>)>,
+        limit: Res<PlayerLimit>,
+        queue: Res<Self>,
+    ) where
+        Clientbound: NetworkDirection<V, Configuration>,
+        Configuration: State<V>,
+    {
+        if limit.queue {
+            for (position, task) in &query {
+                if position.is_changed() {
+                    V::send_position(task, **position, queue.len());
+                }
+            }
+        }
+    }
+
+    /// A system that periodically sends queued players their position.
+    pub fn send_positions<V: Version + QueueTrait>(
+        query: Query<(&QueuePosition, &ConfigTask<V>)>,
+        limit: Res<PlayerLimit>,
+        queue: Res<Self>,
+    ) where
+        Clientbound: NetworkDirection<V, Configuration>,
+        Configuration: State<V>,
+    {
+        if limit.queue {
+            for (position, task) in &query {
+                V::send_position(task, **position, queue.len());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use froglight::{
+        network::versions::v1_21_0::{configuration::ConfigurationClientboundPackets, V1_21_0},
+        prelude::*,
+    };
+
+    use super::{JoinQueue, PlayerLimit, QueuePosition, QueueTrait};
+    use crate::{
+        network::play::PlayTask,
+        testing::{TestClient, TestServer},
+    };
+
+    /// Create a [`TestServer`] with a single player slot.
+    fn single_slot(queue: bool) -> TestServer {
+        let mut server = TestServer::new();
+        server.app().insert_resource(PlayerLimit {
+            max_players: 1,
+            queue,
+            bypass_ops: true,
+            bypass_whitelist: false,
+        });
+        server
+    }
+
+    /// The usernames of queued players, ordered by their position.
+    fn queued(world: &mut World) -> Vec<String> {
+        let mut query = world.query::<(&GameProfile, &QueuePosition)>();
+        let mut queued: Vec<_> = query
+            .iter(world)
+            .map(|(profile, position)| (**position, profile.username.clone()))
+            .collect();
+        queued.sort();
+        queued.into_iter().map(|(_, username)| username).collect()
+    }
+
+    #[test]
+    fn full_server() {
+        let mut server = single_slot(false);
+        let (done, wait) = async_channel::bounded::<()>(1);
+        let alice = server.spawn_client(|address| async move {
+            let conn = TestClient::new(address, "Alice").join().await;
+            let _ = wait.recv().await;
+            conn.map(drop)
+        });
+        assert!(server.update_until(|world| {
+            world.query_filtered::<(), With<PlayTask<V1_21_0>>>().iter(world).count() == 1
+        }));
+
+        // The second player is disconnected
+        let err = server
+            .run(|address| async move { TestClient::new(address, "Bob").join().await.map(drop) })
+            .unwrap_err();
+        assert!(err.to_string().contains(PlayerLimit::FULL_REASON), "Unexpected error: {err}");
+
+        done.send_blocking(()).unwrap();
+        server.run_client(alice).unwrap();
+    }
+
+    #[test]
+    fn queue_order() {
+        let mut server = single_slot(true);
+        let (done, wait) = async_channel::bounded::<()>(1);
+        let alice = server.spawn_client(|address| async move {
+            let conn = TestClient::new(address, "Alice").join().await;
+            let _ = wait.recv().await;
+            conn.map(drop)
+        });
+        assert!(server.update_until(|world| {
+            world.query_filtered::<(), With<PlayTask<V1_21_0>>>().iter(world).count() == 1
+        }));
+
+        // Queue two players, one after the other
+        let join = |username: &'static str| {
+            move |address| async move {
+                let mut client = TestClient::new(address, username);
+                client.join().await.map(|_| client)
+            }
+        };
+        let bob = server.spawn_client(join("Bob"));
+        assert!(server.update_until(|world| queued(world).len() == 1));
+        let carol = server.spawn_client(join("Carol"));
+        assert!(server.update_until(|world| queued(world).len() == 2));
+
+        assert_eq!(queued(server.app().world_mut()), ["Bob", "Carol"]);
+        assert_eq!(server.world().resource::<JoinQueue>().len(), 2);
+
+        // Players are admitted in order as slots free up
+        done.send_blocking(()).unwrap();
+        server.run_client(alice).unwrap();
+        let bob = server.run_client(bob).unwrap();
+        let carol = server.run_client(carol).unwrap();
+
+        // Bob was only sent his position on the queue channel
+        assert!(bob.received.configuration.iter().any(|packet| matches!(
+            packet,
+            ConfigurationClientboundPackets::CustomPayload(payload)
+                if payload.identifier == ResourceKey::const_new(V1_21_0::QUEUE_CHANNEL)
+        )));
+        assert!(!bob.received.configuration.iter().any(|packet| matches!(
+            packet,
+            ConfigurationClientboundPackets::ResourcePackSend(..)
+                | ConfigurationClientboundPackets::ResourcePackRemove(..)
+        )));
+
+        // Carol was sent both of her positions on the queue channel,
+        // along with any periodic updates
+        let mut positions: Vec<u32> = carol
+            .received
+            .configuration
+            .iter()
+            .filter_map(|packet| match packet {
+                ConfigurationClientboundPackets::CustomPayload(payload)
+                    if payload.identifier == ResourceKey::const_new(V1_21_0::QUEUE_CHANNEL) =>
+                {
+                    Some(u32::from_be_bytes(payload.payload[..4].try_into().unwrap()))
+                }
+                _ => None,
+            })
+            .collect();
+        positions.dedup();
+        assert_eq!(positions, [2, 1]);
+    }
+}
@@ -0,0 +1,19 @@
+use froglight::{network::connection::NetworkDirection, prelude::*};
+
+use crate::network::config::ConfigTask;
+
+mod v1_21_0;
+
+/// A trait that tells queued players their position in the join queue.
+pub trait QueueTrait: Version
+where
+    Clientbound: NetworkDirection<Self, Configuration>,
+    Configuration: State<Self>,
+{
+    /// The plugin channel queue positions are sent on.
+    const QUEUE_CHANNEL: &'static str = "froglight:queue";
+
+    /// Send the player's position and the length of the queue
+    /// on the [`QueueTrait::QUEUE_CHANNEL`].
+    fn send_position(task: &ConfigTask<Self>, position: usize, length: usize);
+}
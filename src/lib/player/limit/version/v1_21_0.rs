@@ -0,0 +1,26 @@
+use froglight::{
+    network::versions::v1_21_0::{
+        configuration::ConfigurationClientboundPackets, play::CustomPayloadS2CPacket, V1_21_0,
+    },
+    prelude::*,
+};
+
+use super::QueueTrait;
+use crate::network::config::ConfigTask;
+
+impl QueueTrait for V1_21_0 {
+    fn send_position(task: &ConfigTask<Self>, position: usize, length: usize) {
+        let position = u32::try_from(position).unwrap_or(u32::MAX);
+        let length = u32::try_from(length).unwrap_or(u32::MAX);
+
+        let mut payload = Vec::with_capacity(8);
+        payload.extend_from_slice(&position.to_be_bytes());
+        payload.extend_from_slice(&length.to_be_bytes());
+
+        // Configuration shares its custom payload packet with play
+        task.send(ConfigurationClientboundPackets::CustomPayload(CustomPayloadS2CPacket {
+            identifier: ResourceKey::const_new(Self::QUEUE_CHANNEL),
+            payload: payload.into(),
+        }));
+    }
+}
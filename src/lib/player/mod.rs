@@ -5,12 +5,18 @@ use std::marker::PhantomData;
 use bevy::{app::PluginGroupBuilder, prelude::*};
 use froglight::prelude::Version;
 
+pub mod access;
+use access::PlayerAccessPlugin;
+
 pub mod initialize;
 use initialize::PlayerInitializePlugin;
 
 pub mod keepalive;
 use keepalive::KeepAlivePlugin;
 
+pub mod limit;
+use limit::PlayerLimitPlugin;
+
 pub mod movement;
 use movement::PlayerMovementPlugin;
 
@@ -23,25 +29,36 @@ use settings::PlayerSettingsPlugin;
 pub mod spawner;
 use spawner::PlayerSpawnerPlugin;
 
+pub mod status;
+use status::PlayerStatusPlugin;
+
+pub mod vhost;
+
 /// A [`PluginGroup`] that adds player-related plugins to the [`App`].
 #[derive(Debug, Default)]
 pub struct PlayerPlugins<V: Version>(PhantomData<V>);
 
 impl<V: Version> PluginGroup for PlayerPlugins<V>
 where
+    PlayerAccessPlugin<V>: Plugin,
     KeepAlivePlugin<V>: Plugin,
     PlayerSettingsPlugin<V>: Plugin,
     PlayerSpawnerPlugin<V>: Plugin,
     PlayerInitializePlugin<V>: Plugin,
+    PlayerLimitPlugin<V>: Plugin,
     PlayerMovementPlugin<V>: Plugin,
+    PlayerStatusPlugin<V>: Plugin,
 {
     fn build(self) -> PluginGroupBuilder {
         let mut builder = PluginGroupBuilder::start::<Self>();
+        builder = builder.add(PlayerAccessPlugin::<V>::default());
         builder = builder.add(KeepAlivePlugin::<V>::default());
         builder = builder.add(PlayerSettingsPlugin::<V>::default());
         builder = builder.add(PlayerSpawnerPlugin::<V>::default());
         builder = builder.add(PlayerInitializePlugin::<V>::default());
+        builder = builder.add(PlayerLimitPlugin::<V>::default());
         builder = builder.add(PlayerMovementPlugin::<V>::default());
+        builder = builder.add(PlayerStatusPlugin::<V>::default());
 
         builder = builder.add(PlayerProfileSyncPlugin);
 
@@ -36,8 +36,15 @@ pub struct ProfileSyncSystemSet;
 impl PlayerProfileSyncPlugin {
     /// A system that adds a player profile component to the list of
     /// subapp components.
+    ///
+    /// Profiles that change before the entity is linked to a subapp,
+    /// such as after being verified, are added again.
+    #[expect(clippy::type_complexity)]
     pub fn add_profile_component(
-        mut query: Query<(Entity, &GameProfile, Option<&mut SubAppComponents>), Added<GameProfile>>,
+        mut query: Query<
+            (Entity, &GameProfile, Option<&mut SubAppComponents>),
+            (Changed<GameProfile>, Without<SubAppTracker>),
+        >,
         mut commands: Commands,
     ) {
         for (entity, profile, components) in &mut query {
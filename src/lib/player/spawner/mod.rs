@@ -15,7 +15,7 @@ use crate::{
 };
 
 mod spawn;
-pub use spawn::{PlayerSpawner, PlayerSpawnerData};
+pub use spawn::{ConnectionSpawn, PlayerSpawner, PlayerSpawnerData};
 
 mod systemset;
 pub use systemset::SpawnerSystemSet;
@@ -30,6 +30,8 @@ where
     Configuration: State<V>,
 {
     fn build(&self, app: &mut App) {
+        app.register_type::<ConnectionSpawn>();
+
         // Only insert a `PlayerSpawnerArc` if one doesn't already exist.
         if !app.world().contains_resource::<PlayerSpawnerArc>() {
             let spawner = PlayerSpawnerArc::internal_default();
@@ -86,6 +88,9 @@ impl PlayerSpawnerArc {
 
 impl PlayerSpawnerArc {
     /// A system that sets the spawn dimension for new connections.
+    ///
+    /// Connections that already have a [`DimensionMarker`],
+    /// such as those routed by a virtual host, are skipped.
     pub fn set_spawn_dimension<V: Version>(
         query: Query<(&GameProfile, Option<&DimensionMarker>)>,
         spawns: Res<PlayerSpawnerArc>,
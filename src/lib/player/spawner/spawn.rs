@@ -2,7 +2,7 @@
 
 use std::hash::BuildHasherDefault;
 
-use bevy::utils::HashMap;
+use bevy::{prelude::*, utils::HashMap};
 use froglight::prelude::*;
 
 use crate::dimension::{subapp::DimensionIdentifier, Overworld};
@@ -96,3 +96,23 @@ pub struct PlayerSpawnerData {
     /// The position to spawn the player at.
     pub position: BlockPosition,
 }
+
+/// The spawn point of a single connection,
+/// used instead of the player's spawn in the [`PlayerSpawner`].
+///
+/// Only kept for as long as the connection,
+/// and copied to the entity in the player's dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
+pub struct ConnectionSpawn {
+    /// The player's game mode.
+    pub game_mode: GameMode,
+    /// The position to spawn the player at.
+    pub position: BlockPosition,
+}
+
+impl From<PlayerSpawnerData> for ConnectionSpawn {
+    fn from(data: PlayerSpawnerData) -> Self {
+        Self { game_mode: data.game_mode, position: data.position }
+    }
+}
@@ -0,0 +1,75 @@
+//! TODO
+
+use std::{marker::PhantomData, time::Duration};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use froglight::{network::connection::NetworkDirection, prelude::*};
+
+use super::{limit::PlayerLimit, settings::ClientSettings};
+use crate::network::{play::PlayTask, socket::ListenTask};
+
+/// A [`Plugin`] that keeps the [`ServerStatus`] of all
+/// [`ListenTask`]s in sync with the players online.
+#[derive(Debug, Default)]
+pub struct PlayerStatusPlugin<V: Version>(PhantomData<V>);
+
+impl<V: Version> Plugin for PlayerStatusPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Login> + NetworkDirection<V, Play>,
+    Login: State<V>,
+    Play: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            PlayerStatusPlugin::<V>::update_status
+                .run_if(any_with_component::<ListenTask<V>>)
+                .run_if(on_timer(PlayerStatusPlugin::<V>::UPDATE_INTERVAL)),
+        );
+    }
+}
+
+impl<V: Version> PlayerStatusPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Login> + NetworkDirection<V, Play>,
+    Login: State<V>,
+    Play: State<V>,
+{
+    /// How often the [`ServerStatus`] is updated.
+    pub const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// The maximum number of players shown in the sample.
+    pub const MAX_SAMPLE: usize = 12;
+
+    /// A system that updates the online players and the player sample.
+    ///
+    /// Only players that allow server listing are added to the sample.
+    ///
+    /// If there is a [`PlayerLimit`], the maximum number of players is updated
+    /// too.
+    pub fn update_status(
+        players: Query<(&GameProfile, Option<&ClientSettings>), With<PlayTask<V>>>,
+        listeners: Query<&ListenTask<V>>,
+        limit: Option<Res<PlayerLimit>>,
+    ) {
+        let online = players.iter().count();
+        let sample: Vec<ServerSamplePlayer> = players
+            .iter()
+            .filter(|(_, settings)| settings.is_some_and(|settings| settings.allows_listing))
+            .take(Self::MAX_SAMPLE)
+            .map(|(profile, _)| ServerSamplePlayer {
+                username: profile.username.as_str().into(),
+                uuid: profile.uuid,
+            })
+            .collect();
+
+        for listener in &listeners {
+            let mut status = listener.status().write();
+            status.players.online = online.try_into().unwrap_or_default();
+            if let Some(limit) = &limit {
+                status.players.max = limit.max_players.try_into().unwrap_or_default();
+            }
+            status.players.sample.clone_from(&sample);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::log::warn;
+use compact_str::CompactString;
+use froglight::prelude::*;
+
+use crate::{
+    dimension::subapp::DimensionIdentifier, network::socket::Motd,
+    player::spawner::PlayerSpawnerData,
+};
+
+/// A hostname, or a pattern matching many hostnames.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HostPattern {
+    /// Matches a single hostname, like `lobby.example.com`.
+    Exact(CompactString),
+    /// Matches any subdomain of a hostname, like `*.example.com`.
+    ///
+    /// Only the suffix after the `*.` is stored.
+    Wildcard(CompactString),
+    /// Matches every hostname, written as `*`.
+    Any,
+}
+
+impl HostPattern {
+    /// Parse a [`HostPattern`], ignoring case.
+    #[must_use]
+    pub fn parse(pattern: &str) -> Self {
+        let pattern = Self::normalize(pattern);
+        if pattern == "*" {
+            Self::Any
+        } else if let Some(suffix) = pattern.strip_prefix("*.") {
+            Self::Wildcard(suffix.into())
+        } else {
+            Self::Exact(pattern)
+        }
+    }
+
+    /// Normalize the address sent in a handshake.
+    ///
+    /// Forge and BungeeCord append data to the address after a `\0`,
+    /// and a trailing `.` is allowed in fully qualified names.
+    #[must_use]
+    pub fn normalize(address: &str) -> CompactString {
+        let hostname = address.split('\0').next().unwrap_or_default();
+        hostname.trim_end_matches('.').to_ascii_lowercase().into()
+    }
+
+    /// Returns `true` if the pattern matches the normalized hostname.
+    #[must_use]
+    pub fn matches(&self, hostname: &str) -> bool {
+        match self {
+            Self::Exact(host) => hostname == host.as_str(),
+            Self::Wildcard(suffix) => hostname
+                .strip_suffix(suffix.as_str())
+                .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+            Self::Any => true,
+        }
+    }
+
+    /// How specific the pattern is.
+    ///
+    /// Exact hostnames are preferred over wildcards,
+    /// and longer wildcards over shorter ones.
+    #[must_use]
+    pub fn specificity(&self) -> (u8, usize) {
+        match self {
+            Self::Exact(host) => (2, host.len()),
+            Self::Wildcard(suffix) => (1, suffix.len()),
+            Self::Any => (0, 0),
+        }
+    }
+}
+
+impl std::fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(host) => f.write_str(host),
+            Self::Wildcard(suffix) => write!(f, "*.{suffix}"),
+            Self::Any => f.write_str("*"),
+        }
+    }
+}
+
+/// A hostname clients can connect to,
+/// and how players connecting to it are treated.
+#[derive(Debug, Clone)]
+pub struct VirtualHost {
+    /// The hostnames this host matches.
+    pub pattern: HostPattern,
+    /// The dimension to spawn players in.
+    ///
+    /// Overrides the dimension of the [`VirtualHost::spawn`].
+    pub dimension: Option<DimensionIdentifier>,
+    /// Where and how to spawn players.
+    pub spawn: Option<PlayerSpawnerData>,
+    /// The message of the day shown in the server list.
+    pub motd: Option<Motd>,
+    /// The status shown in the server list.
+    ///
+    /// The player count and version are kept from the server's status.
+    pub status: Option<ServerStatus>,
+}
+
+impl VirtualHost {
+    /// Create a new [`VirtualHost`] for the given pattern.
+    ///
+    /// See [`HostPattern::parse`].
+    #[must_use]
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: HostPattern::parse(pattern),
+            dimension: None,
+            spawn: None,
+            motd: None,
+            status: None,
+        }
+    }
+
+    /// Spawn players in the given dimension.
+    #[must_use]
+    pub fn with_dimension(mut self, dimension: impl Into<DimensionIdentifier>) -> Self {
+        self.dimension = Some(dimension.into());
+        self
+    }
+
+    /// Spawn players using the given [`PlayerSpawnerData`].
+    #[must_use]
+    pub fn with_spawn(mut self, spawn: PlayerSpawnerData) -> Self {
+        self.spawn = Some(spawn);
+        self
+    }
+
+    /// Show the given message of the day in the server list.
+    #[must_use]
+    pub fn with_motd(mut self, motd: impl Into<Motd>) -> Self {
+        self.motd = Some(motd.into());
+        self
+    }
+
+    /// Show the given [`ServerStatus`] in the server list.
+    #[must_use]
+    pub fn with_status(mut self, status: ServerStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// The spawn for players connecting to this host,
+    /// if it has one, based on the given default.
+    #[must_use]
+    pub fn spawn(&self, default: PlayerSpawnerData) -> Option<PlayerSpawnerData> {
+        if self.spawn.is_none() && self.dimension.is_none() {
+            return None;
+        }
+
+        let mut spawn = self.spawn.unwrap_or(default);
+        if let Some(dimension) = self.dimension {
+            spawn.dimension = dimension;
+        }
+        Some(spawn)
+    }
+
+    /// Apply this host's status and message of the day to the server's status.
+    pub fn apply_status(&self, status: &mut ServerStatus) {
+        if let Some(host) = &self.status {
+            let mut host = host.clone();
+            host.players = status.players.clone();
+            host.version = status.version.clone();
+            *status = host;
+        }
+        if let Some(motd) = &self.motd {
+            if let Err(err) = motd.apply(status) {
+                warn!("Invalid message of the day for host \"{}\": {err}", self.pattern);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HostPattern;
+
+    #[test]
+    fn patterns() {
+        let exact = HostPattern::parse("Lobby.Example.com");
+        let wildcard = HostPattern::parse("*.example.com");
+        let any = HostPattern::parse("*");
+        assert_eq!(wildcard, HostPattern::Wildcard("example.com".into()));
+        assert_eq!(any, HostPattern::Any);
+
+        // Handshake addresses are normalized
+        let hostname = HostPattern::normalize("lobby.EXAMPLE.com.\0FML3\0");
+        assert_eq!(hostname, "lobby.example.com");
+        assert!(exact.matches(&hostname));
+        assert!(wildcard.matches(&hostname));
+        assert!(any.matches(&hostname));
+
+        // Wildcards only match subdomains
+        assert!(!wildcard.matches("example.com"));
+        assert!(!wildcard.matches("badexample.com"));
+        assert!(!exact.matches("build.example.com"));
+
+        assert!(exact.specificity() > wildcard.specificity());
+        assert!(wildcard.specificity() > any.specificity());
+        assert_eq!(wildcard.to_string(), "*.example.com");
+    }
+}
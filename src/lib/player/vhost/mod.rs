@@ -0,0 +1,312 @@
+//! TODO
+
+use std::{marker::PhantomData, sync::Arc};
+
+use bevy::prelude::*;
+use froglight::{
+    network::connection::{ConnectionInformation, NetworkDirection},
+    prelude::*,
+};
+use parking_lot::RwLock;
+
+mod host;
+pub use host::{HostPattern, VirtualHost};
+
+use super::spawner::{ConnectionSpawn, PlayerSpawnerArc, SpawnerSystemSet};
+use crate::{
+    dimension::subapp::{DimensionMarker, SubAppComponents},
+    network::{config::ConfigStateEvent, socket::StatusOverrides},
+};
+
+/// A [`Plugin`] that routes players by the hostname they connected to.
+///
+/// Each [`VirtualHost`] can set the dimension and spawn of players,
+/// and the status shown in the server list.
+#[derive(Debug, Clone)]
+pub struct VirtualHostPlugin<V: Version> {
+    /// The hosts to add to the [`VirtualHosts`].
+    pub hosts: Vec<VirtualHost>,
+    _phantom: PhantomData<V>,
+}
+
+impl<V: Version> Default for VirtualHostPlugin<V> {
+    fn default() -> Self { Self::new(Vec::new()) }
+}
+
+impl<V: Version> VirtualHostPlugin<V> {
+    /// Create a new [`VirtualHostPlugin`] with the given hosts.
+    #[must_use]
+    pub const fn new(hosts: Vec<VirtualHost>) -> Self { Self { hosts, _phantom: PhantomData } }
+
+    /// Add a [`VirtualHost`].
+    #[must_use]
+    pub fn with_host(mut self, host: VirtualHost) -> Self {
+        self.hosts.push(host);
+        self
+    }
+}
+
+impl<V: Version> Plugin for VirtualHostPlugin<V>
+where
+    Clientbound: NetworkDirection<V, Configuration>,
+    Configuration: State<V>,
+{
+    fn build(&self, app: &mut App) {
+        // Only insert a `VirtualHosts` if one doesn't already exist,
+        // and show each host's status to clients connecting to it.
+        if !app.world().contains_resource::<VirtualHosts>() {
+            let hosts = VirtualHosts::default();
+            let overrides =
+                app.world_mut().get_resource_or_insert_with(StatusOverrides::default).clone();
+            overrides.add_override({
+                let hosts = hosts.clone();
+                move |address, status| hosts.apply_status(address, status)
+            });
+            app.insert_resource(hosts);
+        }
+
+        let hosts = app.world().resource::<VirtualHosts>().clone();
+        for host in &self.hosts {
+            hosts.insert(host.clone());
+        }
+
+        // Add systems
+        app.add_systems(
+            Update,
+            VirtualHosts::route_players::<V>
+                .run_if(on_event::<ConfigStateEvent<V>>)
+                .in_set(SpawnerSystemSet)
+                .before(PlayerSpawnerArc::set_spawn_dimension::<V>),
+        );
+    }
+}
+
+/// A [`Resource`] that holds every [`VirtualHost`].
+///
+/// As a shared reference, this resource can be
+/// cheaply cloned and accessed in any [`World`].
+#[derive(Debug, Default, Clone, Resource)]
+pub struct VirtualHosts(Arc<RwLock<Vec<VirtualHost>>>);
+
+impl VirtualHosts {
+    /// Add a [`VirtualHost`], replacing any with the same pattern.
+    ///
+    /// Returns the replaced host, if any.
+    pub fn insert(&self, host: VirtualHost) -> Option<VirtualHost> {
+        let mut hosts = self.0.write();
+        if let Some(existing) = hosts.iter_mut().find(|h| h.pattern == host.pattern) {
+            Some(std::mem::replace(existing, host))
+        } else {
+            hosts.push(host);
+            None
+        }
+    }
+
+    /// Remove the [`VirtualHost`] with the given pattern.
+    pub fn remove(&self, pattern: &HostPattern) -> Option<VirtualHost> {
+        let mut hosts = self.0.write();
+        let index = hosts.iter().position(|h| &h.pattern == pattern)?;
+        Some(hosts.remove(index))
+    }
+
+    /// Find the most specific [`VirtualHost`] matching a handshake address.
+    #[must_use]
+    pub fn find(&self, address: &str) -> Option<VirtualHost> {
+        let hostname = HostPattern::normalize(address);
+        let hosts = self.0.read();
+        hosts
+            .iter()
+            .filter(|host| host.pattern.matches(&hostname))
+            .max_by_key(|host| host.pattern.specificity())
+            .cloned()
+    }
+
+    /// The [`ServerStatus`] to show clients connecting to a handshake address.
+    #[must_use]
+    pub fn status(&self, address: &str, status: &ServerStatus) -> ServerStatus {
+        let mut status = status.clone();
+        self.apply_status(address, &mut status);
+        status
+    }
+
+    /// Apply the status of the host matching a handshake address, if any.
+    pub fn apply_status(&self, address: &str, status: &mut ServerStatus) {
+        if let Some(host) = self.find(address) {
+            host.apply_status(status);
+        }
+    }
+
+    /// A system that sets the spawn of new connections
+    /// using the hostname they connected to.
+    ///
+    /// The host's spawn is kept in a [`ConnectionSpawn`] and a
+    /// [`DimensionMarker`], leaving the player's spawn untouched.
+    ///
+    /// Runs before [`PlayerSpawnerArc::set_spawn_dimension`],
+    /// which skips connections that already have a dimension.
+    pub fn route_players<V: Version>(
+        mut query: Query<
+            (&GameProfile, &ConnectionInformation, Option<&mut SubAppComponents>),
+            Without<DimensionMarker>,
+        >,
+        hosts: Res<VirtualHosts>,
+        spawner: Option<Res<PlayerSpawnerArc>>,
+        mut events: EventReader<ConfigStateEvent<V>>,
+        mut commands: Commands,
+    ) where
+        Clientbound: NetworkDirection<V, Configuration>,
+        Configuration: State<V>,
+    {
+        let Some(spawner) = spawner else { return };
+        let default = spawner.read().default;
+
+        for ConfigStateEvent { entity, .. } in events.read() {
+            let Ok((profile, information, components)) = query.get_mut(*entity) else { continue };
+            let Some(address) = information.address.as_deref() else { continue };
+            let Some(host) = hosts.find(address) else { continue };
+            let Some(spawn) = host.spawn(default) else { continue };
+
+            debug!("Routing {} to host \"{}\"", profile.username, host.pattern);
+            let connection = ConnectionSpawn::from(spawn);
+            let mut entity = commands.entity(*entity);
+            entity.insert((connection, DimensionMarker(spawn.dimension)));
+
+            // Copy the spawn to the entity in the player's dimension
+            if let Some(mut components) = components {
+                components.push(connection);
+            } else {
+                entity.insert(SubAppComponents::from_component(connection));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, utils::HashMap};
+    use froglight::{
+        network::{
+            connection::{AccountInformation, ConnectionInformation},
+            versions::v1_21_0::V1_21_0,
+        },
+        prelude::*,
+    };
+    use parking_lot::Mutex;
+
+    use super::{VirtualHost, VirtualHosts};
+    use crate::{
+        dimension::{
+            subapp::{DimensionIdentifier, DimensionMarker, SubAppComponents},
+            Nether, Overworld,
+        },
+        network::{config::ConfigStateEvent, socket::SocketTrait},
+        player::spawner::{ConnectionSpawn, PlayerSpawnerArc, PlayerSpawnerData},
+    };
+
+    fn json(status: &ServerStatus) -> serde_json::Value { serde_json::to_value(status).unwrap() }
+
+    #[test]
+    fn status() {
+        let mut lobby = V1_21_0::status();
+        lobby.description = "Lobby".into();
+        lobby.players.max = 100;
+        lobby.version.name = "Lobby".into();
+
+        let hosts = VirtualHosts::default();
+        hosts.insert(VirtualHost::new("lobby.example.com").with_status(lobby.clone()));
+        hosts.insert(VirtualHost::new("*.example.com").with_motd("Welcome"));
+
+        let mut server = V1_21_0::status();
+        server.players.online = 5;
+
+        // The host's status keeps the server's players and version
+        let mut expected = lobby;
+        expected.players = server.players.clone();
+        expected.version = server.version.clone();
+        assert_eq!(json(&hosts.status("Lobby.Example.com.", &server)), json(&expected));
+
+        // Wildcards only change the message of the day
+        let mut expected = server.clone();
+        expected.description = "Welcome".into();
+        assert_eq!(json(&hosts.status("build.example.com", &server)), json(&expected));
+
+        // Unknown hosts see the server's status
+        assert_eq!(json(&hosts.status("example.org", &server)), json(&server));
+    }
+
+    #[test]
+    fn route_players() {
+        let build = PlayerSpawnerData {
+            game_mode: GameMode::Adventure,
+            dimension: Overworld.into(),
+            position: BlockPosition::new(8, 80, 8),
+        };
+
+        let hosts = VirtualHosts::default();
+        hosts.insert(VirtualHost::new("*.example.com").with_dimension(Nether));
+        hosts.insert(VirtualHost::new("build.example.com").with_spawn(build));
+        hosts.insert(VirtualHost::new("status.example.com").with_motd("Status only"));
+
+        let spawner = PlayerSpawnerArc::new(
+            GameMode::Survival,
+            BlockPosition::new(0, 64, 0),
+            Overworld.into(),
+        );
+        let default = spawner.read().default;
+
+        let mut app = App::new();
+        app.add_event::<ConfigStateEvent<V1_21_0>>()
+            .insert_resource(hosts)
+            .insert_resource(spawner.clone())
+            .add_systems(Update, VirtualHosts::route_players::<V1_21_0>);
+
+        let mut connect = |address: &str| {
+            let entity = app
+                .world_mut()
+                .spawn((
+                    GameProfile {
+                        uuid: AccountInformation::offline_uuid(address),
+                        username: address.to_string(),
+                        properties: HashMap::new(),
+                    },
+                    ConnectionInformation {
+                        address: Some(address.into()),
+                        socket: "127.0.0.1:25565".parse().unwrap(),
+                    },
+                ))
+                .id();
+            app.world_mut()
+                .send_event(ConfigStateEvent::<V1_21_0> { entity, connection: Mutex::new(None) });
+            entity
+        };
+
+        let lobby = connect("lobby.example.com");
+        let build_host = connect("Build.Example.com.");
+        let status_host = connect("status.example.com");
+        let unknown = connect("example.org");
+        app.update();
+
+        let world = app.world();
+        let dimension =
+            |entity: Entity| world.get::<DimensionMarker>(entity).map(|marker| **marker);
+        let spawn = |entity: Entity| world.get::<ConnectionSpawn>(entity).copied();
+
+        // Wildcards change the dimension, keeping the default position
+        assert_eq!(dimension(lobby), Some(DimensionIdentifier::from(Nether)));
+        assert_eq!(spawn(lobby), Some(ConnectionSpawn::from(default)));
+
+        // Exact hosts are preferred over wildcards
+        assert_eq!(dimension(build_host), Some(build.dimension));
+        assert_eq!(spawn(build_host), Some(ConnectionSpawn::from(build)));
+        assert!(world.entity(build_host).contains::<SubAppComponents>());
+
+        // Hosts without a spawn and unknown hosts use the player's spawn
+        for entity in [status_host, unknown] {
+            assert_eq!(dimension(entity), None);
+            assert_eq!(spawn(entity), None);
+        }
+
+        // The player's own spawns are never changed
+        assert!(spawner.read().player.is_empty());
+    }
+}
@@ -3,7 +3,10 @@
 //! Includes a custom taskpool configuration,
 //! [`TASKPOOL_SETTINGS`], for bevy's [`TaskPoolPlugin`].
 
-use std::net::SocketAddr;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
 
 use bevy::{
     app::{PluginGroup, PluginGroupBuilder},
@@ -22,8 +25,13 @@ use froglight::{
 pub use taskpool::TASKPOOL_SETTINGS;
 
 use crate::{
-    network::{LoginPlugin, SocketPlugin},
-    DimensionPlugin, EntityPlugins, NetworkPlugins, PlayerPlugins, WorldPlugins,
+    network::{
+        common::ChannelSettings,
+        forwarding::PlayerForwarding,
+        socket::{Motd, ProxyProtocol, RateLimits},
+        LoginPlugin, NetworkDiagnosticsPlugin, RconPlugin, SocketPlugin,
+    },
+    DimensionPlugin, EntityPlugins, NetworkPlugins, PlayerPlugins, ShutdownPlugin, WorldPlugins,
 };
 
 mod ready;
@@ -80,12 +88,45 @@ use ready::ReadyPlugin;
 /// - [`WorldPlugins`]
 /// - [`EntityPlugins`]
 /// - [`NetworkPlugins`]
+/// - [`NetworkDiagnosticsPlugin`]
+/// - [`PlayerPlugins`]
+/// - [`ShutdownPlugin`]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ServerPlugins {
     /// The address the server will bind to.
     pub socket: Option<SocketAddr>,
+    /// Any additional addresses the server will bind to.
+    pub extra_sockets: Vec<SocketAddr>,
+    /// The paths of Unix domain sockets the server will bind to.
+    pub unix: Vec<PathBuf>,
+    /// Whether to accept connections from the in-memory transport.
+    pub memory: bool,
     /// The address of the authentication server.
     pub auth_server: Option<CompactString>,
+    /// The minimum size of a packet before it is compressed.
+    pub compression_threshold: Option<i32>,
+    /// Whether clients transferred from another server are accepted.
+    ///
+    /// Disabled by default, like vanilla servers.
+    pub accept_transfers: bool,
+    /// The message of the day shown in the server list.
+    pub motd: Option<Motd>,
+    /// The path to the server icon.
+    pub favicon: Option<PathBuf>,
+    /// Settings for accepting connections using the `PROXY` protocol.
+    pub proxy: Option<ProxyProtocol>,
+    /// The [`RateLimits`] for incoming connections.
+    pub rate_limits: Option<RateLimits>,
+    /// How player information is forwarded by a proxy.
+    pub forwarding: Option<PlayerForwarding>,
+    /// The path to record a packet capture to.
+    pub capture: Option<PathBuf>,
+    /// The UDP socket to answer query requests on.
+    pub query: Option<SocketAddr>,
+    /// Settings for accepting RCON connections.
+    pub rcon: Option<RconPlugin>,
+    /// The [`ChannelSettings`] for new connections.
+    pub channels: Option<ChannelSettings>,
 }
 
 impl ServerPlugins {
@@ -103,6 +144,12 @@ impl ServerPlugins {
     #[must_use]
     pub const fn public() -> Self { Self::from_socket(SocketPlugin::<V1_21_0>::PUBLIC) }
 
+    /// Create a new [`ServerPlugins`] that listens on `0.0.0.0` and `[::]`.
+    #[must_use]
+    pub fn dual_stack() -> Self {
+        Self::public().with_sockets([SocketPlugin::<V1_21_0>::PUBLIC_V6])
+    }
+
     /// Create a new [`ServerPlugins`] that listens on the given socket.
     #[must_use]
     pub const fn from_socket(socket: SocketAddr) -> Self { Self::from_option(Some(socket)) }
@@ -111,7 +158,31 @@ impl ServerPlugins {
     /// if it is [`Some`].
     #[must_use]
     pub const fn from_option(socket: Option<SocketAddr>) -> Self {
-        Self { socket, auth_server: None }
+        Self {
+            socket,
+            extra_sockets: Vec::new(),
+            unix: Vec::new(),
+            memory: false,
+            auth_server: None,
+            compression_threshold: None,
+            accept_transfers: false,
+            motd: None,
+            favicon: None,
+            proxy: None,
+            rate_limits: None,
+            forwarding: None,
+            capture: None,
+            query: None,
+            rcon: None,
+            channels: None,
+        }
+    }
+
+    /// Create a new [`ServerPlugins`] that listens on all of the given sockets.
+    #[must_use]
+    pub fn from_sockets(sockets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        let mut sockets = sockets.into_iter();
+        Self::from_option(sockets.next()).with_sockets(sockets)
     }
 
     /// Set the [`SocketAddr`] for the server.
@@ -133,6 +204,35 @@ impl ServerPlugins {
         }
     }
 
+    /// Add another [`SocketAddr`] for the server.
+    pub fn add_socket(&mut self, socket: SocketAddr) { self.extra_sockets.push(socket); }
+
+    /// Add more [`SocketAddr`]s for the server.
+    #[must_use]
+    pub fn with_sockets(mut self, sockets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.extra_sockets.extend(sockets);
+        self
+    }
+
+    /// Add a Unix domain socket for the server.
+    ///
+    /// Useful for a proxy running on the same machine.
+    #[must_use]
+    pub fn with_unix(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix.push(path.into());
+        self
+    }
+
+    /// Accept connections from the in-memory transport.
+    ///
+    /// Clients in the same process can connect
+    /// using the [`MemoryListener`](crate::network::socket::MemoryListener).
+    #[must_use]
+    pub const fn with_memory(mut self) -> Self {
+        self.memory = true;
+        self
+    }
+
     /// Remove the authentication server.
     #[must_use]
     pub fn offline(mut self) -> Self {
@@ -160,6 +260,105 @@ impl ServerPlugins {
             self
         }
     }
+
+    /// Compress packets larger than the given threshold.
+    #[must_use]
+    pub const fn with_compression(mut self, threshold: i32) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Disable packet compression.
+    #[must_use]
+    pub const fn without_compression(mut self) -> Self {
+        self.compression_threshold = None;
+        self
+    }
+
+    /// Set whether clients transferred from another server are accepted.
+    #[must_use]
+    pub const fn with_transfers(mut self, accept: bool) -> Self {
+        self.accept_transfers = accept;
+        self
+    }
+
+    /// Set the message of the day shown in the server list.
+    #[must_use]
+    pub fn with_motd(mut self, motd: impl Into<Motd>) -> Self {
+        self.motd = Some(motd.into());
+        self
+    }
+
+    /// Set the path to the server icon.
+    #[must_use]
+    pub fn with_favicon(mut self, path: impl Into<PathBuf>) -> Self {
+        self.favicon = Some(path.into());
+        self
+    }
+
+    /// Accept connections using the `PROXY` protocol
+    /// from the given trusted addresses.
+    #[must_use]
+    pub fn with_proxy(mut self, trusted: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.proxy = Some(ProxyProtocol::new(trusted));
+        self
+    }
+
+    /// Rate limit incoming connections using the given [`RateLimits`].
+    #[must_use]
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limits = Some(limits);
+        self
+    }
+
+    /// Trust player information forwarded by a Velocity proxy
+    /// using the given secret.
+    #[must_use]
+    pub fn with_velocity(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.forwarding = Some(PlayerForwarding::velocity(secret));
+        self
+    }
+
+    /// Trust player information forwarded by a BungeeCord proxy.
+    #[must_use]
+    pub fn with_bungeecord(mut self) -> Self {
+        self.forwarding = Some(PlayerForwarding::BungeeCord);
+        self
+    }
+
+    /// Record every packet sent and received by players to the given path.
+    #[must_use]
+    pub fn with_capture(mut self, path: impl Into<PathBuf>) -> Self {
+        self.capture = Some(path.into());
+        self
+    }
+
+    /// Answer query requests on the given UDP socket.
+    ///
+    /// Used by server lists and monitoring tools,
+    /// like `enable-query` in vanilla servers.
+    #[must_use]
+    pub const fn with_query(mut self, socket: SocketAddr) -> Self {
+        self.query = Some(socket);
+        self
+    }
+
+    /// Accept RCON connections on the given socket using the given password.
+    #[must_use]
+    pub fn with_rcon(mut self, socket: SocketAddr, password: impl Into<CompactString>) -> Self {
+        self.rcon = Some(RconPlugin::new(socket, password));
+        self
+    }
+
+    /// Use the given [`ChannelSettings`] for new connections.
+    ///
+    /// Controls how many packets are queued for each client,
+    /// and how long slow clients are kept before they are disconnected.
+    #[must_use]
+    pub const fn with_channels(mut self, channels: ChannelSettings) -> Self {
+        self.channels = Some(channels);
+        self
+    }
 }
 
 impl Default for ServerPlugins {
@@ -189,9 +388,29 @@ impl PluginGroup for ServerPlugins {
         builder = builder.add_group(EntityPlugins);
 
         // Add the v1.21.0 `NetworkPlugins`.
-        builder = builder.add_group(NetworkPlugins::<V1_21_0>::from_option(self.socket));
+        let mut network = NetworkPlugins::<V1_21_0>::from_option(self.socket);
+        network.extra_sockets = self.extra_sockets;
+        network.unix = self.unix;
+        network.memory = self.memory;
+        network.auth_server = self.auth_server;
+        network.compression_threshold = self.compression_threshold;
+        network.accept_transfers = self.accept_transfers;
+        network.motd = self.motd;
+        network.favicon = self.favicon;
+        network.proxy = self.proxy;
+        network.rate_limits = self.rate_limits;
+        network.forwarding = self.forwarding;
+        network.capture = self.capture;
+        network.query = self.query;
+        network.rcon = self.rcon;
+        network.channels = self.channels;
+        builder = builder.add_group(network);
+        // Add the `NetworkDiagnosticsPlugin`.
+        builder = builder.add(NetworkDiagnosticsPlugin);
         // Add the v1.21.0 `PlayerPlugins`.
         builder = builder.add_group(PlayerPlugins::<V1_21_0>::default());
+        // Add the `ShutdownPlugin`.
+        builder = builder.add(ShutdownPlugin::default());
 
         builder
     }
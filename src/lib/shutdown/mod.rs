@@ -0,0 +1,330 @@
+//! A graceful shutdown sequence for the server.
+//!
+//! When an [`AppExit`] event is sent, the [`ShutdownPlugin`]
+//! holds it back and instead:
+//! 1. Stops accepting new connections.
+//! 2. Disconnects every client with the [`ShutdownSettings::message`].
+//! 3. Waits for all [`ShutdownTasks`] to send their remaining packets.
+//! 4. Runs the [`OnShutdown`] schedule and waits for any new [`ShutdownTasks`].
+//! 5. Sends the original [`AppExit`] event.
+//!
+//! The whole sequence, including the [`OnShutdown`] schedule and any
+//! [`ShutdownTasks`] it adds, is bounded by the [`ShutdownSettings::timeout`].
+
+use std::time::{Duration, Instant};
+
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, tasks::Task};
+use compact_str::CompactString;
+
+/// A [`Plugin`] that gracefully shuts down the server.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShutdownPlugin {
+    /// The message sent to clients when the server shuts down.
+    pub message: CompactString,
+    /// The maximum time the whole shutdown can take.
+    pub timeout: Duration,
+}
+
+impl Default for ShutdownPlugin {
+    fn default() -> Self { Self::new(ShutdownSettings::DEFAULT_MESSAGE) }
+}
+
+impl ShutdownPlugin {
+    /// Create a new [`ShutdownPlugin`] with the given message.
+    #[must_use]
+    pub fn new(message: impl Into<CompactString>) -> Self {
+        Self { message: message.into(), timeout: ShutdownSettings::DEFAULT_TIMEOUT }
+    }
+
+    /// Set the maximum time the whole shutdown can take.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShutdownSettings {
+            message: self.message.clone(),
+            timeout: self.timeout,
+        });
+        app.init_resource::<ShutdownState>();
+        app.init_resource::<ShutdownTasks>();
+        app.init_schedule(OnShutdown);
+
+        app.configure_sets(
+            Last,
+            (
+                ShutdownSystemSet::Intercept,
+                ShutdownSystemSet::Disconnect,
+                ShutdownSystemSet::Advance,
+            )
+                .chain(),
+        );
+
+        // Add systems
+        app.add_systems(
+            Last,
+            (
+                ShutdownState::intercept_exit.in_set(ShutdownSystemSet::Intercept),
+                ShutdownState::advance.in_set(ShutdownSystemSet::Advance),
+                ShutdownTasks::prune_tasks
+                    .run_if(ShutdownState::is_running)
+                    .in_set(ShutdownSystemSet::Advance),
+            ),
+        );
+    }
+}
+
+/// A [`ScheduleLabel`] for systems that run while the server is shutting down,
+/// after all clients have been disconnected.
+///
+/// Long-running work, such as saving the world,
+/// should be spawned as a [`Task`] and added to the [`ShutdownTasks`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, ScheduleLabel)]
+pub struct OnShutdown;
+
+/// The [`SystemSet`]s the shutdown sequence runs in, in the [`Last`] schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub enum ShutdownSystemSet {
+    /// Catches [`AppExit`] events and starts the shutdown.
+    Intercept,
+    /// Stops listening and disconnects all clients.
+    Disconnect,
+    /// Moves on to the next [`ShutdownPhase`].
+    Advance,
+}
+
+/// The settings used when shutting down.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
+pub struct ShutdownSettings {
+    /// The message sent to clients when the server shuts down.
+    pub message: CompactString,
+    /// The maximum time the whole shutdown can take.
+    ///
+    /// Once it has passed, any remaining [`ShutdownTasks`] are cancelled.
+    pub timeout: Duration,
+}
+
+impl ShutdownSettings {
+    /// The default message sent to clients.
+    pub const DEFAULT_MESSAGE: &'static str = "Server closed";
+    /// The default maximum time the whole shutdown can take.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+}
+
+/// A step of the shutdown sequence.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShutdownPhase {
+    /// The server is running normally.
+    #[default]
+    Running,
+    /// The server is disconnecting all clients.
+    Disconnecting,
+    /// The server is waiting for packets to be sent.
+    Flushing,
+    /// The server is waiting for [`OnShutdown`] tasks.
+    Saving,
+    /// The server is exiting.
+    Exiting,
+}
+
+/// The current state of the shutdown sequence.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct ShutdownState {
+    phase: ShutdownPhase,
+    exit: Option<AppExit>,
+    started: Option<Instant>,
+}
+
+impl ShutdownState {
+    /// The current [`ShutdownPhase`].
+    #[must_use]
+    pub const fn phase(&self) -> ShutdownPhase { self.phase }
+
+    /// A run condition that returns `true` while the server is running.
+    #[must_use]
+    pub fn is_running(state: Option<Res<Self>>) -> bool {
+        state.is_none_or(|state| state.phase == ShutdownPhase::Running)
+    }
+
+    /// A run condition that returns `true` while clients are being
+    /// disconnected.
+    #[must_use]
+    pub fn is_disconnecting(state: Option<Res<Self>>) -> bool {
+        state.is_some_and(|state| state.phase == ShutdownPhase::Disconnecting)
+    }
+
+    /// The time since the shutdown started.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.started.map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    /// Move to the given [`ShutdownPhase`].
+    fn enter(&mut self, phase: ShutdownPhase) {
+        debug!("Shutdown: {:?} -> {phase:?} after {:?}", self.phase, self.elapsed());
+        self.phase = phase;
+    }
+
+    /// A system that holds back [`AppExit`] events
+    /// and starts the shutdown sequence.
+    ///
+    /// Any [`AppExit`] events sent during the shutdown are ignored.
+    pub fn intercept_exit(mut events: ResMut<Events<AppExit>>, mut state: ResMut<Self>) {
+        match state.phase {
+            ShutdownPhase::Exiting => {}
+            ShutdownPhase::Running => {
+                if let Some(exit) = events.drain().last() {
+                    info!("Stopping the server...");
+                    state.exit = Some(exit);
+                    state.started = Some(Instant::now());
+                    state.enter(ShutdownPhase::Disconnecting);
+                }
+            }
+            _ => events.clear(),
+        }
+    }
+
+    /// A system that moves the shutdown sequence to the next
+    /// [`ShutdownPhase`] once all [`ShutdownTasks`] are done.
+    ///
+    /// The time spent in every phase, including running the [`OnShutdown`]
+    /// schedule, counts towards the [`ShutdownSettings::timeout`].
+    pub fn advance(world: &mut World) {
+        let state = world.resource::<Self>();
+        let (phase, elapsed) = (state.phase, state.elapsed());
+        let timeout = world.resource::<ShutdownSettings>().timeout;
+
+        match phase {
+            ShutdownPhase::Running | ShutdownPhase::Exiting => {}
+            ShutdownPhase::Disconnecting => {
+                world.resource_mut::<Self>().enter(ShutdownPhase::Flushing);
+            }
+            ShutdownPhase::Flushing | ShutdownPhase::Saving => {
+                if !world.resource_mut::<ShutdownTasks>().is_done(elapsed, timeout) {
+                    return;
+                }
+
+                if phase == ShutdownPhase::Flushing {
+                    world.resource_mut::<Self>().enter(ShutdownPhase::Saving);
+                    let _ = world.try_run_schedule(OnShutdown);
+                } else {
+                    info!("Server stopped");
+                    let mut state = world.resource_mut::<Self>();
+                    state.enter(ShutdownPhase::Exiting);
+                    let exit = state.exit.take().unwrap_or_default();
+                    world.send_event(exit);
+                }
+            }
+        }
+    }
+}
+
+/// Background [`Task`]s that must finish before the server exits.
+///
+/// Disconnecting clients add a task that finishes
+/// once their remaining packets have been sent.
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct ShutdownTasks(Vec<Task<()>>);
+
+impl ShutdownTasks {
+    /// A system that removes finished tasks.
+    pub fn prune_tasks(mut tasks: ResMut<Self>) { tasks.retain(|task| !task.is_finished()); }
+
+    /// Remove finished tasks and return `true` if none remain,
+    /// or if the timeout has passed.
+    fn is_done(&mut self, elapsed: Duration, timeout: Duration) -> bool {
+        self.retain(|task| !task.is_finished());
+
+        if self.is_empty() {
+            true
+        } else if elapsed >= timeout {
+            // Dropping the remaining tasks cancels them
+            warn!("Shutdown timed out waiting for {} tasks", self.len());
+            self.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::{prelude::*, tasks::IoTaskPool};
+    use froglight::network::versions::v1_21_0::{play::PlayClientboundPackets, V1_21_0};
+
+    use super::{OnShutdown, ShutdownPhase, ShutdownSettings, ShutdownState, ShutdownTasks};
+    use crate::{
+        network::play::PlayTask,
+        testing::{TestClient, TestServer},
+    };
+
+    const TIMEOUT: Duration = Duration::from_millis(250);
+
+    /// Add a task that never finishes.
+    fn add_stuck_task(mut tasks: ResMut<ShutdownTasks>) {
+        tasks.push(IoTaskPool::get().spawn(std::future::pending()));
+    }
+
+    /// Block for longer than the whole timeout.
+    fn slow_system() { std::thread::sleep(TIMEOUT * 2); }
+
+    /// Update the server until it is ready to exit.
+    fn shutdown(server: &mut TestServer) {
+        server.app().world_mut().send_event(AppExit::Success);
+        assert!(server.update_until(|world| {
+            world.resource::<ShutdownState>().phase() == ShutdownPhase::Exiting
+        }));
+    }
+
+    /// Clients are sent the shutdown message before the server exits.
+    #[test]
+    fn disconnect_clients() {
+        let mut server = TestServer::new();
+        let client = server.spawn_client(|address| async move {
+            let mut client = TestClient::new(address, "Player");
+            let mut conn = client.join().await?;
+            while !matches!(
+                client.recv_play(&mut conn).await?,
+                PlayClientboundPackets::Disconnect(..)
+            ) {}
+            Ok::<_, froglight::prelude::ConnectionError>(client)
+        });
+        assert!(server.update_until(|world| {
+            world.query::<&PlayTask<V1_21_0>>().iter(world).count() == 1
+        }));
+
+        shutdown(&mut server);
+        let client = server.run_client(client).unwrap();
+
+        let Some(PlayClientboundPackets::Disconnect(packet)) = client.received.play.last() else {
+            panic!("Expected a disconnect packet");
+        };
+        let message = &server.world().resource::<ShutdownSettings>().message;
+        assert_eq!(packet.reason, message.as_str().into());
+    }
+
+    /// Time spent in [`OnShutdown`] counts towards the timeout.
+    #[test]
+    fn bounded_shutdown() {
+        let mut server = TestServer::new();
+        server.app().world_mut().resource_mut::<ShutdownSettings>().timeout = TIMEOUT;
+        server.app().add_systems(OnShutdown, (slow_system, add_stuck_task).chain());
+
+        let start = Instant::now();
+        shutdown(&mut server);
+
+        // The stuck task was cancelled as soon as it was checked,
+        // instead of being given a full timeout of its own
+        let elapsed = start.elapsed();
+        assert!(elapsed < TIMEOUT * 3, "Shutdown took {elapsed:?}");
+        assert!(server.world().resource::<ShutdownTasks>().is_empty());
+        assert!(server.world().resource::<ShutdownState>().elapsed() >= TIMEOUT);
+    }
+}
@@ -0,0 +1,244 @@
+use std::{io::Error as IoError, net::SocketAddr};
+
+use async_std::net::TcpStream;
+use compact_str::CompactString;
+use froglight::{
+    network::{
+        connection::AccountInformation,
+        versions::v1_21_0::{
+            configuration::{ConfigurationClientboundPackets, ReadyC2SPacket},
+            handshake::HandshakePacket,
+            login::{
+                EnterConfigurationPacket, LoginClientboundPackets, LoginHelloC2SPacket,
+                LoginHelloS2CPacket, LoginKeyC2SPacket,
+            },
+            play::PlayClientboundPackets,
+            status::{QueryRequestPacket, StatusClientboundPackets},
+            V1_21_0,
+        },
+    },
+    prelude::*,
+};
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+
+use crate::network::socket::{MemoryListener, SocketPlugin};
+
+/// How a [`TestClient`] connects to the server.
+#[derive(Debug, Clone)]
+pub enum ServerAddress {
+    /// Connect using TCP.
+    Tcp(SocketAddr),
+    /// Connect using the in-memory transport.
+    Memory(MemoryListener),
+}
+
+impl From<SocketAddr> for ServerAddress {
+    fn from(socket: SocketAddr) -> Self { Self::Tcp(socket) }
+}
+
+impl From<MemoryListener> for ServerAddress {
+    fn from(listener: MemoryListener) -> Self { Self::Memory(listener) }
+}
+
+/// The packets a [`TestClient`] received in each [`State`].
+#[derive(Debug, Default, Clone)]
+pub struct ReceivedPackets {
+    /// The packets received while logging in.
+    pub login: Vec<LoginClientboundPackets>,
+    /// The packets received while configuring.
+    pub configuration: Vec<ConfigurationClientboundPackets>,
+    /// The packets received while playing.
+    pub play: Vec<PlayClientboundPackets>,
+}
+
+/// A scripted client that connects to a [`TestServer`](super::TestServer).
+///
+/// Every packet the client receives is kept in [`TestClient::received`].
+#[derive(Debug, Clone)]
+pub struct TestClient {
+    /// The address of the server.
+    pub address: ServerAddress,
+    /// The username the client logs in with.
+    pub username: CompactString,
+    /// The packets the client has received.
+    pub received: ReceivedPackets,
+}
+
+impl TestClient {
+    /// Create a new [`TestClient`] for the server at the given address.
+    #[must_use]
+    pub fn new(address: impl Into<ServerAddress>, username: impl Into<CompactString>) -> Self {
+        Self {
+            address: address.into(),
+            username: username.into(),
+            received: ReceivedPackets::default(),
+        }
+    }
+
+    /// Connect to the server and send a handshake with the given intent.
+    ///
+    /// # Errors
+    /// Returns an error if the client could not connect.
+    pub async fn connect(
+        &self,
+        intent: ConnectionIntent,
+    ) -> Result<Connection<V1_21_0, Handshake, Serverbound>, ConnectionError> {
+        let (mut conn, socket) = match &self.address {
+            ServerAddress::Tcp(socket) => {
+                let stream = TcpStream::connect(socket).await?;
+                (Connection::from_async_stream(stream)?, *socket)
+            }
+            ServerAddress::Memory(listener) => {
+                let stream = listener.connect()?;
+                (Connection::from_async_stream(stream)?, SocketPlugin::<V1_21_0>::LOCALHOST)
+            }
+        };
+
+        conn.send(HandshakePacket {
+            protocol: V1_21_0::ID,
+            address: socket.ip().to_string().into(),
+            port: socket.port(),
+            intent,
+        })
+        .await?;
+
+        Ok(conn)
+    }
+
+    /// Request the server's status.
+    ///
+    /// # Errors
+    /// Returns an error if the status could not be received.
+    pub async fn status(&self) -> Result<ServerStatus, ConnectionError> {
+        let mut conn = self.connect(ConnectionIntent::Status).await?.status();
+        conn.send(QueryRequestPacket).await?;
+
+        match conn.recv().await? {
+            StatusClientboundPackets::QueryResponse(response) => Ok(response.status),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    /// Log in to the server.
+    ///
+    /// Returns once the login succeeds and the client enters configuration.
+    ///
+    /// If the server requests encryption, the connection is encrypted.
+    /// The client does not contact a session server, so the server should
+    /// use a [`TestSession`](super::TestSession) to verify clients.
+    ///
+    /// # Errors
+    /// Returns an error if the client is disconnected or the login fails.
+    pub async fn login(
+        &mut self,
+    ) -> Result<Connection<V1_21_0, Configuration, Serverbound>, ConnectionError> {
+        let mut conn = self.connect(ConnectionIntent::Login).await?.login();
+        conn.send(LoginHelloC2SPacket {
+            username: self.username.clone(),
+            uuid: AccountInformation::offline_uuid(&self.username),
+        })
+        .await?;
+
+        loop {
+            let packet = conn.recv().await?;
+            self.received.login.push(packet.clone());
+
+            match packet {
+                LoginClientboundPackets::LoginCompression(compression) => {
+                    conn.set_compression(Some(compression.compression_threshold));
+                }
+                LoginClientboundPackets::LoginSuccess(..) => {
+                    conn.send(EnterConfigurationPacket).await?;
+                    return Ok(conn.configuration());
+                }
+                LoginClientboundPackets::LoginHello(request) => {
+                    Self::encrypt(&mut conn, &request, &request.nonce).await?;
+                }
+                LoginClientboundPackets::LoginDisconnect(..) => return Err(unexpected(&packet)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Answer an encryption request and enable encryption.
+    ///
+    /// The given verify token is sent back to the server,
+    /// which should be the token from the request.
+    ///
+    /// # Errors
+    /// Returns an error if the public key is invalid or the response fails to
+    /// send.
+    pub async fn encrypt(
+        conn: &mut Connection<V1_21_0, Login, Serverbound>,
+        request: &LoginHelloS2CPacket,
+        token: &[u8],
+    ) -> Result<(), ConnectionError> {
+        let key = RsaPublicKey::from_public_key_der(&request.public_key).map_err(IoError::other)?;
+
+        let mut rng = rand::thread_rng();
+        let secret: [u8; 16] = rand::random();
+        let encrypted_secret_key =
+            key.encrypt(&mut rng, Pkcs1v15Encrypt, &secret).map_err(IoError::other)?;
+        let nonce = key.encrypt(&mut rng, Pkcs1v15Encrypt, token).map_err(IoError::other)?;
+
+        conn.send(LoginKeyC2SPacket { encrypted_secret_key, nonce }).await?;
+        conn.enable_encryption(&secret);
+        Ok(())
+    }
+
+    /// Configure the client.
+    ///
+    /// Returns once the server finishes the configuration.
+    ///
+    /// # Errors
+    /// Returns an error if the client is disconnected.
+    pub async fn configure(
+        &mut self,
+        mut conn: Connection<V1_21_0, Configuration, Serverbound>,
+    ) -> Result<Connection<V1_21_0, Play, Serverbound>, ConnectionError> {
+        loop {
+            let packet = conn.recv().await?;
+            self.received.configuration.push(packet.clone());
+
+            match packet {
+                ConfigurationClientboundPackets::Ready(..) => {
+                    conn.send(ReadyC2SPacket).await?;
+                    return Ok(conn.play());
+                }
+                ConfigurationClientboundPackets::Disconnect(..) => {
+                    return Err(unexpected(&packet));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Log in to the server and configure the client.
+    ///
+    /// # Errors
+    /// Returns an error if the client is disconnected.
+    pub async fn join(
+        &mut self,
+    ) -> Result<Connection<V1_21_0, Play, Serverbound>, ConnectionError> {
+        let conn = self.login().await?;
+        self.configure(conn).await
+    }
+
+    /// Receive a packet while playing.
+    ///
+    /// # Errors
+    /// Returns an error if the client is disconnected.
+    pub async fn recv_play(
+        &mut self,
+        conn: &mut Connection<V1_21_0, Play, Serverbound>,
+    ) -> Result<PlayClientboundPackets, ConnectionError> {
+        let packet = conn.recv().await?;
+        self.received.play.push(packet.clone());
+        Ok(packet)
+    }
+}
+
+/// Create a [`ConnectionError`] for a packet the client did not expect.
+fn unexpected(packet: &impl std::fmt::Debug) -> ConnectionError {
+    IoError::other(format!("Unexpected packet: {packet:?}")).into()
+}
@@ -0,0 +1,283 @@
+//! Utilities for testing the server with scripted clients.
+//!
+//! Requires the `testing` feature.
+
+use std::{
+    future::Future,
+    net::{Ipv4Addr, TcpListener},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{PluginGroup, PluginsState},
+    log::LogPlugin,
+    prelude::*,
+    tasks::{block_on, tick_global_task_pools_on_main_thread},
+};
+
+mod client;
+pub use client::{ReceivedPackets, ServerAddress, TestClient};
+
+mod session;
+pub use session::TestSession;
+
+use crate::{network::socket::MemoryListener, ServerPlugins};
+
+/// A server that runs in the current thread.
+///
+/// By default clients connect using the in-memory transport,
+/// so no ports are used. Use [`TestServer::tcp`] to
+/// listen on a free port on `127.0.0.1` instead.
+///
+/// Clients are run on their own threads while the server updates.
+pub struct TestServer {
+    app: App,
+    address: ServerAddress,
+}
+
+impl TestServer {
+    /// How long a client can run before the test fails.
+    pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Create a new [`TestServer`] using the default [`ServerPlugins`].
+    #[must_use]
+    pub fn new() -> Self { Self::from_plugins(ServerPlugins::localhost()) }
+
+    /// Create a new [`TestServer`] using the given [`ServerPlugins`].
+    ///
+    /// The sockets are replaced with the in-memory transport,
+    /// and the [`LogPlugin`] is disabled.
+    #[must_use]
+    pub fn from_plugins(mut plugins: ServerPlugins) -> Self {
+        plugins.socket = None;
+        plugins.extra_sockets.clear();
+        plugins.unix.clear();
+        plugins.memory = true;
+
+        let app = Self::build(plugins);
+        let address = app.world().resource::<MemoryListener>().clone().into();
+        Self { app, address }
+    }
+
+    /// Create a new [`TestServer`] that listens
+    /// on a free port using the default [`ServerPlugins`].
+    ///
+    /// # Panics
+    /// Panics if no free port is available.
+    #[must_use]
+    pub fn tcp() -> Self { Self::tcp_from_plugins(ServerPlugins::localhost()) }
+
+    /// Create a new [`TestServer`] that listens
+    /// on a free port using the given [`ServerPlugins`].
+    ///
+    /// The sockets are replaced with a free port on `127.0.0.1`,
+    /// and the [`LogPlugin`] is disabled.
+    ///
+    /// # Panics
+    /// Panics if no free port is available.
+    #[must_use]
+    pub fn tcp_from_plugins(mut plugins: ServerPlugins) -> Self {
+        let socket = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to find a free port");
+        plugins.socket = Some(socket);
+        plugins.extra_sockets.clear();
+        plugins.unix.clear();
+        plugins.memory = false;
+
+        Self { app: Self::build(plugins), address: socket.into() }
+    }
+
+    /// Build and finish an [`App`] using the given [`ServerPlugins`].
+    fn build(plugins: ServerPlugins) -> App {
+        let mut app = App::new();
+        app.add_plugins(plugins.build().disable::<LogPlugin>());
+
+        // Finish adding plugins, as `App::run` would
+        while app.plugins_state() == PluginsState::Adding {
+            tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+
+        app
+    }
+
+    /// The address clients connect to.
+    #[must_use]
+    pub const fn address(&self) -> &ServerAddress { &self.address }
+
+    /// Get the server's [`App`].
+    #[must_use]
+    pub fn app(&mut self) -> &mut App { &mut self.app }
+
+    /// Get the server's [`World`].
+    #[must_use]
+    pub fn world(&self) -> &World { self.app.world() }
+
+    /// Update the server once.
+    pub fn update(&mut self) { self.app.update(); }
+
+    /// Update the server until the condition returns `true`.
+    ///
+    /// Returns `false` if [`TestServer::TIMEOUT`] is reached first.
+    pub fn update_until(&mut self, mut condition: impl FnMut(&mut World) -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Self::TIMEOUT {
+            self.update();
+            if condition(self.app.world_mut()) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        false
+    }
+
+    /// Run a client script on another thread.
+    ///
+    /// The script is given the address of the server.
+    pub fn spawn_client<F>(
+        &self,
+        script: impl FnOnce(ServerAddress) -> F,
+    ) -> ClientHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let future = script(self.address.clone());
+        ClientHandle(std::thread::spawn(move || block_on(future)))
+    }
+
+    /// Update the server until the client finishes and return its output.
+    ///
+    /// # Panics
+    /// Panics if the client panics or does not
+    /// finish before [`TestServer::TIMEOUT`].
+    pub fn run_client<T>(&mut self, client: ClientHandle<T>) -> T {
+        assert!(self.update_until(|_| client.is_finished()), "Client did not finish in time");
+        client.0.join().expect("Client panicked")
+    }
+
+    /// Run a client script and return its output.
+    ///
+    /// See [`TestServer::spawn_client`] and [`TestServer::run_client`].
+    pub fn run<F>(&mut self, script: impl FnOnce(ServerAddress) -> F) -> F::Output
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let client = self.spawn_client(script);
+        self.run_client(client)
+    }
+}
+
+impl Default for TestServer {
+    fn default() -> Self { Self::new() }
+}
+
+/// A client script running on another thread.
+#[derive(Debug)]
+pub struct ClientHandle<T>(JoinHandle<T>);
+
+impl<T> ClientHandle<T> {
+    /// Returns `true` if the client has finished.
+    #[must_use]
+    pub fn is_finished(&self) -> bool { self.0.is_finished() }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use froglight::{
+        network::versions::v1_21_0::{
+            configuration::ConfigurationClientboundPackets, login::LoginClientboundPackets, V1_21_0,
+        },
+        prelude::*,
+    };
+
+    use super::{TestClient, TestServer};
+    use crate::network::{
+        common::{NetworkStats, StatsState},
+        play::PlayTask,
+    };
+
+    #[test]
+    fn status() {
+        let mut server = TestServer::new();
+        let status =
+            server.run(|address| async move { TestClient::new(address, "").status().await });
+
+        let status = status.unwrap();
+        assert_eq!(status.version.protocol, V1_21_0::ID);
+    }
+
+    #[test]
+    fn tcp_status() {
+        let mut server = TestServer::tcp();
+        let status =
+            server.run(|address| async move { TestClient::new(address, "").status().await });
+
+        assert_eq!(status.unwrap().version.protocol, V1_21_0::ID);
+    }
+
+    #[test]
+    fn login_to_play() {
+        let mut server = TestServer::new();
+        let (done, wait) = async_channel::bounded::<()>(1);
+        let client = server.spawn_client(|address| async move {
+            let mut client = TestClient::new(address, "Player");
+            let conn = client.join().await;
+
+            // Stay connected until the test is done
+            let _ = wait.recv().await;
+            (client, conn.map(drop))
+        });
+
+        // Wait for the server to start playing
+        assert!(server.update_until(|world| {
+            world.query_filtered::<(), With<PlayTask<V1_21_0>>>().iter(world).count() == 1
+        }));
+
+        // The server counted the packets
+        let world = server.app().world_mut();
+        let mut query = world.query::<&NetworkStats>();
+        let stats = query.single(world);
+        assert!(stats.get(StatsState::Login).packets_received >= 1);
+        assert!(stats.get(StatsState::Configuration).packets_received >= 1);
+
+        done.send_blocking(()).unwrap();
+        let (client, result) = server.run_client(client);
+        result.unwrap();
+
+        // The login succeeds with the client's username
+        let Some(LoginClientboundPackets::LoginSuccess(success)) = client.received.login.last()
+        else {
+            panic!("Expected a login success, got {:?}", client.received.login);
+        };
+        assert_eq!(success.profile.username, "Player");
+
+        // The configuration finishes
+        assert!(matches!(
+            client.received.configuration.last(),
+            Some(ConfigurationClientboundPackets::Ready(..))
+        ));
+    }
+
+    #[test]
+    fn multiple_clients() {
+        let mut server = TestServer::new();
+        let clients: Vec<_> = ["Alice", "Bob", "Carol"]
+            .into_iter()
+            .map(|username| {
+                server.spawn_client(move |address| async move {
+                    TestClient::new(address, username).join().await.map(drop)
+                })
+            })
+            .collect();
+
+        for client in clients {
+            server.run_client(client).unwrap();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use bevy::utils::BoxedFuture;
+use froglight::prelude::GameProfile;
+use parking_lot::Mutex;
+
+use crate::network::login::{SessionClient, SessionError};
+
+/// A [`SessionClient`] that answers requests without a session server.
+///
+/// Every requested url is kept, see [`TestSession::requests`].
+#[derive(Debug, Default, Clone)]
+pub struct TestSession {
+    profile: Option<Arc<GameProfile>>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestSession {
+    /// Create a new [`TestSession`] that verifies every client
+    /// as the given [`GameProfile`].
+    #[must_use]
+    pub fn accept(profile: GameProfile) -> Self {
+        Self { profile: Some(Arc::new(profile)), requests: Arc::default() }
+    }
+
+    /// Create a new [`TestSession`] that does not verify any clients.
+    #[must_use]
+    pub fn reject() -> Self { Self::default() }
+
+    /// The urls requested so far.
+    #[must_use]
+    pub fn requests(&self) -> Vec<String> { self.requests.lock().clone() }
+}
+
+impl SessionClient for TestSession {
+    fn get(&self, url: String) -> BoxedFuture<'static, Result<Option<String>, SessionError>> {
+        self.requests.lock().push(url);
+
+        let body = self.profile.as_ref().map(|profile| {
+            serde_json::json!({
+                "id": profile.uuid.to_string(),
+                "name": profile.username,
+                "properties": [],
+            })
+            .to_string()
+        });
+        Box::pin(async move { Ok(body) })
+    }
+}
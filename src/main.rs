@@ -1,10 +1,15 @@
 //! TODO
 
 use bevy::prelude::*;
-use froglight_server::ServerPlugins;
+use froglight::network::versions::v1_21_0::V1_21_0;
+use froglight_server::{player::access::PlayerAccessPlugin, ServerPlugins};
 
 #[cfg(feature = "mimalloc")]
 #[cfg_attr(feature = "mimalloc", global_allocator)]
 static GLOBAL: froglight_server::MiMalloc = froglight_server::MiMalloc;
 
-fn main() -> AppExit { App::new().add_plugins(ServerPlugins::localhost()).run() }
+fn main() -> AppExit {
+    // Store the access lists in the working directory, like a vanilla server
+    let plugins = ServerPlugins::localhost().set(PlayerAccessPlugin::<V1_21_0>::new("."));
+    App::new().add_plugins(plugins).run()
+}